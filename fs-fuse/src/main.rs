@@ -43,6 +43,12 @@ fn easy_fs_pack() -> std::io::Result<()> {
                 .long("target")
                 .help("Executable target dir(with backslash)"),
         )
+        .arg(
+            Arg::new("extra")
+                .short('e')
+                .long("extra")
+                .help("Dir of prebuilt executables packed as they are, e.g. static musl binaries"),
+        )
         .get_matches();
 
     let source_path = matches.get_one::<String>("source").unwrap();
@@ -54,6 +60,7 @@ fn easy_fs_pack() -> std::io::Result<()> {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(format!("{}/{}", target_path, "fs.img"))?;
         f.set_len(16 * 2048 * 512).unwrap();
         f
@@ -83,6 +90,19 @@ fn easy_fs_pack() -> std::io::Result<()> {
         // write data to easy-fs
         inode.write_at(0, all_data.as_slice());
     }
+    if let Some(extra_path) = matches.get_one::<String>("extra") {
+        for dir_entry in read_dir(extra_path)? {
+            let dir_entry = dir_entry?;
+            if !dir_entry.file_type()?.is_file() {
+                continue;
+            }
+            let name = dir_entry.file_name().into_string().unwrap();
+            println!("extra: {}", name);
+            let all_data = std::fs::read(dir_entry.path())?;
+            let inode = root_inode.create(name.as_str()).unwrap();
+            inode.write_at(0, all_data.as_slice());
+        }
+    }
     // list apps
     // for app in root_inode.ls() {
     //     println!("{}", app);
@@ -98,6 +118,7 @@ fn efs_test() -> std::io::Result<()> {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open("../target/fs.img")?;
         f.set_len(8192 * 512).unwrap();
         f
@@ -153,3 +174,82 @@ fn efs_test() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn efs_unlink_test() -> std::io::Result<()> {
+    let block_file = Arc::new(BlockFile(Mutex::new({
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open("../target/fs_unlink.img")?;
+        f.set_len(8192 * 512).unwrap();
+        f
+    })));
+    EasyFileSystem::create(block_file.clone(), 4096, 1);
+    let efs = EasyFileSystem::open(block_file.clone());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let usage = root_inode.fs_usage();
+    // the root directory
+    assert_eq!(usage.inodes - usage.free_inodes, 1);
+
+    let filea = root_inode.create("filea").unwrap();
+    filea.write_at(0, &[1u8; 10 * BLOCK_SZ]);
+    root_inode.create("fileb");
+    root_inode.create_dir("dir").unwrap();
+    assert!(root_inode.find("dir").unwrap().ls().is_empty());
+    assert!(root_inode.fs_usage().free_blocks < usage.free_blocks);
+
+    // the entry is gone, but the file is still open
+    let in_use = root_inode.fs_usage();
+    assert!(root_inode.unlink("filea"));
+    assert!(!root_inode.unlink("filea"));
+    assert!(root_inode.find("filea").is_none());
+    let mut buf = [0u8; BLOCK_SZ];
+    assert_eq!(filea.read_at(9 * BLOCK_SZ, &mut buf), BLOCK_SZ);
+    assert_eq!(buf, [1u8; BLOCK_SZ]);
+    assert_eq!(root_inode.fs_usage().free_blocks, in_use.free_blocks);
+    // the blocks and the inode are freed with it, and the slot is reused
+    drop(filea);
+    let freed = root_inode.fs_usage();
+    assert_eq!(freed.free_blocks, in_use.free_blocks + 10);
+    assert_eq!(freed.free_inodes, in_use.free_inodes + 1);
+    assert_eq!(root_inode.ls(), ["fileb", "dir"]);
+    root_inode.create("filec");
+    assert_eq!(root_inode.ls(), ["filec", "fileb", "dir"]);
+
+    assert!(root_inode.rename("fileb", &root_inode, "filed"));
+    assert!(!root_inode.rename("fileb", &root_inode, "filed"));
+    assert_eq!(root_inode.ls(), ["filec", "filed", "dir"]);
+
+    // into another directory, and back
+    let dir = root_inode.find("dir").unwrap();
+    // `.` and `..` are entries, which ls leaves out
+    assert_eq!(dir.find(".").unwrap().stat().0, dir.stat().0);
+    assert_eq!(dir.find("..").unwrap().stat().0, 0);
+    assert_eq!(root_inode.find("..").unwrap().stat().0, 0);
+    assert!(root_inode.rename("filec", &dir, "filee"));
+    assert_eq!(root_inode.ls(), ["filed", "dir"]);
+    assert_eq!(dir.ls(), ["filee"]);
+    assert!(dir.find("filee").unwrap().stat().1);
+    assert!(dir.rename("filee", &root_inode, "filec"));
+    assert!(dir.ls().is_empty());
+    assert_eq!(root_inode.ls(), ["filec", "filed", "dir"]);
+    // a directory moved into another one has a new `..`
+    let sub = root_inode.create_dir("sub").unwrap();
+    assert!(root_inode.rename("sub", &dir, "sub"));
+    assert_eq!(sub.find("..").unwrap().stat().0, dir.stat().0);
+    drop(sub);
+    assert!(dir.unlink("sub"));
+
+    drop(dir);
+    for name in root_inode.ls() {
+        assert!(root_inode.unlink(&name));
+    }
+    let after = root_inode.fs_usage();
+    assert_eq!(after.free_inodes, usage.free_inodes);
+    // the block of the root directory's entries was there from the start
+    assert_eq!(after.free_blocks, usage.free_blocks);
+    Ok(())
+}
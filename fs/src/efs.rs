@@ -3,8 +3,21 @@ use super::{
     SuperBlock,
 };
 use crate::BLOCK_SZ;
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use spin::Mutex;
+/// How much of a filesystem is in use
+pub struct FsUsage {
+    /// Data blocks
+    pub blocks: usize,
+    /// Data blocks not allocated
+    pub free_blocks: usize,
+    /// Inodes
+    pub inodes: usize,
+    /// Inodes not allocated
+    pub free_inodes: usize,
+}
+
 ///An easy file system on block
 pub struct EasyFileSystem {
     ///Real device
@@ -15,6 +28,10 @@ pub struct EasyFileSystem {
     pub data_bitmap: Bitmap,
     inode_area_start_block: u32,
     data_area_start_block: u32,
+    /// The number of `Inode`s of each inode id in memory, an unlinked inode is only freed
+    /// with the last of them. It has a lock of its own, which every `Inode` shares, so dropping
+    /// one does not need the lock of the filesystem.
+    pub(crate) inode_refs: Arc<Mutex<BTreeMap<u32, usize>>>,
 }
 
 type DataBlock = [u8; BLOCK_SZ];
@@ -45,6 +62,7 @@ impl EasyFileSystem {
             data_bitmap,
             inode_area_start_block: 1 + inode_bitmap_blocks,
             data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            inode_refs: Arc::new(Mutex::new(BTreeMap::new())),
         };
         // clear all blocks
         for i in 0..total_blocks {
@@ -78,8 +96,12 @@ impl EasyFileSystem {
             .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
                 disk_inode.initialize(DiskInodeType::Directory);
             });
+        let efs = Arc::new(Mutex::new(efs));
+        // `..` of the root directory is the root directory itself
+        let root_inode = Self::root_inode(&efs);
+        root_inode.add_dot_entries(0, &mut efs.lock());
         block_cache_sync_all();
-        Arc::new(Mutex::new(efs))
+        efs
     }
     /// Open a block device as a filesystem
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
@@ -99,17 +121,17 @@ impl EasyFileSystem {
                     ),
                     inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                    inode_refs: Arc::new(Mutex::new(BTreeMap::new())),
                 };
                 Arc::new(Mutex::new(efs))
             })
     }
     /// Get the root inode of the filesystem
     pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
-        let block_device = Arc::clone(&efs.lock().block_device);
         // acquire efs lock temporarily
-        let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
+        let mut fs = efs.lock();
+        Inode::new(0, Arc::clone(efs), &mut fs)
         // release efs lock
-        Inode::new(0, block_id, block_offset, Arc::clone(efs), block_device)
     }
     /// Get inode by id
     pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
@@ -130,6 +152,12 @@ impl EasyFileSystem {
         self.inode_bitmap.alloc(&self.block_device).unwrap() as u32
     }
 
+    /// Deallocate an inode
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize)
+    }
+
     /// Allocate a data block
     pub fn alloc_data(&mut self) -> u32 {
         self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
@@ -148,4 +176,19 @@ impl EasyFileSystem {
             (block_id - self.data_area_start_block) as usize,
         )
     }
+    /// Count the blocks and inodes in use
+    pub fn usage(&self) -> FsUsage {
+        let blocks = get_block_cache(0, Arc::clone(&self.block_device))
+            .lock()
+            .read(0, |super_block: &SuperBlock| {
+                super_block.data_area_blocks as usize
+            });
+        let inodes = self.inode_bitmap.maximum();
+        FsUsage {
+            blocks,
+            free_blocks: blocks - self.data_bitmap.allocated(&self.block_device),
+            inodes,
+            free_inodes: inodes - self.inode_bitmap.allocated(&self.block_device),
+        }
+    }
 }
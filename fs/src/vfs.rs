@@ -1,35 +1,38 @@
 use super::{
     block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    EasyFileSystem, FsUsage, DIRENT_SZ,
 };
+use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
-/// Virtual filesystem layer over easy-fs
+/// Virtual filesystem layer over easy-fs.
+///
+/// The last `Inode` of an inode which no entry links to frees it when it is dropped, which takes
+/// the lock of the filesystem, so an `Inode` must not be dropped while that lock is held.
 pub struct Inode {
     inode_id: u32,
     block_id: usize,
     block_offset: usize,
     fs: Arc<Mutex<EasyFileSystem>>,
     block_device: Arc<dyn BlockDevice>,
+    // `inode_refs` of the filesystem
+    refs: Arc<Mutex<BTreeMap<u32, usize>>>,
 }
 
 impl Inode {
-    /// Create a vfs inode
-    pub fn new(
-        inode_id: u32,
-        block_id: u32,
-        block_offset: usize,
-        fs: Arc<Mutex<EasyFileSystem>>,
-        block_device: Arc<dyn BlockDevice>,
-    ) -> Self {
+    /// Create a vfs inode, `efs` is `fs` locked
+    pub fn new(inode_id: u32, fs: Arc<Mutex<EasyFileSystem>>, efs: &mut EasyFileSystem) -> Self {
+        let (block_id, block_offset) = efs.get_disk_inode_pos(inode_id);
+        *efs.inode_refs.lock().entry(inode_id).or_insert(0) += 1;
         Self {
             inode_id,
             block_id: block_id as usize,
             block_offset,
             fs,
-            block_device,
+            block_device: Arc::clone(&efs.block_device),
+            refs: Arc::clone(&efs.inode_refs),
         }
     }
     /// Call a function over a disk inode to read it
@@ -46,8 +49,17 @@ impl Inode {
     }
     /// Find inode under a disk inode by name
     fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        self.find_dirent(name, disk_inode)
+            .map(|(_, inode_id)| inode_id)
+    }
+    /// Find the slot of an entry under a disk inode by name, and its inode id
+    fn find_dirent(&self, name: &str, disk_inode: &DiskInode) -> Option<(usize, u32)> {
         // assert it is a directory
         assert!(disk_inode.is_dir());
+        // the slots of removed entries are empty
+        if name.is_empty() {
+            return None;
+        }
         let file_count = (disk_inode.size as usize) / DIRENT_SZ;
         let mut dirent = DirEntry::empty();
         for i in 0..file_count {
@@ -56,26 +68,36 @@ impl Inode {
                 DIRENT_SZ,
             );
             if dirent.name() == name {
-                return Some(dirent.inode_id());
+                return Some((i, dirent.inode_id()));
             }
         }
         None
     }
+    /// Write a dirent into the first empty slot of a directory, or append it if there is none
+    fn add_dirent(
+        &self,
+        dirent: &DirEntry,
+        dir_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) {
+        let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+        let mut slot = DirEntry::empty();
+        let free = (0..file_count).find(|&i| {
+            dir_inode.read_at(DIRENT_SZ * i, slot.as_bytes_mut(), &self.block_device);
+            slot.name().is_empty()
+        });
+        let index = free.unwrap_or_else(|| {
+            // increase size
+            self.increase_size(((file_count + 1) * DIRENT_SZ) as u32, dir_inode, fs);
+            file_count
+        });
+        dir_inode.write_at(index * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+    }
     /// Find inode under current inode by name
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
-        let fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| {
-            self.find_inode_id(name, disk_inode).map(|inode_id| {
-                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
-                Arc::new(Self::new(
-                    inode_id,
-                    block_id,
-                    block_offset,
-                    self.fs.clone(),
-                    self.block_device.clone(),
-                ))
-            })
-        })
+        let mut fs = self.fs.lock();
+        let inode_id = self.read_disk_inode(|disk_inode| self.find_inode_id(name, disk_inode))?;
+        Some(Arc::new(Self::new(inode_id, self.fs.clone(), &mut fs)))
     }
     /// Increase the size of a disk inode
     fn increase_size(
@@ -94,8 +116,16 @@ impl Inode {
         }
         disk_inode.increase_size(new_size, v, &self.block_device);
     }
-    /// Create inode under current inode by name
+    /// Create a file under current inode by name
     pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File)
+    }
+    /// Create a directory under current inode by name
+    pub fn create_dir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Directory)
+    }
+    /// Create an inode of `type_` under current inode by name
+    fn create_inode(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
         let mut fs = self.fs.lock();
         let op = |root_inode: &DiskInode| {
             // assert it is a directory
@@ -110,40 +140,36 @@ impl Inode {
         // alloc a inode with an indirect block
         let new_inode_id = fs.alloc_inode();
         // initialize inode
+        let is_dir = type_ == DiskInodeType::Directory;
         let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
         get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-                new_inode.initialize(DiskInodeType::File);
+                new_inode.initialize(type_);
             });
         self.modify_disk_inode(|root_inode| {
-            // append file in the dirent
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let new_size = (file_count + 1) * DIRENT_SZ;
-            // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
-            // write dirent
             let dirent = DirEntry::new(name, new_inode_id);
-            root_inode.write_at(
-                file_count * DIRENT_SZ,
-                dirent.as_bytes(),
-                &self.block_device,
-            );
+            self.add_dirent(&dirent, root_inode, &mut fs);
         });
+        let inode = Arc::new(Self::new(new_inode_id, self.fs.clone(), &mut fs));
+        if is_dir {
+            inode.add_dot_entries(self.inode_id, &mut fs);
+        }
 
-        let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
         block_cache_sync_all();
         // return inode
-        Some(Arc::new(Self::new(
-            new_inode_id,
-            block_id,
-            block_offset,
-            self.fs.clone(),
-            self.block_device.clone(),
-        )))
+        Some(inode)
         // release efs lock automatically by compiler
     }
-    /// List inodes under current inode
+    /// Add the `.` and `..` entries of a new directory, `..` leads to `parent_id`. They do not
+    /// count in `nlink`.
+    pub(crate) fn add_dot_entries(&self, parent_id: u32, fs: &mut MutexGuard<EasyFileSystem>) {
+        self.modify_disk_inode(|dir_inode| {
+            self.add_dirent(&DirEntry::new(".", self.inode_id), dir_inode, fs);
+            self.add_dirent(&DirEntry::new("..", parent_id), dir_inode, fs);
+        });
+    }
+    /// List inodes under current inode, but `.` and `..`
     pub fn ls(&self) -> Vec<String> {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
@@ -155,7 +181,9 @@ impl Inode {
                     disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device,),
                     DIRENT_SZ,
                 );
-                v.push(String::from(dirent.name()));
+                if !matches!(dirent.name(), "" | "." | "..") {
+                    v.push(String::from(dirent.name()));
+                }
             }
             v
         })
@@ -189,6 +217,12 @@ impl Inode {
         block_cache_sync_all();
     }
 
+    /// Size of current inode in bytes
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+
     /// Stat. ino, is_file, nlink
     pub fn stat(&self) -> (u32, bool, u32) {
         self.read_disk_inode(|disk_inode| (self.inode_id, disk_inode.is_file(), disk_inode.nlink))
@@ -203,51 +237,130 @@ impl Inode {
 
         let mut fs = self.fs.lock();
         self.modify_disk_inode(|root_inode| {
-            // append file in the dirent
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let new_size = (file_count + 1) * DIRENT_SZ;
-            // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
-            // write dirent
             let dirent = DirEntry::new(target_path, inode.inode_id);
+            self.add_dirent(&dirent, root_inode, &mut fs);
+        });
+
+        block_cache_sync_all();
+    }
+
+    /// Remove the entry `name`, and free its inode once no entry links to it and no `Inode` of
+    /// it is left, so an open file can still be used. Whether there was such an entry.
+    pub fn unlink(&self, name: &str) -> bool {
+        let mut fs = self.fs.lock();
+        let Some((slot, inode_id)) =
+            self.read_disk_inode(|disk_inode| self.find_dirent(name, disk_inode))
+        else {
+            return false;
+        };
+        self.modify_disk_inode(|root_inode| {
             root_inode.write_at(
-                file_count * DIRENT_SZ,
-                dirent.as_bytes(),
+                DIRENT_SZ * slot,
+                DirEntry::empty().as_bytes(),
                 &self.block_device,
             );
         });
+        // under the lock of the counts, so that either this or the last drop frees the inode
+        let refs = self.refs.lock();
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        let nlink = get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(block_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.nlink -= 1;
+                disk_inode.nlink
+            });
+        let unused = !refs.contains_key(&inode_id);
+        drop(refs);
+        if nlink == 0 && unused {
+            free_inode(&mut fs, inode_id);
+        }
+        block_cache_sync_all();
+        true
+    }
 
+    /// Move the entry `old` to `new` in the directory `dir`, which may be this one. `new` must not
+    /// exist yet. Whether there was such an entry.
+    pub fn rename(&self, old: &str, dir: &Inode, new: &str) -> bool {
+        let mut fs = self.fs.lock();
+        let Some((slot, inode_id)) =
+            self.read_disk_inode(|disk_inode| self.find_dirent(old, disk_inode))
+        else {
+            return false;
+        };
+        let dirent = DirEntry::new(new, inode_id);
+        if dir.inode_id == self.inode_id {
+            self.modify_disk_inode(|root_inode| {
+                root_inode.write_at(DIRENT_SZ * slot, dirent.as_bytes(), &self.block_device);
+            });
+        } else {
+            self.modify_disk_inode(|root_inode| {
+                root_inode.write_at(
+                    DIRENT_SZ * slot,
+                    DirEntry::empty().as_bytes(),
+                    &self.block_device,
+                );
+            });
+            dir.modify_disk_inode(|dir_inode| {
+                dir.add_dirent(&dirent, dir_inode, &mut fs);
+            });
+            // a directory moved to another one has a new `..`
+            let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+            get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                .lock()
+                .modify(block_offset, |disk_inode: &mut DiskInode| {
+                    if !disk_inode.is_dir() {
+                        return;
+                    }
+                    if let Some((slot, _)) = self.find_dirent("..", disk_inode) {
+                        let parent = DirEntry::new("..", dir.inode_id);
+                        disk_inode.write_at(
+                            DIRENT_SZ * slot,
+                            parent.as_bytes(),
+                            &self.block_device,
+                        );
+                    }
+                });
+        }
         block_cache_sync_all();
+        true
     }
 
-    /// unlink
-    pub fn unlink(&self, path: &str) {
-        let inode = self.find(path).unwrap();
-        inode.modify_disk_inode(|disk_inode| {
-            disk_inode.nlink -= 1;
-            if disk_inode.nlink == 0 {
-                disk_inode.clear_size(&self.block_device);
-            }
-        });
+    /// Count the blocks and inodes in use on the filesystem of this inode
+    pub fn fs_usage(&self) -> FsUsage {
+        self.fs.lock().usage()
+    }
+}
 
-        self.modify_disk_inode(|root_inode| {
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let mut dirent = DirEntry::empty();
-            for i in 0..file_count {
-                assert_eq!(
-                    root_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device,),
-                    DIRENT_SZ,
-                );
-                if dirent.name() == path {
-                    root_inode.write_at(
-                        DIRENT_SZ * i,
-                        DirEntry::empty().as_bytes(),
-                        &self.block_device,
-                    );
-                }
-            }
-        });
+impl Drop for Inode {
+    /// Free the inode with its last `Inode` if no entry links to it any more
+    fn drop(&mut self) {
+        let mut refs = self.refs.lock();
+        let count = refs.get_mut(&self.inode_id).unwrap();
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        refs.remove(&self.inode_id);
+        let unlinked = self.read_disk_inode(|disk_inode| disk_inode.nlink) == 0;
+        drop(refs);
+        if unlinked {
+            free_inode(&mut self.fs.lock(), self.inode_id);
+            block_cache_sync_all();
+        }
+    }
+}
 
-        block_cache_sync_all();
+/// Free the data blocks and the inode `inode_id`, which is not in use any more
+fn free_inode(fs: &mut EasyFileSystem, inode_id: u32) {
+    let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+    let block_device = Arc::clone(&fs.block_device);
+    let data_blocks_dealloc = get_block_cache(block_id as usize, Arc::clone(&block_device))
+        .lock()
+        .modify(block_offset, |disk_inode: &mut DiskInode| {
+            disk_inode.clear_size(&block_device)
+        });
+    for data_block in data_blocks_dealloc.into_iter() {
+        fs.dealloc_data(data_block);
     }
+    fs.dealloc_inode(inode_id);
 }
@@ -1,11 +1,35 @@
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2; // 8KiB
 pub const USER_STACK_SIZE: usize = 4096 * 2; // 8KiB
 
+// top of the lower half of sv39, user stack grows down from here
+pub const USER_STACK_TOP: usize = 0x40_0000_0000;
+// rlimit of the user stack, it grows on demand up to this size
+pub const USER_STACK_LIMIT: usize = 4096 * 2048; // 8MiB
+
+// unmapped pages below the stack limit, a fault in them is a stack overflow
+pub const USER_STACK_GUARD: usize = 4096 * 16; // 64KiB
+
+// the page holding the code signal handlers return to, right below the lowest stack guard
+pub const SIGRETURN_TRAMPOLINE: usize =
+    USER_STACK_TOP - USER_STACK_LIMIT - USER_STACK_GUARD - PAGE_SIZE;
+
+// where a position-independent executable is loaded
+pub const ELF_DYN_BASE: usize = 0x1000_0000;
+// where the interpreter of a dynamically linked program, e.g. ld-musl-riscv64.so.1, is loaded
+pub const ELF_INTERP_BASE: usize = 0x3000_0000;
+
+// where mmap starts to search for a free range when no address is given
+pub const MMAP_BASE: usize = 0x4000_0000;
+
+// rlimit of open files, descriptors are below this
+pub const FD_LIMIT: usize = 1024;
+
 // qemu-system-riscv64 -machine virt,dumpdtb=dump.dtb
 // dtc dump.dtb | vi -
 pub const TIMEBASE_FREQUENCY: usize = 0x989680;
 pub const TICK_PER_SEC: usize = 100;
-pub const MSEC_PER_SEC: usize = 1000;
+pub const USEC_PER_SEC: usize = 1_000_000;
+pub const NSEC_PER_SEC: usize = 1_000_000_000;
 
 pub const HEAP_ORDER_SIZE: usize = 32;
 pub const KERNEL_HEAP_SIZE: usize = 0x3_00000;
@@ -21,8 +45,29 @@ pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 
 pub const MEMORY_END: usize = 0x8800_0000;
 
+// size of the swap device in pages, 128MiB
+pub const SWAP_PAGES: usize = 0x8000;
+// once swapping starts, keep this many frames free for page tables and kernel stacks
+pub const FRAME_RESERVED: usize = 16;
+
+// Each kernel stack has an unmapped guard page below it
 pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
     let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
     let bottom = top - KERNEL_STACK_SIZE;
     (bottom, top)
 }
+
+// Each thread of a process has its trap context in its own page, slot 0 at TRAP_CONTEXT and the
+// others below it
+pub fn trap_context_position(slot: usize) -> usize {
+    TRAP_CONTEXT - slot * PAGE_SIZE
+}
+
+// Whether `addr` is in the guard page of some kernel stack.
+// Kernel stacks live in the upper half of sv39, below the trampoline.
+pub fn is_kernel_stack_guard(addr: usize) -> bool {
+    if addr >= TRAMPOLINE || addr < usize::MAX << 38 {
+        return false;
+    }
+    (TRAMPOLINE - addr - 1) % (KERNEL_STACK_SIZE + PAGE_SIZE) >= KERNEL_STACK_SIZE
+}
@@ -1,6 +1,7 @@
 mod virtio_blk;
 
 pub use virtio_blk::VirtIOBlock;
+use virtio_blk::VIRTIO1;
 
 use crate::qemu::BlockDeviceImpl;
 use alloc::sync::Arc;
@@ -9,6 +10,9 @@ use lazy_static::*;
 
 lazy_static! {
     pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(BlockDeviceImpl::new());
+    // swapping is disabled if qemu is started without the second device
+    pub static ref SWAP_DEVICE: Option<Arc<dyn BlockDevice>> =
+        BlockDeviceImpl::try_new(VIRTIO1).map(|dev| Arc::new(dev) as Arc<dyn BlockDevice>);
 }
 
 #[allow(unused)]
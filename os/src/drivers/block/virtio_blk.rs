@@ -1,8 +1,8 @@
 use super::BlockDevice;
 use crate::{
     mm::{
-        frame_alloc, frame_dealloc, kernel_space::get_kernel_token, FrameTracker, PageTable,
-        PhysicalAddr, PhysicalPageNumber, StepByOne, VirtualAddr,
+        frame_alloc_contiguous, kernel_space::get_kernel_token, FrameTracker, PageTable,
+        PhysicalAddr, PhysicalPageNumber, VirtualAddr,
     },
     sync::Mutex,
 };
@@ -12,6 +12,8 @@ use virtio_drivers::{Hal, VirtIOBlk, VirtIOHeader};
 
 #[allow(unused)]
 const VIRTIO0: usize = 0x10001000;
+// the second virtio device, used as the swap area
+pub const VIRTIO1: usize = 0x10002000;
 
 pub struct VirtIOBlock(Mutex<VirtIOBlk<'static, VirtioHal>>);
 
@@ -37,11 +39,18 @@ impl BlockDevice for VirtIOBlock {
 impl VirtIOBlock {
     #[allow(unused)]
     pub fn new() -> Self {
-        unsafe {
-            Self(Mutex::new(
-                VirtIOBlk::<VirtioHal>::new(&mut *(VIRTIO0 as *mut VirtIOHeader)).unwrap(),
-            ))
+        Self::try_new(VIRTIO0).unwrap()
+    }
+
+    /// Probe the virtio block device whose registers are at `base`, None if there isn't one.
+    pub fn try_new(base: usize) -> Option<Self> {
+        let header = unsafe { &mut *(base as *mut VirtIOHeader) };
+        if !header.verify() {
+            return None;
         }
+        VirtIOBlk::<VirtioHal>::new(header)
+            .ok()
+            .map(|blk| Self(Mutex::new(blk)))
     }
 }
 
@@ -49,26 +58,18 @@ pub struct VirtioHal;
 
 impl Hal for VirtioHal {
     fn dma_alloc(pages: usize) -> usize {
-        let mut ppn_base = PhysicalPageNumber(0);
-        for i in 0..pages {
-            let frame = frame_alloc().unwrap();
-            if i == 0 {
-                ppn_base = frame.ppn;
-            }
-            assert_eq!(frame.ppn.0, ppn_base.0 + i);
-            QUEUE_FRAMES.lock().push(frame);
-        }
-        let pa: PhysicalAddr = ppn_base.into();
+        let frames = frame_alloc_contiguous(pages, 1).unwrap();
+        let pa: PhysicalAddr = frames[0].ppn.into();
+        QUEUE_FRAMES.lock().extend(frames);
         pa.0
     }
 
     fn dma_dealloc(pa: usize, pages: usize) -> i32 {
-        let pa = PhysicalAddr::from(pa);
-        let mut ppn_base: PhysicalPageNumber = pa.into();
-        for _ in 0..pages {
-            frame_dealloc(ppn_base);
-            ppn_base.step();
-        }
+        let ppn_base: PhysicalPageNumber = PhysicalAddr::from(pa).into();
+        // dropping the trackers frees the frames
+        QUEUE_FRAMES
+            .lock()
+            .retain(|frame| frame.ppn.0 < ppn_base.0 || frame.ppn.0 >= ppn_base.0 + pages);
         0
     }
 
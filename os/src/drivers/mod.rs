@@ -1,2 +1,2 @@
 pub mod block;
-pub use block::BLOCK_DEVICE;
\ No newline at end of file
+pub use block::{BLOCK_DEVICE, SWAP_DEVICE};
\ No newline at end of file
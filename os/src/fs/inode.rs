@@ -1,10 +1,12 @@
-use super::File;
+use super::{dirents, Dirent, File, Stat, DT_DIR, DT_REG};
 use crate::mm::UserBuffer;
+use crate::syscall::Errno;
 use crate::{drivers::BLOCK_DEVICE, sync::Mutex};
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::*;
-use fs::{EasyFileSystem, Inode};
+use fs::{EasyFileSystem, FsUsage, Inode};
 use lazy_static::*;
 /// A wrapper around a filesystem inode
 /// to implement File trait atop
@@ -17,15 +19,20 @@ pub struct OSInode {
 pub struct OSInodeInner {
     offset: usize,
     inode: Arc<Inode>,
+    status: OpenFlags,
 }
 
 impl OSInode {
     /// Construct an OS inode from a inode
-    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+    pub fn new(readable: bool, writable: bool, status: OpenFlags, inode: Arc<Inode>) -> Self {
         Self {
             readable,
             writable,
-            inner: Mutex::new(OSInodeInner { offset: 0, inode }),
+            inner: Mutex::new(OSInodeInner {
+                offset: 0,
+                inode,
+                status,
+            }),
         }
     }
     /// Read all data inside a inode into vector
@@ -61,7 +68,8 @@ pub fn list_apps() {
 }
 
 bitflags! {
-    ///Open file flags
+    ///Open file flags, the same values as Linux
+    #[derive(Debug, Clone, Copy)]
     pub struct OpenFlags: u32 {
         ///Read only
         const RDONLY = 0;
@@ -70,48 +78,189 @@ bitflags! {
         ///Read & Write
         const RDWR = 1 << 1;
         ///Allow create
-        const CREATE = 1 << 9;
+        const CREATE = 0o100;
         ///Clear file and return an empty one
-        const TRUNC = 1 << 10;
+        const TRUNC = 0o1000;
+        ///Write at the end of the file
+        const APPEND = 0o2000;
+        ///Fail with EAGAIN rather than block
+        const NONBLOCK = 0o4000;
     }
 }
 
 impl OpenFlags {
+    /// The status flags, which F_SETFL changes later
+    pub fn status(self) -> Self {
+        self & (Self::APPEND | Self::NONBLOCK)
+    }
+
     /// Do not check validity for simplicity
     /// Return (readable, writable)
     pub fn read_write(&self) -> (bool, bool) {
-        if self.is_empty() {
-            (true, false)
+        if self.contains(Self::RDWR) {
+            (true, true)
         } else if self.contains(Self::WRONLY) {
             (false, true)
         } else {
-            (true, true)
+            (true, false)
+        }
+    }
+}
+
+/// The entry `name` of the directory `dir`, or `dir` itself if the name is empty
+fn entry(dir: &Arc<Inode>, name: &str) -> Result<Arc<Inode>, Errno> {
+    if name.is_empty() {
+        return Ok(dir.clone());
+    }
+    dir.find(name).ok_or(Errno::ENOENT)
+}
+
+/// Whether `dir` is a directory that was removed, where nothing can be created any more
+fn is_removed(dir: &Inode) -> bool {
+    dir.stat().2 == 0
+}
+
+/// Whether the directory `dir` is `ancestor` or inside it, going up through the `..` entries
+fn is_within(dir: &Arc<Inode>, ancestor: &Inode) -> bool {
+    let mut dir = dir.clone();
+    loop {
+        if dir.stat().0 == ancestor.stat().0 {
+            return true;
+        }
+        if is_root(&dir) {
+            return false;
+        }
+        match dir.find("..") {
+            Some(parent) => dir = parent,
+            None => return false,
         }
     }
 }
+
+pub fn is_root(inode: &Inode) -> bool {
+    inode.stat().0 == ROOT_INODE.stat().0
+}
+
+pub fn is_dir(inode: &Inode) -> bool {
+    !inode.stat().1
+}
+
+// The functions below take a directory and the name of an entry in it, as `resolve` finds them
+
 ///Open file with flags
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+pub fn open_file(dir: &Arc<Inode>, name: &str, flags: OpenFlags) -> Result<Arc<OSInode>, Errno> {
     let (readable, writable) = flags.read_write();
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
-            log::debug!("");
-            // clear size
-            inode.clear();
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
-        } else {
-            // create file
-            ROOT_INODE
-                .create(name)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
-        }
-    } else {
-        ROOT_INODE.find(name).map(|inode| {
+    let inode = match entry(dir, name) {
+        Ok(inode) => {
+            if is_dir(&inode) && (writable || flags.contains(OpenFlags::TRUNC)) {
+                return Err(Errno::EISDIR);
+            }
             if flags.contains(OpenFlags::TRUNC) {
                 inode.clear();
             }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
+            inode
+        }
+        // create file
+        Err(Errno::ENOENT) if flags.contains(OpenFlags::CREATE) => {
+            if is_removed(dir) {
+                return Err(Errno::ENOENT);
+            }
+            dir.create(name).ok_or(Errno::EEXIST)?
+        }
+        Err(errno) => return Err(errno),
+    };
+    Ok(Arc::new(OSInode::new(
+        readable,
+        writable,
+        flags.status(),
+        inode,
+    )))
+}
+
+/// The `struct stat` of the entry
+pub fn stat_path(dir: &Arc<Inode>, name: &str) -> Result<Stat, Errno> {
+    entry(dir, name).map(|inode| inode_stat(&inode))
+}
+
+/// Create the directory `name` in `dir`
+pub fn make_dir(dir: &Arc<Inode>, name: &str) -> Result<(), Errno> {
+    if name.is_empty() {
+        return Err(Errno::EEXIST);
+    }
+    if is_removed(dir) {
+        return Err(Errno::ENOENT);
+    }
+    dir.create_dir(name).map(|_| ()).ok_or(Errno::EEXIST)
+}
+
+/// Remove the file `name` of `parent_dir`, or with `dir` the empty directory
+pub fn remove(parent_dir: &Arc<Inode>, name: &str, dir: bool) -> Result<(), Errno> {
+    if name.is_empty() {
+        return Err(if dir { Errno::EBUSY } else { Errno::EISDIR });
+    }
+    let inode = parent_dir.find(name).ok_or(Errno::ENOENT)?;
+    match (dir, is_dir(&inode)) {
+        (false, true) => return Err(Errno::EISDIR),
+        (true, false) => return Err(Errno::ENOTDIR),
+        // `.` and `..` go with their directories
+        (true, true) if name == "." => return Err(Errno::EINVAL),
+        (true, true) if name == ".." || !inode.ls().is_empty() => return Err(Errno::ENOTEMPTY),
+        _ => {}
+    }
+    parent_dir.unlink(name);
+    Ok(())
+}
+
+/// Move the entry `old_name` of `old_dir` to `new_name` in `new_dir`, replacing what is there
+/// unless that is a directory which is not empty, or not a directory while the old one is, or the
+/// other way round
+pub fn rename(
+    old_dir: &Arc<Inode>,
+    old_name: &str,
+    new_dir: &Arc<Inode>,
+    new_name: &str,
+) -> Result<(), Errno> {
+    let dots = |name: &str| matches!(name, "" | "." | "..");
+    if dots(old_name) || dots(new_name) {
+        return Err(Errno::EBUSY);
     }
+    let inode = old_dir.find(old_name).ok_or(Errno::ENOENT)?;
+    if old_dir.stat().0 == new_dir.stat().0 && old_name == new_name {
+        return Ok(());
+    }
+    if is_removed(new_dir) {
+        return Err(Errno::ENOENT);
+    }
+    // a directory cannot go into itself
+    if is_dir(&inode) && is_within(new_dir, &inode) {
+        return Err(Errno::EINVAL);
+    }
+    if let Some(target) = new_dir.find(new_name) {
+        match (is_dir(&inode), is_dir(&target)) {
+            (false, true) => return Err(Errno::EISDIR),
+            (true, false) => return Err(Errno::ENOTDIR),
+            (true, true) if !target.ls().is_empty() => return Err(Errno::ENOTEMPTY),
+            _ => {}
+        }
+        new_dir.unlink(new_name);
+    }
+    old_dir.rename(old_name, &new_dir, new_name);
+    Ok(())
+}
+
+/// How much of the disk is in use
+pub fn fs_usage() -> FsUsage {
+    ROOT_INODE.fs_usage()
+}
+
+fn inode_stat(inode: &Inode) -> Stat {
+    let (ino, is_file, nlink) = inode.stat();
+    let mode = if is_file {
+        Stat::S_IFREG
+    } else {
+        Stat::S_IFDIR
+    } | 0o755;
+    Stat::new(ino as u64, mode, nlink, inode.size())
 }
 
 impl File for OSInode {
@@ -125,7 +274,7 @@ impl File for OSInode {
         self.writable
     }
 
-    fn read(&self, mut buf: UserBuffer) -> usize {
+    fn read(&self, mut buf: UserBuffer) -> Result<usize, Errno> {
         let mut inner = self.inner.lock();
         let mut total_read_size = 0usize;
         for slice in buf.buffers.iter_mut() {
@@ -136,10 +285,13 @@ impl File for OSInode {
             inner.offset += read_size;
             total_read_size += read_size;
         }
-        total_read_size
+        Ok(total_read_size)
     }
-    fn write(&self, buf: UserBuffer) -> usize {
+    fn write(&self, buf: UserBuffer) -> Result<usize, Errno> {
         let mut inner = self.inner.lock();
+        if inner.status.contains(OpenFlags::APPEND) {
+            inner.offset = inner.inode.size();
+        }
         let mut total_write_size = 0usize;
         for slice in buf.buffers.iter() {
             let write_size = inner.inode.write_at(inner.offset, slice);
@@ -147,6 +299,41 @@ impl File for OSInode {
             inner.offset += write_size;
             total_write_size += write_size;
         }
-        total_write_size
+        Ok(total_write_size)
+    }
+
+    fn inode(&self) -> Option<Arc<Inode>> {
+        Some(self.inner.lock().inode.clone())
+    }
+
+    fn stat(&self) -> Stat {
+        inode_stat(&self.inner.lock().inode)
+    }
+
+    fn status_flags(&self) -> OpenFlags {
+        self.inner.lock().status
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        self.inner.lock().status = flags;
+    }
+
+    // the offset of a directory counts the entries read so far
+    fn getdents(&self, len: usize) -> Result<Vec<u8>, Errno> {
+        let mut inner = self.inner.lock();
+        if !is_dir(&inner.inode) {
+            return Err(Errno::ENOTDIR);
+        }
+        // ls leaves out `.` and `..`
+        let mut entries: Vec<Dirent> = Vec::new();
+        let dots = [".", ".."].map(String::from);
+        for name in dots.into_iter().chain(inner.inode.ls()) {
+            if let Some(child) = inner.inode.find(&name) {
+                let (ino, is_file, _) = child.stat();
+                let kind = if is_file { DT_REG } else { DT_DIR };
+                entries.push((ino as u64, kind, name));
+            }
+        }
+        dirents(&entries, &mut inner.offset, len)
     }
 }
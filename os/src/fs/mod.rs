@@ -1,7 +1,11 @@
 mod inode;
+mod pipe;
+mod proc;
 mod stdio;
 
-use crate::mm::UserBuffer;
+use crate::{mm::UserBuffer, syscall::Errno};
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use fs::{FsUsage, Inode, BLOCK_SZ, NAME_LENGTH_LIMIT};
 /// File trait
 pub trait File: Send + Sync {
     #[allow(unused)]
@@ -13,14 +17,294 @@ pub trait File: Send + Sync {
     fn writable(&self) -> bool;
 
     #[allow(unused)]
-    /// Read file to `UserBuffer`
-    fn read(&self, buf: UserBuffer) -> usize;
+    /// Read file to `UserBuffer`, return how many bytes were read, 0 at the end of the file
+    fn read(&self, buf: UserBuffer) -> Result<usize, Errno>;
 
     #[allow(unused)]
-    /// Write `UserBuffer` to file
-    fn write(&self, buf: UserBuffer) -> usize;
+    /// Write `UserBuffer` to file, return how many bytes were written
+    fn write(&self, buf: UserBuffer) -> Result<usize, Errno>;
+
+    /// The easy-fs inode behind the file, used by file-backed mmap
+    fn inode(&self) -> Option<Arc<Inode>> {
+        None
+    }
+
+    /// If it is a terminal, which takes the terminal ioctls
+    fn is_tty(&self) -> bool {
+        false
+    }
+
+    /// The `struct stat` of fstat
+    fn stat(&self) -> Stat;
+
+    /// The status flags of F_GETFL, which descriptors duplicated from one another share
+    fn status_flags(&self) -> OpenFlags {
+        OpenFlags::empty()
+    }
+
+    /// Set the status flags for F_SETFL, a file that neither blocks nor seeks ignores them
+    fn set_status_flags(&self, _flags: OpenFlags) {}
+
+    /// The next entries of a directory as `struct linux_dirent64`, as many as fit in `len`
+    /// bytes, for getdents64. Empty at the end, ENOTDIR if it is not a directory.
+    fn getdents(&self, _len: usize) -> Result<Vec<u8>, Errno> {
+        Err(Errno::ENOTDIR)
+    }
+
+    /// The absolute path of a directory in /proc, where the relative paths of the *at syscalls
+    /// start from. A directory on the disk gives its inode instead.
+    fn path(&self) -> Option<String> {
+        None
+    }
+}
+
+/// An entry of the descriptor table, an open file and the flags of the descriptor itself
+#[derive(Clone)]
+pub struct FileDescriptor {
+    pub file: Arc<dyn File + Send + Sync>,
+    // closed by exec
+    pub cloexec: bool,
+}
+
+impl FileDescriptor {
+    pub fn new(file: Arc<dyn File + Send + Sync>, cloexec: bool) -> Self {
+        Self { file, cloexec }
+    }
+}
+
+// `d_type` of `struct linux_dirent64`
+pub const DT_DIR: u8 = 4;
+pub const DT_REG: u8 = 8;
+
+/// An entry of a directory for getdents64: its inode number, `d_type` and name
+pub type Dirent = (u64, u8, String);
+
+/// The entries from `*offset` on as `struct linux_dirent64`, as many as fit in `len` bytes, and
+/// move `offset` past them. EINVAL if not even one fits.
+pub fn dirents(entries: &[Dirent], offset: &mut usize, len: usize) -> Result<Vec<u8>, Errno> {
+    let mut buf = Vec::new();
+    while let Some((ino, kind, name)) = entries.get(*offset) {
+        // d_off is the position of the next entry
+        if !push_dirent(&mut buf, len, *ino, *offset as u64 + 1, *kind, name) {
+            break;
+        }
+        *offset += 1;
+    }
+    if buf.is_empty() && *offset < entries.len() {
+        return Err(Errno::EINVAL);
+    }
+    Ok(buf)
+}
+
+/// Append a `struct linux_dirent64` to `buf` unless that makes it longer than `len`, return
+/// whether it fits
+fn push_dirent(buf: &mut Vec<u8>, len: usize, ino: u64, off: u64, kind: u8, name: &str) -> bool {
+    // d_ino, d_off, d_reclen and d_type, then the name and its NUL, padded to 8 bytes
+    let start = buf.len();
+    let reclen = (19 + name.len() + 1).next_multiple_of(8);
+    if start + reclen > len {
+        return false;
+    }
+    buf.extend_from_slice(&ino.to_ne_bytes());
+    buf.extend_from_slice(&off.to_ne_bytes());
+    buf.extend_from_slice(&(reclen as u16).to_ne_bytes());
+    buf.push(kind);
+    buf.extend_from_slice(name.as_bytes());
+    buf.resize(start + reclen, 0);
+    true
+}
+
+/// `struct stat` of fstat and newfstatat, as in asm-generic/stat.h
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    _pad: u64,
+    pub size: i64,
+    pub blksize: i32,
+    _pad2: i32,
+    pub blocks: i64,
+    pub atime_sec: i64,
+    pub atime_nsec: i64,
+    pub mtime_sec: i64,
+    pub mtime_nsec: i64,
+    pub ctime_sec: i64,
+    pub ctime_nsec: i64,
+    _unused: [u32; 2],
+}
+
+impl Stat {
+    pub const S_IFIFO: u32 = 0o010000;
+    pub const S_IFCHR: u32 = 0o020000;
+    pub const S_IFDIR: u32 = 0o040000;
+    pub const S_IFREG: u32 = 0o100000;
+
+    pub fn new(ino: u64, mode: u32, nlink: u32, size: usize) -> Self {
+        Self {
+            ino,
+            mode,
+            nlink,
+            size: size as i64,
+            blksize: BLOCK_SZ as i32,
+            blocks: ((size + 511) / 512) as i64,
+            ..Default::default()
+        }
+    }
+}
+
+/// `path` as an absolute path without `.`, `..` or empty names, a relative one starts from the
+/// absolute path `base`. There are no symbolic links, so in /proc, which has no `..` entries,
+/// this is where the path leads.
+pub fn absolute_path(base: &str, path: &str) -> String {
+    let base = if path.starts_with('/') { "" } else { base };
+    let mut names: Vec<&str> = Vec::new();
+    for name in base.split('/').chain(path.split('/')) {
+        match name {
+            "" | "." => {}
+            // `..` of the root directory is the root directory itself
+            ".." => {
+                names.pop();
+            }
+            name => names.push(name),
+        }
+    }
+    format!("/{}", names.join("/"))
+}
+
+/// Where a path leads
+enum Resolved {
+    /// The directory on the disk with the entry of the last name, which is empty if the path
+    /// names the directory it starts from
+    Disk(Arc<Inode>, String),
+    Proc(Result<ProcEntry, Errno>),
+}
+
+/// Follow `path` up to its last name, a relative path starts from the directory `base`. `..` is
+/// an entry of the directory like any other, and /proc takes over at `proc` in the root directory.
+fn resolve(base: &Arc<Inode>, path: &str) -> Result<Resolved, Errno> {
+    let mut dir = if path.starts_with('/') {
+        ROOT_INODE.clone()
+    } else {
+        base.clone()
+    };
+    let mut names = path.split('/').filter(|name| !name.is_empty()).peekable();
+    while let Some(name) = names.next() {
+        if name == "proc" && inode::is_root(&dir) {
+            let path = absolute_path("/proc", &names.collect::<Vec<_>>().join("/"));
+            return match ProcEntry::lookup(&path) {
+                Some(entry) => Ok(Resolved::Proc(entry)),
+                // `..` out of /proc
+                None => resolve(base, &path),
+            };
+        }
+        if !inode::is_dir(&dir) {
+            return Err(Errno::ENOTDIR);
+        }
+        if name.len() > NAME_LENGTH_LIMIT {
+            return Err(Errno::ENAMETOOLONG);
+        }
+        if names.peek().is_none() {
+            return Ok(Resolved::Disk(dir, name.into()));
+        }
+        dir = dir.find(name).ok_or(Errno::ENOENT)?;
+    }
+    Ok(Resolved::Disk(dir, String::new()))
+}
+
+// The functions below take the path and the directory `base` a relative one starts from
+
+/// Open the file at `path`, in /proc or on the disk
+pub fn open_path(
+    base: &Arc<Inode>,
+    path: &str,
+    flags: OpenFlags,
+) -> Result<Arc<dyn File + Send + Sync>, Errno> {
+    match resolve(base, path)? {
+        Resolved::Proc(entry) => {
+            if flags.read_write().1 {
+                return Err(Errno::EACCES);
+            }
+            Ok(Arc::new(entry?.open()?))
+        }
+        Resolved::Disk(dir, name) => Ok(inode::open_file(&dir, &name, flags)?),
+    }
+}
+
+/// Open the file at `path` on the disk, for the kernel to read. EACCES in /proc.
+pub fn open_file(base: &Arc<Inode>, path: &str, flags: OpenFlags) -> Result<Arc<OSInode>, Errno> {
+    match resolve(base, path)? {
+        Resolved::Proc(_) => Err(Errno::EACCES),
+        Resolved::Disk(dir, name) => inode::open_file(&dir, &name, flags),
+    }
+}
+
+/// The directory on the disk at `path`, ENOTDIR for a file and EACCES in /proc
+pub fn open_dir(base: &Arc<Inode>, path: &str) -> Result<Arc<Inode>, Errno> {
+    let dir = open_file(base, path, OpenFlags::RDONLY)?.inode().unwrap();
+    if !inode::is_dir(&dir) {
+        return Err(Errno::ENOTDIR);
+    }
+    Ok(dir)
+}
+
+/// The `struct stat` of the file at `path`
+pub fn stat_path(base: &Arc<Inode>, path: &str) -> Result<Stat, Errno> {
+    match resolve(base, path)? {
+        Resolved::Proc(entry) => Ok(entry?.stat()),
+        Resolved::Disk(dir, name) => inode::stat_path(&dir, &name),
+    }
+}
+
+/// Create the directory `path`
+pub fn make_dir(base: &Arc<Inode>, path: &str) -> Result<(), Errno> {
+    match resolve(base, path)? {
+        Resolved::Proc(Ok(_)) => Err(Errno::EEXIST),
+        Resolved::Proc(Err(_)) => Err(Errno::EPERM),
+        Resolved::Disk(dir, name) => inode::make_dir(&dir, &name),
+    }
+}
+
+/// Remove the file at `path`, or with `dir` the empty directory
+pub fn remove_path(base: &Arc<Inode>, path: &str, dir: bool) -> Result<(), Errno> {
+    match resolve(base, path)? {
+        Resolved::Proc(_) => Err(Errno::EPERM),
+        Resolved::Disk(parent, name) => inode::remove(&parent, &name, dir),
+    }
+}
+
+/// Move the file or directory at `old`, relative to `old_base`, to `new`, relative to `new_base`
+pub fn rename_path(
+    old_base: &Arc<Inode>,
+    old: &str,
+    new_base: &Arc<Inode>,
+    new: &str,
+) -> Result<(), Errno> {
+    match (resolve(old_base, old)?, resolve(new_base, new)?) {
+        (Resolved::Disk(old_dir, old_name), Resolved::Disk(new_dir, new_name)) => {
+            inode::rename(&old_dir, &old_name, &new_dir, &new_name)
+        }
+        (Resolved::Proc(_), Resolved::Proc(_)) => Err(Errno::EPERM),
+        _ => Err(Errno::EXDEV),
+    }
+}
+
+/// How much of the filesystem of `path` is in use, None for /proc which takes no room
+pub fn fs_usage(base: &Arc<Inode>, path: &str) -> Result<Option<FsUsage>, Errno> {
+    match resolve(base, path)? {
+        Resolved::Proc(entry) => entry.map(|_| None),
+        Resolved::Disk(dir, name) => inode::stat_path(&dir, &name).map(|_| Some(inode::fs_usage())),
+    }
 }
 
-pub use inode::{list_apps, open_file, OpenFlags};
+use inode::OSInode;
+pub use inode::{list_apps, OpenFlags, ROOT_INODE};
+pub use pipe::make_pipe;
+use proc::ProcEntry;
 #[allow(unused_imports)]
-pub use stdio::{Stdin, Stdout};
+pub use stdio::{poll_console, Stdin, Stdout};
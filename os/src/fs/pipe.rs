@@ -0,0 +1,223 @@
+//! Anonymous pipes, a ring buffer shared by a read end and a write end
+
+use super::{File, OpenFlags, Stat};
+use crate::mm::UserBuffer;
+use crate::process::processor::{get_current_process, schedule};
+use crate::process::signal::Signal;
+use crate::process::{current_has_signal, wait_queue::WaitQueue};
+use crate::sync::Mutex;
+use crate::syscall::Errno;
+use alloc::boxed::Box;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+
+/// How many bytes a pipe holds before its writers block
+const PIPE_SIZE: usize = 4096;
+
+/// One end of a pipe. Descriptors share an end, and it is closed when the last of them is.
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    status: Mutex<OpenFlags>,
+    buffer: Arc<Mutex<PipeRingBuffer>>,
+}
+
+struct PipeRingBuffer {
+    arr: Box<[u8]>,
+    // where the next byte is read from
+    head: usize,
+    len: usize,
+    read_end: Weak<Pipe>,
+    write_end: Weak<Pipe>,
+    // tasks waiting for bytes to read
+    readers: WaitQueue,
+    // tasks waiting for room to write
+    writers: WaitQueue,
+}
+
+impl PipeRingBuffer {
+    fn new() -> Self {
+        Self {
+            arr: vec![0; PIPE_SIZE].into_boxed_slice(),
+            head: 0,
+            len: 0,
+            read_end: Weak::new(),
+            write_end: Weak::new(),
+            readers: WaitQueue::new(),
+            writers: WaitQueue::new(),
+        }
+    }
+
+    /// Move as many bytes as there are into `buf`, return how many
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(self.len);
+        for (i, byte) in buf[..n].iter_mut().enumerate() {
+            *byte = self.arr[(self.head + i) % PIPE_SIZE];
+        }
+        self.head = (self.head + n) % PIPE_SIZE;
+        self.len -= n;
+        n
+    }
+
+    /// Append as much of `buf` as there is room for, return how many bytes
+    fn write(&mut self, buf: &[u8]) -> usize {
+        let n = buf.len().min(PIPE_SIZE - self.len);
+        let tail = self.head + self.len;
+        for (i, &byte) in buf[..n].iter().enumerate() {
+            self.arr[(tail + i) % PIPE_SIZE] = byte;
+        }
+        self.len += n;
+        n
+    }
+
+    fn all_readers_closed(&self) -> bool {
+        self.read_end.strong_count() == 0
+    }
+
+    fn all_writers_closed(&self) -> bool {
+        self.write_end.strong_count() == 0
+    }
+}
+
+/// A new pipe with `status` flags on both ends, return its read end and write end
+pub fn make_pipe(status: OpenFlags) -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
+    let read_end = Arc::new(Pipe {
+        readable: true,
+        writable: false,
+        status: Mutex::new(status),
+        buffer: buffer.clone(),
+    });
+    let write_end = Arc::new(Pipe {
+        readable: false,
+        writable: true,
+        status: Mutex::new(status),
+        buffer: buffer.clone(),
+    });
+    let mut ring = buffer.lock();
+    ring.read_end = Arc::downgrade(&read_end);
+    ring.write_end = Arc::downgrade(&write_end);
+    drop(ring);
+    (read_end, write_end)
+}
+
+impl Pipe {
+    fn nonblocking(&self) -> bool {
+        self.status.lock().contains(OpenFlags::NONBLOCK)
+    }
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+
+    fn writable(&self) -> bool {
+        self.writable
+    }
+
+    // Return what is there as soon as there is something, wait while the pipe is empty.
+    // 0 once it is empty and every write end is closed, EINTR if a signal arrives first,
+    // EAGAIN if it does not block.
+    fn read(&self, mut buf: UserBuffer) -> Result<usize, Errno> {
+        if buf.len() == 0 {
+            return Ok(0);
+        }
+        loop {
+            let mut ring = self.buffer.lock();
+            if ring.len > 0 {
+                let mut total = 0;
+                for slice in buf.buffers.iter_mut() {
+                    let n = ring.read(slice);
+                    total += n;
+                    if n < slice.len() {
+                        break;
+                    }
+                }
+                ring.writers.wake(usize::MAX);
+                return Ok(total);
+            }
+            if ring.all_writers_closed() {
+                return Ok(0);
+            }
+            drop(ring);
+            if self.nonblocking() {
+                return Err(Errno::EAGAIN);
+            }
+            if current_has_signal() {
+                return Err(Errno::EINTR);
+            }
+            self.buffer.lock().readers.block_current();
+            schedule();
+        }
+    }
+
+    // Write all of `buf`, waiting for room while the pipe is full. With every read end closed it
+    // is EPIPE and SIGPIPE. A signal stops it early, with EINTR if nothing was written yet, and
+    // so does a full pipe that does not block, with EAGAIN.
+    fn write(&self, buf: UserBuffer) -> Result<usize, Errno> {
+        let mut written = 0;
+        for slice in buf.buffers.iter() {
+            let mut rest: &[u8] = slice;
+            while !rest.is_empty() {
+                let mut ring = self.buffer.lock();
+                if ring.all_readers_closed() {
+                    drop(ring);
+                    get_current_process()
+                        .unwrap()
+                        .inner
+                        .lock()
+                        .raise(Signal::SIGPIPE);
+                    return partial(written, Errno::EPIPE);
+                }
+                let n = ring.write(rest);
+                if n > 0 {
+                    written += n;
+                    rest = &rest[n..];
+                    ring.readers.wake(usize::MAX);
+                    continue;
+                }
+                drop(ring);
+                if self.nonblocking() {
+                    return partial(written, Errno::EAGAIN);
+                }
+                if current_has_signal() {
+                    return partial(written, Errno::EINTR);
+                }
+                self.buffer.lock().writers.block_current();
+                schedule();
+            }
+        }
+        Ok(written)
+    }
+
+    fn stat(&self) -> Stat {
+        Stat::new(0, Stat::S_IFIFO | 0o600, 1, 0)
+    }
+
+    fn status_flags(&self) -> OpenFlags {
+        *self.status.lock()
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        *self.status.lock() = flags;
+    }
+}
+
+// a write cut short returns what it wrote, or `errno` if that is nothing
+fn partial(written: usize, errno: Errno) -> Result<usize, Errno> {
+    if written > 0 {
+        Ok(written)
+    } else {
+        Err(errno)
+    }
+}
+
+impl Drop for Pipe {
+    // the tasks waiting on the other end find out that this one is closed
+    fn drop(&mut self) {
+        let mut ring = self.buffer.lock();
+        ring.readers.wake(usize::MAX);
+        ring.writers.wake(usize::MAX);
+    }
+}
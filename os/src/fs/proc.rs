@@ -0,0 +1,195 @@
+//! A read-only /proc with a directory for each process, holding its `stat`
+
+use super::{dirents, Dirent, File, Stat, DT_DIR, DT_REG};
+use crate::mm::UserBuffer;
+use crate::process::processor::get_current_process;
+use crate::process::{pid2process, processes, ProcessControlBlock, TaskState};
+use crate::sync::Mutex;
+use crate::syscall::Errno;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// inode numbers of /proc, apart from those on the disk
+const ROOT_INO: u64 = 1 << 32;
+const PROCESS_INO: u64 = ROOT_INO + 1;
+
+/// What a path in /proc names
+#[derive(Clone, Copy)]
+pub enum ProcEntry {
+    Root,
+    // the directory of a process
+    Process(usize),
+    Stat(usize),
+}
+
+impl ProcEntry {
+    /// The entry at the absolute `path`, or None if the path is not in /proc.
+    /// ENOENT if it is in /proc, but there is no such entry.
+    pub fn lookup(path: &str) -> Option<Result<Self, Errno>> {
+        let rest = path.strip_prefix("/proc")?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        let mut parts = rest.split('/').filter(|part| !part.is_empty());
+        let Some(pid) = parts.next() else {
+            return Some(Ok(Self::Root));
+        };
+        let pid = match pid {
+            "self" => get_current_process().unwrap().get_pid(),
+            pid => match pid.parse() {
+                Ok(pid) if pid2process(pid).is_some() => pid,
+                _ => return Some(Err(Errno::ENOENT)),
+            },
+        };
+        Some(match (parts.next(), parts.next()) {
+            (None, _) => Ok(Self::Process(pid)),
+            (Some("stat"), None) => Ok(Self::Stat(pid)),
+            _ => Err(Errno::ENOENT),
+        })
+    }
+
+    pub fn stat(&self) -> Stat {
+        match *self {
+            Self::Root => Stat::new(ROOT_INO, Stat::S_IFDIR | 0o555, 2, 0),
+            Self::Process(pid) => {
+                Stat::new(PROCESS_INO + 2 * pid as u64, Stat::S_IFDIR | 0o555, 2, 0)
+            }
+            // the size is not known before it is read, as on Linux
+            Self::Stat(pid) => Stat::new(
+                PROCESS_INO + 2 * pid as u64 + 1,
+                Stat::S_IFREG | 0o444,
+                1,
+                0,
+            ),
+        }
+    }
+
+    /// Its absolute path, with the pid for `self`
+    pub fn path(&self) -> String {
+        match *self {
+            Self::Root => "/proc".into(),
+            Self::Process(pid) => format!("/proc/{}", pid),
+            Self::Stat(pid) => format!("/proc/{}/stat", pid),
+        }
+    }
+
+    /// Open the entry, a snapshot of it is read from then on. ESRCH if the process is gone.
+    pub fn open(self) -> Result<ProcFile, Errno> {
+        let content = match self {
+            Self::Root => {
+                let mut entries: Vec<Dirent> =
+                    vec![(ROOT_INO, DT_DIR, ".".into()), (0, DT_DIR, "..".into())];
+                for process in processes() {
+                    let pid = process.get_pid();
+                    entries.push((Self::Process(pid).stat().ino, DT_DIR, format!("{}", pid)));
+                }
+                Content::Dir(entries)
+            }
+            Self::Process(pid) => Content::Dir(vec![
+                (self.stat().ino, DT_DIR, ".".into()),
+                (ROOT_INO, DT_DIR, "..".into()),
+                (Self::Stat(pid).stat().ino, DT_REG, "stat".into()),
+            ]),
+            Self::Stat(pid) => {
+                let process = pid2process(pid).ok_or(Errno::ESRCH)?;
+                Content::File(process_stat(&process).into_bytes())
+            }
+        };
+        Ok(ProcFile {
+            entry: self,
+            content,
+            offset: Mutex::new(0),
+        })
+    }
+}
+
+/// A line like Linux's /proc/<pid>/stat, up to num_threads. The fields that are not kept are 0,
+/// but the priority.
+fn process_stat(process: &ProcessControlBlock) -> String {
+    let ppid = process.get_ppid();
+    let inner = process.inner.lock();
+    let threads: Vec<TaskState> = inner
+        .threads
+        .iter()
+        .flatten()
+        .map(|thread| thread.inner.lock().state)
+        .filter(|&state| state != TaskState::Zombie)
+        .collect();
+    let state = if inner.zombie {
+        'Z'
+    } else if threads
+        .iter()
+        .any(|&state| state == TaskState::Runnable || state == TaskState::Running)
+    {
+        'R'
+    } else {
+        'S'
+    };
+    format!(
+        "{} ({}) {} {} 0 0 0 0 0 0 0 0 0 0 0 0 0 20 0 {}\n",
+        process.get_pid(),
+        inner.name,
+        state,
+        ppid,
+        threads.len()
+    )
+}
+
+enum Content {
+    Dir(Vec<Dirent>),
+    File(Vec<u8>),
+}
+
+/// An open entry of /proc
+pub struct ProcFile {
+    entry: ProcEntry,
+    content: Content,
+    // a byte offset in a file, the number of entries read in a directory
+    offset: Mutex<usize>,
+}
+
+impl File for ProcFile {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn read(&self, mut buf: UserBuffer) -> Result<usize, Errno> {
+        let Content::File(data) = &self.content else {
+            return Err(Errno::EISDIR);
+        };
+        let mut offset = self.offset.lock();
+        let mut total = 0;
+        for slice in buf.buffers.iter_mut() {
+            let rest = &data[*offset..];
+            let n = slice.len().min(rest.len());
+            slice[..n].copy_from_slice(&rest[..n]);
+            *offset += n;
+            total += n;
+        }
+        Ok(total)
+    }
+
+    fn write(&self, _buf: UserBuffer) -> Result<usize, Errno> {
+        Err(Errno::EBADF)
+    }
+
+    fn stat(&self) -> Stat {
+        self.entry.stat()
+    }
+
+    fn getdents(&self, len: usize) -> Result<Vec<u8>, Errno> {
+        match &self.content {
+            Content::Dir(entries) => dirents(entries, &mut self.offset.lock(), len),
+            Content::File(_) => Err(Errno::ENOTDIR),
+        }
+    }
+
+    fn path(&self) -> Option<String> {
+        Some(self.entry.path())
+    }
+}
@@ -1,8 +1,37 @@
-use super::File;
+use super::{File, OpenFlags, Stat};
 use crate::mm::UserBuffer;
-use crate::process::mark_current_suspend;
 use crate::process::processor::schedule;
-use crate::sbi::console_getchar;
+use crate::process::signal::console_interrupt;
+use crate::process::{current_has_signal, mark_current_suspend};
+use crate::sbi::{console_getchar, console_putchar};
+use crate::sync::Mutex;
+use crate::syscall::Errno;
+use alloc::collections::VecDeque;
+use lazy_static::lazy_static;
+
+// Ctrl-C
+const ETX: usize = 0x03;
+
+lazy_static! {
+    // console input polled but not read yet
+    static ref INPUT: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+}
+
+// status flags of the console input, which every process shares
+static STDIN_STATUS: Mutex<OpenFlags> = Mutex::new(OpenFlags::empty());
+
+/// Move the console input into the input buffer, it is also polled on timer interrupts so that
+/// Ctrl-C interrupts programs that do not read the console
+pub fn poll_console() {
+    loop {
+        match console_getchar() {
+            0 => return,
+            ETX => console_interrupt(),
+            c => INPUT.lock().push_back(c as u8),
+        }
+    }
+}
+
 ///Standard input
 pub struct Stdin;
 ///Standard output
@@ -17,29 +46,44 @@ impl File for Stdin {
         false
     }
 
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
+    // return one byte at a time, which is allowed for a terminal.
+    // EINTR if a signal arrives first, EAGAIN if there is no input and it does not block.
+    fn read(&self, mut user_buf: UserBuffer) -> Result<usize, Errno> {
+        let Some(byte) = user_buf.buffers.iter_mut().find_map(|b| b.first_mut()) else {
+            return Ok(0);
+        };
         // busy loop
-        let mut c: usize;
         loop {
-            c = console_getchar();
-            if c == 0 {
-                mark_current_suspend();
-                schedule();
-                continue;
-            } else {
-                break;
+            poll_console();
+            if let Some(c) = INPUT.lock().pop_front() {
+                *byte = c;
+                return Ok(1);
             }
+            if STDIN_STATUS.lock().contains(OpenFlags::NONBLOCK) {
+                return Err(Errno::EAGAIN);
+            }
+            if current_has_signal() {
+                return Err(Errno::EINTR);
+            }
+            mark_current_suspend();
+            schedule();
         }
-        let ch = c as u8;
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
-        }
-        1
     }
-    fn write(&self, _user_buf: UserBuffer) -> usize {
+    fn write(&self, _user_buf: UserBuffer) -> Result<usize, Errno> {
         panic!("Cannot write to stdin!");
     }
+    fn is_tty(&self) -> bool {
+        true
+    }
+    fn stat(&self) -> Stat {
+        tty_stat()
+    }
+    fn status_flags(&self) -> OpenFlags {
+        *STDIN_STATUS.lock()
+    }
+    fn set_status_flags(&self, flags: OpenFlags) {
+        *STDIN_STATUS.lock() = flags;
+    }
 }
 
 impl File for Stdout {
@@ -49,13 +93,29 @@ impl File for Stdout {
     fn writable(&self) -> bool {
         true
     }
-    fn read(&self, _user_buf: UserBuffer) -> usize {
+    fn read(&self, _user_buf: UserBuffer) -> Result<usize, Errno> {
         panic!("Cannot read from stdout!");
     }
-    fn write(&self, user_buf: UserBuffer) -> usize {
+    // bytes go out as they are, utf-8 characters may cross the buffers
+    fn write(&self, user_buf: UserBuffer) -> Result<usize, Errno> {
         for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(buffer).unwrap());
+            for &byte in buffer.iter() {
+                console_putchar(byte as usize);
+            }
         }
-        user_buf.len()
+        Ok(user_buf.len())
     }
+    fn is_tty(&self) -> bool {
+        true
+    }
+    fn stat(&self) -> Stat {
+        tty_stat()
+    }
+}
+
+// the console is a character device
+fn tty_stat() -> Stat {
+    let mut stat = Stat::new(0, Stat::S_IFCHR | 0o620, 1, 0);
+    stat.rdev = 0x500;
+    stat
 }
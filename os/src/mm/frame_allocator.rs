@@ -1,6 +1,7 @@
 use core::fmt::Debug;
 
 use super::address::{PhysicalAddr, PhysicalPageNumber};
+use super::memory_set::reclaim_frames;
 use crate::{
     config::{MEMORY_END, PAGE_SIZE},
     sync::Mutex,
@@ -9,7 +10,7 @@ use alloc::vec::Vec;
 use lazy_static::*;
 use log::info;
 
-type FrameAllocatorImpl = StackAllocator;
+type FrameAllocatorImpl = BitmapAllocator;
 
 lazy_static! {
     static ref FRAME_ALLOCATOR: Mutex<FrameAllocatorImpl> = {
@@ -24,7 +25,13 @@ lazy_static! {
     };
 }
 
+/// Allocate a frame, swapping out pages of other address spaces if there is none left
 pub fn frame_alloc() -> Option<FrameTracker> {
+    let ppn = FRAME_ALLOCATOR.lock().alloca();
+    if let Some(ppn) = ppn {
+        return Some(FrameTracker::new(ppn));
+    }
+    reclaim_frames();
     FRAME_ALLOCATOR.lock().alloca().map(FrameTracker::new)
 }
 
@@ -32,6 +39,26 @@ pub fn frame_dealloc(ppn: PhysicalPageNumber) {
     FRAME_ALLOCATOR.lock().dealloca(ppn)
 }
 
+/// Allocate `pages` physically contiguous frames starting at a multiple of `align` pages
+pub fn frame_alloc_contiguous(pages: usize, align: usize) -> Option<Vec<FrameTracker>> {
+    let base = FRAME_ALLOCATOR.lock().alloca_contiguous(pages, align)?;
+    Some(
+        (base.0..base.0 + pages)
+            .map(|ppn| FrameTracker::new(ppn.into()))
+            .collect(),
+    )
+}
+
+/// Number of frames that can still be allocated
+pub fn frame_remaining() -> usize {
+    FRAME_ALLOCATOR.lock().remaining()
+}
+
+/// Number of frames the allocator hands out, free or not
+pub fn frame_total() -> usize {
+    FRAME_ALLOCATOR.lock().total()
+}
+
 pub struct FrameTracker {
     pub ppn: PhysicalPageNumber,
 }
@@ -66,42 +93,106 @@ impl FrameTracker {
 pub trait FrameAllocator {
     fn new(start: PhysicalPageNumber, end: PhysicalPageNumber) -> Self;
     fn alloca(&mut self) -> Option<PhysicalPageNumber>;
+    /// Allocate `pages` contiguous frames, the first ppn is a multiple of `align`
+    fn alloca_contiguous(&mut self, pages: usize, align: usize) -> Option<PhysicalPageNumber>;
     fn dealloca(&mut self, ppn: PhysicalPageNumber);
+    fn remaining(&self) -> usize;
+    fn total(&self) -> usize;
 }
 
-struct StackAllocator {
-    current: usize,
+/// One bit per frame, set if the frame is allocated
+struct BitmapAllocator {
+    start: usize,
     end: usize,
-    recycled: Vec<usize>,
+    bitmap: Vec<u64>,
+    // no free frame in the words before it
+    hint: usize,
+    free: usize,
 }
 
-impl FrameAllocator for StackAllocator {
+impl BitmapAllocator {
+    fn is_allocated(&self, index: usize) -> bool {
+        self.bitmap[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set(&mut self, index: usize) {
+        self.bitmap[index / 64] |= 1 << (index % 64);
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.bitmap[index / 64] &= !(1 << (index % 64));
+    }
+}
+
+impl FrameAllocator for BitmapAllocator {
     fn new(start: PhysicalPageNumber, end: PhysicalPageNumber) -> Self {
+        let frames = end.0 - start.0;
+        let mut bitmap = vec![0u64; frames.div_ceil(64)];
+        // the tail of the last word is beyond `end`, never hand it out
+        if frames % 64 != 0 {
+            *bitmap.last_mut().unwrap() = !0 << (frames % 64);
+        }
         Self {
-            current: start.into(),
-            end: end.into(),
-            recycled: Vec::new(),
+            start: start.0,
+            end: end.0,
+            bitmap,
+            hint: 0,
+            free: frames,
         }
     }
 
     fn alloca(&mut self) -> Option<PhysicalPageNumber> {
-        if let Some(ppn) = self.recycled.pop() {
-            Some(ppn.into())
-        } else {
-            if self.current == self.end {
-                return None;
+        let word = (self.hint..self.bitmap.len()).find(|&i| self.bitmap[i] != !0)?;
+        self.hint = word;
+        let index = word * 64 + self.bitmap[word].trailing_ones() as usize;
+        self.set(index);
+        self.free -= 1;
+        Some((self.start + index).into())
+    }
+
+    fn alloca_contiguous(&mut self, pages: usize, align: usize) -> Option<PhysicalPageNumber> {
+        assert!(
+            align.is_power_of_two(),
+            "align {} is not a power of 2",
+            align
+        );
+        if pages == 0 || pages > self.free {
+            return None;
+        }
+        // the first aligned frame that may be free
+        let mut index = (self.start + self.hint * 64).next_multiple_of(align) - self.start;
+        while index + pages <= self.end - self.start {
+            match (index..index + pages).rev().find(|&i| self.is_allocated(i)) {
+                // skip to the first aligned index after the allocated frame
+                Some(used) => index = (self.start + used + 1).next_multiple_of(align) - self.start,
+                None => {
+                    for i in index..index + pages {
+                        self.set(i);
+                    }
+                    self.free -= pages;
+                    return Some((self.start + index).into());
+                }
             }
-            let ret = self.current;
-            self.current += 1;
-            Some(ret.into())
         }
+        None
     }
 
     fn dealloca(&mut self, ppn: PhysicalPageNumber) {
-        if ppn.0 >= self.current || self.recycled.iter().any(|&v| v == ppn.0) {
+        if ppn.0 < self.start || ppn.0 >= self.end || !self.is_allocated(ppn.0 - self.start) {
             panic!("Frame ppn={:#x} has not been allocated!", ppn.0);
         }
-        self.recycled.push(ppn.into())
+        let index = ppn.0 - self.start;
+        self.clear(index);
+        self.hint = self.hint.min(index / 64);
+        self.free += 1;
+    }
+
+    fn remaining(&self) -> usize {
+        self.free
+    }
+
+    fn total(&self) -> usize {
+        self.end - self.start
     }
 }
 
@@ -120,5 +211,11 @@ pub fn frame_allocator_test() {
         v.push(frame);
     }
     drop(v);
+    let frames = frame_alloc_contiguous(4, 4).unwrap();
+    assert_eq!(frames[0].ppn.0 % 4, 0);
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.ppn.0, frames[0].ppn.0 + i);
+    }
+    drop(frames);
     info!("frame_allocator_test PASSED!");
 }
@@ -1,21 +1,35 @@
-use core::arch::asm;
+use core::{arch::asm, fmt::Debug};
 
-use alloc::{collections::BTreeMap, vec::Vec};
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
 use bitflags::bitflags;
+use fs::Inode;
 use log::info;
 use riscv::register::satp;
-use xmas_elf::program::ProgramHeader;
+use xmas_elf::{
+    dynamic::Tag,
+    header::{Class, Data, Machine},
+    program::{ProgramHeader, SegmentData},
+    ElfFile,
+};
 
 use crate::{
-    config::{MEMORY_END, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE},
+    config::{
+        ELF_DYN_BASE, ELF_INTERP_BASE, FRAME_RESERVED, MEMORY_END, MMAP_BASE, PAGE_SIZE,
+        SIGRETURN_TRAMPOLINE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_GUARD, USER_STACK_LIMIT,
+        USER_STACK_SIZE, USER_STACK_TOP,
+    },
+    fs::{open_file, OpenFlags, ROOT_INODE},
     mm::address::{PhysicalAddr, StepByOne},
+    process::processes,
     qemu::MMIO,
+    syscall::Errno,
 };
 
 use super::{
     address::{PhysicalPageNumber, VPNRange, VirtualAddr, VirtualPageNumber},
-    frame_allocator::{frame_alloc, FrameTracker},
-    page_table::{PTEFlags, PageTable, PageTableEntry},
+    frame_allocator::{frame_alloc, frame_remaining, FrameTracker},
+    page_table::{PTEFlags, PageSize, PageTable, PageTableEntry},
+    swap::{swap_out, SwapSlot},
 };
 
 extern "C" {
@@ -29,26 +43,116 @@ extern "C" {
     fn ebss(); // end of block start symbol
     fn ekernel();
     fn strampoline();
+    fn __sigreturn();
+    fn __sigreturn_end();
+}
+
+/// Types of the auxiliary vector entries, see include/uapi/linux/auxvec.h
+pub struct Aux;
+
+impl Aux {
+    pub const NULL: usize = 0;
+    pub const PHDR: usize = 3;
+    pub const PHENT: usize = 4;
+    pub const PHNUM: usize = 5;
+    pub const PAGESZ: usize = 6;
+    pub const BASE: usize = 7;
+    pub const FLAGS: usize = 8;
+    pub const ENTRY: usize = 9;
+    pub const RANDOM: usize = 25;
+}
+
+/// Why an ELF file can not be loaded by `MemorySet::from_elf`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    /// Not an ELF file, or not a riscv64 executable
+    NotExecutable,
+    /// The program headers or segments are malformed
+    Invalid,
+    /// Not enough free frames for the segments or the initial stack
+    NoMemory,
+    /// The interpreter named by PT_INTERP does not exist
+    NoInterpreter,
+}
+
+/// Why a page fault can not be resolved by `MemorySet::handle_page_fault`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultError {
+    /// The page is not mapped for the access, SIGSEGV
+    Illegal,
+    /// No frame is left for the page, even after swapping out, SIGBUS
+    NoMemory,
+}
+
+/// No frame is left for a page
+#[derive(Debug, Clone, Copy)]
+pub struct NoFrame;
+
+impl From<NoFrame> for FaultError {
+    fn from(_: NoFrame) -> Self {
+        Self::NoMemory
+    }
+}
+
+/// A frame for a page faulted in, swapping out pages of every process if need be. It may not take
+/// the `FRAME_RESERVED` frames, which are left for page tables and kernel stacks.
+fn user_frame() -> Result<FrameTracker, NoFrame> {
+    if frame_remaining() <= FRAME_RESERVED {
+        reclaim_frames();
+        if frame_remaining() <= FRAME_RESERVED {
+            return Err(NoFrame);
+        }
+    }
+    frame_alloc().ok_or(NoFrame)
 }
 
 #[derive(Debug)]
 pub struct MemorySet {
     pub page_table: PageTable,
     areas: Vec<MapArea>,
+    // the heap area starts at heap_bottom and ends at the page holding brk
+    heap_bottom: usize,
+    brk: usize,
+    // where the clock of page replacement resumes, see `swap_out_one`
+    clock_hand: VirtualPageNumber,
+    // the user stack grows down from here on demand, 0 if there is no user stack
+    stack_top: VirtualPageNumber,
 }
 
 #[derive(Debug)]
 pub struct MapArea {
     vpn_range: VPNRange,
-    date_frames: BTreeMap<VirtualPageNumber, FrameTracker>,
+    // Frames may be shared with other address spaces after fork, see `MemorySet::from_other_proc`
+    date_frames: BTreeMap<VirtualPageNumber, Arc<FrameTracker>>,
+    // Pages swapped out, slots are shared after fork like frames
+    swapped: BTreeMap<VirtualPageNumber, Arc<SwapSlot>>,
     map_type: MapType,
     map_perm: MapPermission,
+    // MAP_SHARED, frames are shared writable after fork instead of copy-on-write
+    shared: bool,
+    file: Option<FileMapping>,
+}
+
+/// The file backing a mmap area
+#[derive(Clone)]
+pub struct FileMapping {
+    inode: Arc<Inode>,
+    // file offset of the first page of the area
+    offset: usize,
+}
+
+impl Debug for FileMapping {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!("FileMapping: offset={:#x}", self.offset))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MapType {
     Identical,
     Framed,
+    /// Like `Framed`, but a frame is only allocated when the page is first accessed
+    Lazy,
 }
 
 bitflags! {
@@ -61,6 +165,17 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags of mmap, the values are the same as Linux
+    #[derive(Debug, Clone, Copy)]
+    pub struct MmapFlags : u32 {
+      const SHARED = 0x01;
+      const PRIVATE = 0x02;
+      const FIXED = 0x10;
+      const ANONYMOUS = 0x20;
+    }
+}
+
 impl MapArea {
     pub fn new(
         start_va: VirtualAddr,
@@ -71,8 +186,11 @@ impl MapArea {
         Self {
             vpn_range: VPNRange::new(start_va.floor(), end_va.ceil()),
             date_frames: BTreeMap::new(),
+            swapped: BTreeMap::new(),
             map_type,
             map_perm,
+            shared: false,
+            file: None,
         }
     }
 
@@ -80,8 +198,46 @@ impl MapArea {
         Self {
             vpn_range: area.vpn_range,
             date_frames: BTreeMap::new(),
+            swapped: BTreeMap::new(),
             map_type: area.map_type,
             map_perm: area.map_perm,
+            shared: area.shared,
+            file: area.file.clone(),
+        }
+    }
+
+    /// Split the area at `at`, `[at, end)` with its frames is moved into the returned area.
+    pub fn split_off(&mut self, at: VirtualPageNumber) -> Self {
+        let mut tail = MapArea::from_other(self);
+        tail.vpn_range = VPNRange::new(at, self.vpn_range.get_end());
+        if let Some(file) = tail.file.as_mut() {
+            file.offset += (at.0 - self.vpn_range.get_start().0) * PAGE_SIZE;
+        }
+        tail.date_frames = self.date_frames.split_off(&at);
+        tail.swapped = self.swapped.split_off(&at);
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), at);
+        tail
+    }
+
+    fn file_offset(&self, vpn: VirtualPageNumber) -> usize {
+        let file = self.file.as_ref().unwrap();
+        file.offset + (vpn.0 - self.vpn_range.get_start().0) * PAGE_SIZE
+    }
+
+    /// Write a page of a shared file mapping back to the file.
+    /// Mapping never changes the file size, so the part beyond the end of file is dropped.
+    pub fn write_back(&self, vpn: VirtualPageNumber) {
+        if !self.shared {
+            return;
+        }
+        if let (Some(file), Some(frame)) = (&self.file, self.date_frames.get(&vpn)) {
+            let offset = self.file_offset(vpn);
+            let size = file.inode.size();
+            if offset < size {
+                let len = (size - offset).min(PAGE_SIZE);
+                file.inode
+                    .write_at(offset, &frame.ppn.get_bytes_array()[..len]);
+            }
         }
     }
 
@@ -89,81 +245,195 @@ impl MapArea {
         &mut self,
         page_table: &mut PageTable,
         vpn: VirtualPageNumber,
-    ) -> Option<FrameTracker> {
-        page_table.unmap(vpn);
-        if self.map_type == MapType::Framed {
-            self.date_frames.remove(&vpn)
-        } else {
-            None
+    ) -> Option<Arc<FrameTracker>> {
+        self.write_back(vpn);
+        let frame = self.date_frames.remove(&vpn);
+        let slot = self.swapped.remove(&vpn);
+        if self.map_type != MapType::Lazy || frame.is_some() || slot.is_some() {
+            page_table.unmap(vpn);
         }
+        frame
     }
 
     pub fn map(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+        match self.map_type {
+            // lazy pages are mapped in page fault
+            MapType::Lazy => {}
+            MapType::Identical => {
+                let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
+                let mut vpn = self.vpn_range.get_start();
+                while vpn < self.vpn_range.get_end() {
+                    let size = self.identical_page_size(vpn);
+                    page_table.map_sized(vpn, PhysicalPageNumber(vpn.0), pte_flags, size);
+                    vpn.0 += size.pages();
+                }
+            }
+            MapType::Framed => {
+                for vpn in self.vpn_range {
+                    self.map_one(page_table, vpn)
+                        .expect("no frame for an eagerly mapped page");
+                }
+            }
         }
     }
 
+    /// The largest page that is aligned at `vpn` and fits in the area
+    fn identical_page_size(&self, vpn: VirtualPageNumber) -> PageSize {
+        [PageSize::Size1G, PageSize::Size2M]
+            .into_iter()
+            .find(|size| {
+                vpn.0 % size.pages() == 0 && vpn.0 + size.pages() <= self.vpn_range.get_end().0
+            })
+            .unwrap_or(PageSize::Size4K)
+    }
+
+    pub fn contains(&self, vpn: VirtualPageNumber) -> bool {
+        self.vpn_range.get_start() <= vpn && vpn < self.vpn_range.get_end()
+    }
+
     #[allow(unused)]
     pub fn unmap(&mut self, page_table: &mut PageTable) {
+        if self.map_type == MapType::Identical {
+            let mut vpn = self.vpn_range.get_start();
+            while vpn < self.vpn_range.get_end() {
+                vpn.0 += page_table.unmap(vpn).pages();
+            }
+            return;
+        }
         for vpn in self.vpn_range {
             self.unmap_one(page_table, vpn);
         }
     }
-    #[allow(unused)]
+    pub fn append_to(&mut self, page_table: &mut PageTable, new_end: VirtualPageNumber) {
+        let old_end = self.vpn_range.get_end();
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+        // lazy pages are mapped in page fault
+        if self.map_type == MapType::Lazy {
+            return;
+        }
+        for vpn in VPNRange::new(old_end, new_end) {
+            self.map_one(page_table, vpn)
+                .expect("no frame for an eagerly mapped page");
+        }
+    }
     pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtualPageNumber) {
         for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
             self.unmap_one(page_table, vpn)
         }
         self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
     }
-    // copy data into physical frames
-    pub fn copy_data(&mut self, page_table: &PageTable, data: &[u8]) {
+    // copy data into physical frames, starting `offset` bytes into the first page
+    pub fn copy_data(&mut self, page_table: &PageTable, data: &[u8], offset: usize) {
         assert!(
             self.map_type == MapType::Framed,
             "Map type identical cannot copy data\n"
         );
 
         let mut cur_vpn = self.vpn_range.get_start();
-        let mut len = data.len();
+        let mut page_offset = offset;
         let mut start = 0;
-        while len != 0 {
-            let dst: usize =
-                PhysicalAddr::from(page_table.translate(cur_vpn).unwrap().get_ppn()).into();
-            let n = len.min(PAGE_SIZE);
-            let src = &data[start..start + n];
-
-            start += PAGE_SIZE;
-            len -= n;
+        while start < data.len() {
+            let n = (PAGE_SIZE - page_offset).min(data.len() - start);
+            let dst = page_table
+                .translate(cur_vpn)
+                .unwrap()
+                .get_ppn()
+                .get_bytes_array();
+            dst[page_offset..page_offset + n].copy_from_slice(&data[start..start + n]);
 
-            unsafe { core::ptr::copy(src.as_ptr(), dst as *mut u8, n) }
+            start += n;
+            page_offset = 0;
             cur_vpn.step();
         }
     }
 
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtualPageNumber) {
+    /// Map `vpn`, a page of a lazy area is faulted in and gets its frame from `user_frame`
+    pub fn map_one(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtualPageNumber,
+    ) -> Result<(), NoFrame> {
         // first map in data_frames
         // then map in page_table. Actually, page table's map is writing data to pte which can be translated by mmu
         let ppn: PhysicalPageNumber;
         match self.map_type {
             MapType::Identical => ppn = PhysicalPageNumber(vpn.0),
-            MapType::Framed => {
-                let frame = frame_alloc().unwrap();
+            MapType::Framed | MapType::Lazy => {
+                let frame = if self.map_type == MapType::Lazy {
+                    user_frame()?
+                } else {
+                    frame_alloc().ok_or(NoFrame)?
+                };
+                if let Some(file) = &self.file {
+                    file.inode
+                        .read_at(self.file_offset(vpn), frame.ppn.get_bytes_array());
+                }
                 ppn = frame.ppn;
-                self.date_frames.insert(vpn, frame);
+                self.date_frames.insert(vpn, Arc::new(frame));
             }
         }
 
         let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
         page_table.map(vpn, ppn, pte_flags);
+        Ok(())
+    }
+
+    /// Give `vpn` a private frame and restore its write permission.
+    /// The data is copied only if the frame is still shared with another address space.
+    pub fn copy_on_write(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtualPageNumber,
+    ) -> Result<(), NoFrame> {
+        let frame = self.date_frames.get_mut(&vpn).unwrap();
+        if Arc::strong_count(frame) > 1 {
+            let new_frame = user_frame()?;
+            new_frame
+                .ppn
+                .get_bytes_array()
+                .copy_from_slice(frame.ppn.get_bytes_array());
+            *frame = Arc::new(new_frame);
+        }
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
+        page_table.remap(vpn, frame.ppn, pte_flags);
+        Ok(())
+    }
+
+    /// Whether the private frames of this area may be swapped out.
+    /// Shared mappings and kernel areas such as the trap context always stay resident.
+    fn swappable(&self) -> bool {
+        self.map_type != MapType::Identical
+            && self.map_perm.contains(MapPermission::U)
+            && !self.shared
+    }
+
+    /// Read the swapped-out page of `vpn` back into a new frame.
+    pub fn swap_in(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtualPageNumber,
+    ) -> Result<(), NoFrame> {
+        // the slot is taken only once there is a frame for it, so a failure keeps the page
+        let frame = user_frame()?;
+        let slot = self.swapped.remove(&vpn).unwrap();
+        slot.read(frame.ppn);
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
+        page_table.map(vpn, frame.ppn, pte_flags);
+        self.date_frames.insert(vpn, Arc::new(frame));
+        Ok(())
     }
 
     #[allow(unused)]
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtualPageNumber) {
-        if let MapType::Framed = self.map_type {
-            self.date_frames.remove(&vpn);
+        self.umap_one_with_data(page_table, vpn);
+    }
+}
+
+impl Drop for MapArea {
+    fn drop(&mut self) {
+        for vpn in self.date_frames.keys() {
+            self.write_back(*vpn);
         }
-        page_table.unmap(vpn);
     }
 }
 
@@ -172,6 +442,10 @@ impl MemorySet {
         Self {
             page_table: PageTable::new(),
             areas: Vec::new(),
+            heap_bottom: 0,
+            brk: 0,
+            clock_hand: VirtualPageNumber(0),
+            stack_top: VirtualPageNumber(0),
         }
     }
 
@@ -182,7 +456,6 @@ impl MemorySet {
     pub fn translate(&self, vpn: VirtualPageNumber) -> Option<PageTableEntry> {
         self.page_table.translate(vpn)
     }
-    #[allow(unused)]
     pub fn shrink_to(&mut self, start: VirtualAddr, new_end: VirtualAddr) -> bool {
         if let Some(area) = self
             .areas
@@ -195,10 +468,51 @@ impl MemorySet {
             false
         }
     }
-    pub fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
+    pub fn append_to(&mut self, start: VirtualAddr, new_end: VirtualAddr) -> bool {
+        if let Some(area) = self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_start() == start.floor())
+        {
+            area.append_to(&mut self.page_table, new_end.ceil());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Move the program break to `new_brk`.
+    ///
+    /// Return the new break on success, or the current break if `new_brk` is below the heap
+    /// bottom or the heap cannot grow into an occupied range. `brk(0)` queries the break.
+    pub fn brk(&mut self, new_brk: usize) -> usize {
+        if new_brk < self.heap_bottom {
+            return self.brk;
+        }
+        let cur_end = VirtualAddr::from(self.brk).ceil();
+        let new_end = VirtualAddr::from(new_brk).ceil();
+        if new_end > cur_end {
+            if !self.is_free(cur_end, new_end)
+                || !self.append_to(self.heap_bottom.into(), new_brk.into())
+            {
+                return self.brk;
+            }
+        } else if new_end < cur_end {
+            self.shrink_to(self.heap_bottom.into(), new_brk.into());
+        }
+        self.brk = new_brk;
+        self.brk
+    }
+    pub fn push(&mut self, map_area: MapArea, data: Option<&[u8]>) {
+        self.push_with_offset(map_area, 0, data);
+    }
+
+    /// Push an area whose data starts `offset` bytes into its first page,
+    /// e.g. an ELF segment that is not page aligned
+    pub fn push_with_offset(&mut self, mut map_area: MapArea, offset: usize, data: Option<&[u8]>) {
         map_area.map(&mut self.page_table);
         if let Some(data) = data {
-            map_area.copy_data(&self.page_table, data)
+            map_area.copy_data(&self.page_table, data, offset)
         }
         self.areas.push(map_area);
     }
@@ -336,53 +650,103 @@ impl MemorySet {
         mm_set
     }
 
-    /// Map from elf
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// Map from elf, return the memory set, the user stack top, the entry point and the auxiliary
+    /// vector for the initial stack.
+    /// A position-independent executable (ET_DYN) is loaded at `ELF_DYN_BASE`, and the
+    /// interpreter named by PT_INTERP, if any, at `ELF_INTERP_BASE`.
+    pub fn from_elf(
+        elf_data: &[u8],
+    ) -> Result<(Self, usize, usize, Vec<(usize, usize)>), ElfError> {
         let mut mm_set = MemorySet::new_bare();
         // Map trampoline
         mm_set.map_trampoline();
         // Map program header, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+        let elf = parse_elf(elf_data)?;
         let elf_header = elf.header;
-        // check magic
-        assert_eq!(
-            elf_header.pt1.magic,
-            [0x7f, 0x45, 0x4c, 0x46],
-            "invalid elf!"
-        );
-        let ph_count = elf_header.pt2.ph_count();
+        let base = match elf_header.pt2.type_().as_type() {
+            xmas_elf::header::Type::SharedObject => ELF_DYN_BASE,
+            _ => 0,
+        };
+        let mut max_end_vpn = mm_set.map_elf(&elf, base)?;
+        let entry_point = base + elf_header.pt2.entry_point() as usize;
 
-        let mut max_end_vpn = VirtualPageNumber(0);
-        for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
-                let start_va: VirtualAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtualAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
-                let perm = get_permission(&ph);
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, perm);
-                max_end_vpn = map_area.vpn_range.get_end();
-                mm_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+        // A dynamically linked program starts in its interpreter, the dynamic loader, which
+        // finds the program through auxv and finishes loading it
+        let (start, interp_base) = match elf_interp(&elf) {
+            Some(path) => {
+                let interp_data = open_file(&ROOT_INODE, path, OpenFlags::RDONLY)
+                    .map_err(|_| ElfError::NoInterpreter)?
+                    .read_all();
+                let interp = parse_elf(&interp_data)?;
+                let interp_base = match interp.header.pt2.type_().as_type() {
+                    xmas_elf::header::Type::SharedObject => ELF_INTERP_BASE,
+                    _ => 0,
+                };
+                max_end_vpn = max_end_vpn.max(mm_set.map_elf(&interp, interp_base)?);
+                (
+                    interp_base + interp.header.pt2.entry_point() as usize,
+                    interp_base,
+                )
             }
-        }
+            None => (entry_point, 0),
+        };
+        let auxv = vec![
+            (Aux::PHDR, base + elf_phdr(&elf)),
+            (Aux::PHENT, elf_header.pt2.ph_entry_size() as usize),
+            (Aux::PHNUM, elf_header.pt2.ph_count() as usize),
+            (Aux::PAGESZ, PAGE_SIZE),
+            (Aux::BASE, interp_base),
+            (Aux::FLAGS, 0),
+            (Aux::ENTRY, entry_point),
+        ];
 
-        // Map user stack
+        // Map an empty heap right after the segments of the program and the interpreter, it
+        // grows with brk
         let max_end_va: VirtualAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
-        // TODO guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        let heap_bottom: usize = max_end_va.into();
+        mm_set.push(
+            MapArea::new(
+                heap_bottom.into(),
+                heap_bottom.into(),
+                MapType::Lazy,
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            ),
+            None,
+        );
+        mm_set.heap_bottom = heap_bottom;
+        mm_set.brk = heap_bottom;
+
+        // Map user stack at the top of user space, away from the heap.
+        // It grows down on page faults, see `grow_stack`.
+        let user_stack_top = USER_STACK_TOP;
+        let user_stack_bottom = user_stack_top - USER_STACK_SIZE;
         mm_set.push(
             MapArea::new(
                 user_stack_bottom.into(),
                 user_stack_top.into(),
-                MapType::Framed,
+                MapType::Lazy,
                 MapPermission::R | MapPermission::W | MapPermission::U,
             ),
             None,
         );
+        mm_set.stack_top = VirtualAddr::from(user_stack_top).floor();
+
+        // Map the code signal handlers return to
+        let sigreturn = unsafe {
+            core::slice::from_raw_parts(
+                __sigreturn as usize as *const u8,
+                __sigreturn_end as usize - __sigreturn as usize,
+            )
+        };
+        mm_set.push(
+            MapArea::new(
+                SIGRETURN_TRAMPOLINE.into(),
+                (SIGRETURN_TRAMPOLINE + PAGE_SIZE).into(),
+                MapType::Framed,
+                MapPermission::R | MapPermission::X | MapPermission::U,
+            ),
+            Some(sigreturn),
+        );
 
         // Map TrapContext
         mm_set.push(
@@ -394,32 +758,291 @@ impl MemorySet {
             ),
             None,
         );
-        (
-            mm_set,
-            user_stack_top,
-            elf.header.pt2.entry_point() as usize,
-        )
+        Ok((mm_set, user_stack_top, start, auxv))
     }
 
-    pub fn from_other_proc(other: &MemorySet) -> Self {
+    /// Map the PT_LOAD segments of `elf` at `base` and apply its R_RISCV_RELATIVE relocations.
+    /// Return the end of the last segment.
+    fn map_elf(&mut self, elf: &ElfFile, base: usize) -> Result<VirtualPageNumber, ElfError> {
+        let mut max_end_vpn = VirtualPageNumber(0);
+        for ph in elf.program_iter() {
+            if ph.get_type() != Ok(xmas_elf::program::Type::Load) {
+                continue;
+            }
+            let (start, end) = load_range(&ph, base)?;
+            let start_va: VirtualAddr = start.into();
+            let map_area = MapArea::new(start_va, end.into(), MapType::Framed, get_permission(&ph));
+            let range = map_area.vpn_range;
+            // segments may not share a page, with each other or with the interpreter
+            if self.areas.iter().any(|area| {
+                range.get_start() < area.vpn_range.get_end()
+                    && range.get_end() > area.vpn_range.get_start()
+            }) {
+                return Err(ElfError::Invalid);
+            }
+            if frame_remaining() < range.get_end().0 - range.get_start().0 + FRAME_RESERVED {
+                return Err(ElfError::NoMemory);
+            }
+            max_end_vpn = max_end_vpn.max(range.get_end());
+            // in the file, checked by `parse_elf`
+            let offset = ph.offset() as usize;
+            self.push_with_offset(
+                map_area,
+                start_va.get_offset(),
+                Some(&elf.input[offset..offset + ph.file_size() as usize]),
+            );
+        }
+        if max_end_vpn.0 == 0 {
+            return Err(ElfError::NotExecutable);
+        }
+        if base != 0 {
+            self.relocate(elf, base)?;
+        }
+        Ok(max_end_vpn)
+    }
+
+    /// Apply the R_RISCV_RELATIVE relocations in the RELA table of `elf` loaded at `base`.
+    /// They are all a static PIE has, a dynamic loader takes care of the others.
+    fn relocate(&mut self, elf: &ElfFile, base: usize) -> Result<(), ElfError> {
+        let Some(dynamic) = elf
+            .program_iter()
+            .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Dynamic))
+        else {
+            return Ok(());
+        };
+        // xmas_elf reads the entries in place
+        if dynamic.offset() % 8 != 0 || dynamic.file_size() % DYN_SIZE != 0 {
+            return Err(ElfError::Invalid);
+        }
+        let Ok(SegmentData::Dynamic64(entries)) = dynamic.get_data(elf) else {
+            return Ok(());
+        };
+        let (mut rela, mut rela_size, mut rela_ent) = (0, 0, RELA_SIZE);
+        for entry in entries {
+            match entry.get_tag() {
+                Ok(Tag::Rela) => rela = entry.get_ptr().unwrap() as usize,
+                Ok(Tag::RelaSize) => rela_size = entry.get_val().unwrap() as usize,
+                Ok(Tag::RelaEnt) => rela_ent = entry.get_val().unwrap() as usize,
+                _ => {}
+            }
+        }
+        if rela_size == 0 {
+            return Ok(());
+        }
+        if rela_ent < RELA_SIZE {
+            return Err(ElfError::Invalid);
+        }
+        let table = elf_offset(elf, rela)
+            .and_then(|offset| elf.input.get(offset..offset.checked_add(rela_size)?))
+            .ok_or(ElfError::Invalid)?;
+        for entry in table.chunks_exact(rela_ent) {
+            let word = |n: usize| u64::from_le_bytes(entry[n * 8..n * 8 + 8].try_into().unwrap());
+            let (r_offset, r_info, r_addend) = (word(0), word(1), word(2));
+            if r_info & 0xffff_ffff == R_RISCV_RELATIVE {
+                let value = base.wrapping_add(r_addend as usize);
+                self.write_loaded(base.wrapping_add(r_offset as usize), &value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write to loaded segments, which may be read-only for the user
+    fn write_loaded(&mut self, va: usize, bytes: &[u8]) -> Result<(), ElfError> {
+        for (i, &byte) in bytes.iter().enumerate() {
+            let va = VirtualAddr::from(va.wrapping_add(i));
+            let pte = self
+                .page_table
+                .translate(va.floor())
+                .filter(|pte| pte.is_valid() && pte.flags().contains(PTEFlags::U))
+                .ok_or(ElfError::Invalid)?;
+            pte.get_ppn().get_bytes_array()[va.get_offset()] = byte;
+        }
+        Ok(())
+    }
+
+    /// Copy an address space for fork.
+    ///
+    /// User frames are not copied but shared read-only by both address spaces, the first write
+    /// from either side traps into `copy_on_write`. Kernel-only areas such as the trap context are
+    /// still copied eagerly, because the kernel writes them through physical addresses.
+    pub fn from_other_proc(other: &mut MemorySet) -> Self {
         let mut this = MemorySet::new_bare();
+        this.heap_bottom = other.heap_bottom;
+        this.brk = other.brk;
+        this.stack_top = other.stack_top;
         // Map trampoline
         this.map_trampoline();
 
-        for other_area in other.areas.iter() {
-            let area = MapArea::from_other(other_area);
-            this.push(area, None);
-            // copy data
-            for vpn in other_area.vpn_range {
-                let src = other.translate(vpn).unwrap().get_ppn();
-                let dst = this.translate(vpn).unwrap().get_ppn();
-                dst.get_bytes_array().copy_from_slice(src.get_bytes_array());
+        for other_area in other.areas.iter_mut() {
+            let mut area = MapArea::from_other(other_area);
+            if other_area.shared {
+                // Both sides must see the same frames, so fault in the whole area before sharing
+                let pte_flags = PTEFlags::from_bits(other_area.map_perm.bits()).unwrap();
+                for vpn in other_area.vpn_range {
+                    if !other_area.date_frames.contains_key(&vpn) {
+                        other_area
+                            .map_one(&mut other.page_table, vpn)
+                            .expect("no frame for a shared page");
+                    }
+                    let frame = other_area.date_frames.get(&vpn).unwrap();
+                    this.page_table.map(vpn, frame.ppn, pte_flags);
+                    area.date_frames.insert(vpn, frame.clone());
+                }
+                this.areas.push(area);
+            } else if other_area.map_perm.contains(MapPermission::U) {
+                let pte_flags =
+                    PTEFlags::from_bits(other_area.map_perm.bits()).unwrap() - PTEFlags::W;
+                for (vpn, frame) in other_area.date_frames.iter() {
+                    other.page_table.remap(*vpn, frame.ppn, pte_flags);
+                    this.page_table.map(*vpn, frame.ppn, pte_flags);
+                    area.date_frames.insert(*vpn, frame.clone());
+                }
+                for (vpn, slot) in other_area.swapped.iter() {
+                    this.page_table.swap_out(*vpn, slot.id);
+                    area.swapped.insert(*vpn, slot.clone());
+                }
+                this.areas.push(area);
+            } else {
+                this.push(area, None);
+                // copy data
+                for vpn in other_area.vpn_range {
+                    let src = other.page_table.translate(vpn).unwrap().get_ppn();
+                    let dst = this.translate(vpn).unwrap().get_ppn();
+                    dst.get_bytes_array().copy_from_slice(src.get_bytes_array());
+                }
             }
         }
 
         this
     }
 
+    /// Resolve a page fault at `vpn` caused by an `access` (one of R, W, X).
+    ///
+    /// A fault is legal if `vpn` lies in an area that permits the access and either the page is
+    /// lazy and has never been touched, it is swapped out, or it is a write to a copy-on-write
+    /// page. Return `Illegal` if the fault is illegal, or `NoMemory` if there is no frame for the
+    /// page, in both cases the task should be killed.
+    pub fn handle_page_fault(
+        &mut self,
+        vpn: VirtualPageNumber,
+        access: MapPermission,
+    ) -> Result<(), FaultError> {
+        self.reclaim(1);
+        self.resolve_page_fault(vpn, access)
+    }
+
+    fn resolve_page_fault(
+        &mut self,
+        vpn: VirtualPageNumber,
+        access: MapPermission,
+    ) -> Result<(), FaultError> {
+        let area = match self.areas.iter_mut().find(|area| area.contains(vpn)) {
+            Some(area) => area,
+            None => return self.grow_stack(vpn, access),
+        };
+        if !area.map_perm.contains(access | MapPermission::U) {
+            return Err(FaultError::Illegal);
+        }
+        match self.page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() => {
+                if access.contains(MapPermission::W)
+                    && !pte.writable()
+                    && area.date_frames.contains_key(&vpn)
+                {
+                    Ok(area.copy_on_write(&mut self.page_table, vpn)?)
+                } else {
+                    Err(FaultError::Illegal)
+                }
+            }
+            Some(pte) if pte.is_swapped() => Ok(area.swap_in(&mut self.page_table, vpn)?),
+            _ => {
+                if area.map_type == MapType::Lazy {
+                    Ok(area.map_one(&mut self.page_table, vpn)?)
+                } else {
+                    Err(FaultError::Illegal)
+                }
+            }
+        }
+    }
+
+    /// Whether `vpn` is in a MAP_SHARED area, whose frames stay where they are as long as it is
+    /// mapped: they are neither copied on write nor swapped out.
+    pub fn is_shared(&self, vpn: VirtualPageNumber) -> bool {
+        self.areas
+            .iter()
+            .find(|area| area.contains(vpn))
+            .is_some_and(|area| area.shared)
+    }
+
+    /// Swap out pages until `pages` frames plus `FRAME_RESERVED` can be allocated, or nothing is
+    /// left to swap out.
+    fn reclaim(&mut self, pages: usize) {
+        while frame_remaining() < pages + FRAME_RESERVED && self.swap_out_one() {}
+    }
+
+    /// Choose a victim page with the clock (second chance) algorithm and swap it out.
+    ///
+    /// The clock sweeps the swappable resident pages in address order, resuming at `clock_hand`.
+    /// A page accessed since the last sweep has its A bit cleared and is skipped, the first page
+    /// found with A clear is the victim. Frames still shared copy-on-write are skipped, as the
+    /// other address space maps them too. Return false if there is no victim or the swap area is
+    /// full.
+    fn swap_out_one(&mut self) -> bool {
+        // the first sweep may only clear A bits, the second one finds a victim if there is any
+        let mut wraps = 0;
+        let (i, vpn) = loop {
+            match self.next_candidate(self.clock_hand) {
+                Some((i, vpn)) => {
+                    self.clock_hand = VirtualPageNumber(vpn.0 + 1);
+                    let pte = self.page_table.translate(vpn).unwrap();
+                    if !pte.accessed() {
+                        break (i, vpn);
+                    }
+                    self.page_table
+                        .remap(vpn, pte.get_ppn(), pte.flags() - PTEFlags::A);
+                }
+                None if wraps == 2 => return false,
+                None => {
+                    wraps += 1;
+                    self.clock_hand = VirtualPageNumber(0);
+                }
+            }
+        };
+        let area = &mut self.areas[i];
+        let frame = area.date_frames.remove(&vpn).unwrap();
+        let ret = match swap_out(frame.ppn) {
+            Some(slot) => {
+                self.page_table.swap_out(vpn, slot.id);
+                area.swapped.insert(vpn, Arc::new(slot));
+                true
+            }
+            None => {
+                area.date_frames.insert(vpn, frame);
+                false
+            }
+        };
+        // cleared A bits and the victim's pte may be cached
+        unsafe {
+            asm!("sfence.vma");
+        }
+        ret
+    }
+
+    /// The lowest page from `from` on the clock may swap out, and the index of its area
+    fn next_candidate(&self, from: VirtualPageNumber) -> Option<(usize, VirtualPageNumber)> {
+        self.areas
+            .iter()
+            .enumerate()
+            .filter(|(_, area)| area.swappable())
+            .filter_map(|(i, area)| {
+                area.date_frames
+                    .range(from..)
+                    .find(|(_, frame)| Arc::strong_count(frame) == 1)
+                    .map(|(vpn, _)| (i, *vpn))
+            })
+            .min_by_key(|(_, vpn)| *vpn)
+    }
+
     pub fn activate(&self) {
         let _satp = self.page_table.get_token();
         unsafe {
@@ -432,27 +1055,138 @@ impl MemorySet {
     pub fn recycle_data_pages(&mut self) {
         self.areas.clear();
     }
-    //mmap
-    pub fn mmap(&mut self, start: usize, len: usize, port: usize) -> Result<(), ()> {
+    /// Pages `[limit, top)` the user stack may grow into, and the guard pages below them.
+    /// Both ranges are empty if there is no user stack.
+    fn stack_range(&self) -> (VPNRange, VPNRange) {
+        if self.stack_top.0 == 0 {
+            let empty = VPNRange::new(self.stack_top, self.stack_top);
+            return (empty, empty);
+        }
+        let limit = VirtualPageNumber(self.stack_top.0 - USER_STACK_LIMIT / PAGE_SIZE);
+        let guard = VirtualPageNumber(limit.0 - USER_STACK_GUARD / PAGE_SIZE);
+        (
+            VPNRange::new(limit, self.stack_top),
+            VPNRange::new(guard, limit),
+        )
+    }
+
+    /// Grow the user stack down to `vpn`, as long as it stays within `USER_STACK_LIMIT`.
+    fn grow_stack(
+        &mut self,
+        vpn: VirtualPageNumber,
+        access: MapPermission,
+    ) -> Result<(), FaultError> {
+        let (stack, _) = self.stack_range();
+        if vpn < stack.get_start() || vpn >= stack.get_end() {
+            return Err(FaultError::Illegal);
+        }
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_end() == stack.get_end())
+            .ok_or(FaultError::Illegal)?;
+        if !area.map_perm.contains(access | MapPermission::U) {
+            return Err(FaultError::Illegal);
+        }
+        // the pages in between are lazy and mapped when touched
+        area.vpn_range = VPNRange::new(vpn, area.vpn_range.get_end());
+        Ok(area.map_one(&mut self.page_table, vpn)?)
+    }
+
+    /// Whether `va` is in the guard pages below the user stack, a fault there is a stack overflow
+    pub fn is_stack_guard(&self, va: usize) -> bool {
+        let (_, guard) = self.stack_range();
+        let vpn = VirtualAddr::from(va).floor();
+        guard.get_start() <= vpn && vpn < guard.get_end()
+    }
+
+    fn is_free(&self, start_vpn: VirtualPageNumber, end_vpn: VirtualPageNumber) -> bool {
+        // user space ends at the stack top
+        if end_vpn > VirtualAddr::from(USER_STACK_TOP).floor() {
+            return false;
+        }
+        // the stack may grow into its range at any time, and the guard must stay unmapped
+        let (stack, guard) = self.stack_range();
+        let reserved = VPNRange::new(guard.get_start(), stack.get_end());
+        !self
+            .areas
+            .iter()
+            .map(|area| area.vpn_range)
+            .chain(core::iter::once(reserved))
+            .any(|range| start_vpn < range.get_end() && end_vpn > range.get_start())
+    }
+
+    /// Find the lowest free range of `pages` pages from `MMAP_BASE`
+    fn find_free_area(&self, pages: usize) -> VirtualPageNumber {
+        let mut start_vpn = VirtualAddr::from(MMAP_BASE).floor();
+        while let Some(area) = self.areas.iter().find(|area| {
+            start_vpn.0 < area.vpn_range.get_end().0
+                && start_vpn.0 + pages > area.vpn_range.get_start().0
+        }) {
+            start_vpn = area.vpn_range.get_end();
+        }
+        start_vpn
+    }
+
+    /// Map a new area and return its start address.
+    ///
+    /// `file` is the inode and the offset in it to map, ignored if `flags` contains `ANONYMOUS`.
+    /// Without `FIXED`, `start` is a hint, a free range is chosen by the kernel if it is 0 or taken.
+    /// Pages are loaded lazily, for MAP_PRIVATE a write only changes the private copy in memory,
+    /// while for MAP_SHARED it is written back to the file on munmap, msync and exit.
+    /// EINVAL for bad arguments or a `FIXED` range that is taken, ENOMEM if there is no room.
+    pub fn mmap(
+        &mut self,
+        start: usize,
+        len: usize,
+        port: usize,
+        flags: MmapFlags,
+        file: Option<(Arc<Inode>, usize)>,
+    ) -> Result<usize, Errno> {
         if len == 0 {
-            return Ok(());
+            return Ok(start);
         }
 
         if start % PAGE_SIZE != 0 || (port & 0x7 == 0) || (port & !0x7 != 0) {
-            return Err(());
+            return Err(Errno::EINVAL);
+        }
+        // exactly one of SHARED and PRIVATE
+        if flags.contains(MmapFlags::SHARED) == flags.contains(MmapFlags::PRIVATE) {
+            return Err(Errno::EINVAL);
         }
 
-        let start_va = VirtualAddr::from(start);
-        let end_va = VirtualAddr::from(start + len);
+        let file = if flags.contains(MmapFlags::ANONYMOUS) {
+            None
+        } else {
+            match file {
+                Some((inode, offset)) if offset % PAGE_SIZE == 0 => {
+                    Some(FileMapping { inode, offset })
+                }
+                Some(_) => return Err(Errno::EINVAL),
+                None => return Err(Errno::EBADF),
+            }
+        };
+
+        if len > USER_STACK_TOP {
+            return Err(Errno::ENOMEM);
+        }
+        let pages = VirtualAddr::from(len).ceil().0;
+        let hint = VirtualAddr::from(start).floor();
+        let start_vpn = if flags.contains(MmapFlags::FIXED)
+            || (start != 0 && self.is_free(hint, VirtualPageNumber(hint.0 + pages)))
+        {
+            hint
+        } else {
+            self.find_free_area(pages)
+        };
+        let end_vpn = VirtualPageNumber(start_vpn.0 + pages);
         // check
-        if self.areas.iter().any(|area| {
-            let range = &area.vpn_range;
-            let range_start = range.get_start();
-            let range_end = range.get_end();
-            (start_va.floor() >= range_start && start_va.floor() < range_end)
-                || (end_va.ceil() > range_start && end_va.floor() < range_end)
-        }) {
-            return Err(());
+        if !self.is_free(start_vpn, end_vpn) {
+            return Err(if flags.contains(MmapFlags::FIXED) {
+                Errno::EINVAL
+            } else {
+                Errno::ENOMEM
+            });
         }
 
         let mut map_perm = MapPermission::U;
@@ -467,104 +1201,189 @@ impl MemorySet {
             map_perm |= MapPermission::X;
         }
 
-        // todo
-        let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+        let mut map_area = MapArea::new(start_vpn.into(), end_vpn.into(), MapType::Lazy, map_perm);
+        map_area.shared = flags.contains(MmapFlags::SHARED);
+        map_area.file = file;
 
         self.push(map_area, None);
+        Ok(VirtualAddr::from(start_vpn).into())
+    }
+
+    /// Unmap `[start, start + len)`, which must lie in a single area of user memory, EINVAL
+    /// otherwise. The area is shrunk or split if only part of it is unmapped.
+    pub fn munmap(&mut self, start: usize, len: usize) -> Result<(), Errno> {
+        let start_va = VirtualAddr::from(start);
+
+        if len == 0 || !start_va.aligned() {
+            return Err(Errno::EINVAL);
+        }
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= USER_STACK_TOP)
+            .ok_or(Errno::EINVAL)?;
+
+        let start_vpn = start_va.floor();
+        let end_vpn = VirtualAddr::from(end).ceil();
+        let sigreturn = VirtualAddr::from(SIGRETURN_TRAMPOLINE).floor();
+
+        // the trap contexts and the code signal handlers return to belong to the kernel
+        let index = self
+            .areas
+            .iter()
+            .position(|area| {
+                area.map_perm.contains(MapPermission::U)
+                    && area.vpn_range.get_start() != sigreturn
+                    && start_vpn >= area.vpn_range.get_start()
+                    && end_vpn <= area.vpn_range.get_end()
+            })
+            .ok_or(Errno::EINVAL)?;
+
+        let area = &mut self.areas[index];
+        let tail = if end_vpn < area.vpn_range.get_end() {
+            Some(area.split_off(end_vpn))
+        } else {
+            None
+        };
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            area.unmap_one(&mut self.page_table, vpn);
+        }
+        if start_vpn == area.vpn_range.get_start() {
+            self.areas.remove(index);
+        } else {
+            area.vpn_range = VPNRange::new(area.vpn_range.get_start(), start_vpn);
+        }
+        if let Some(tail) = tail {
+            self.areas.push(tail);
+        }
         Ok(())
     }
 
-    /// Unmap a area.
-    pub fn munmap(&mut self, start: usize, len: usize) -> Result<(), ()> {
+    /// Write the pages of shared file mappings in `[start, start + len)` back to their files.
+    pub fn msync(&self, start: usize, len: usize) -> Result<(), Errno> {
         let start_va = VirtualAddr::from(start);
 
         if !start_va.aligned() {
-            return Err(());
+            return Err(Errno::EINVAL);
         }
+        let end = start.checked_add(len).ok_or(Errno::ENOMEM)?;
 
-        let end_va = VirtualAddr::from(start + len);
         let start_vpn = start_va.floor();
-        let end_vpn = end_va.ceil();
-
-        let mut flag: u8 = 0;
-        let mut index = None;
-
-        for (i, area) in self.areas.iter_mut().enumerate() {
-            if start_vpn >= area.vpn_range.get_start() && end_vpn <= area.vpn_range.get_end() {
-                // There are four scenarios
-                //
-                // 1. start_vpn == area.vpn_range.get_start()                                         => 1
-                // 2. end_vpn == area.vpn_range.get_end()                                             => 2
-                // 3. start_vpn == area.vpn_range.get_start() && end_vpn == area.vpn_range.get_end()  => 3
-                // 4. start_vpn != area.vpn_range.get_start() && end_vpn != area.vpn_range.get_end()  => 0
-
-                if start_vpn == area.vpn_range.get_start() {
-                    flag |= 0b1;
-                }
-                if end_vpn == area.vpn_range.get_end() {
-                    flag |= 0b10;
-                }
-                if flag == 0b11 {
-                    index = Some(i);
+        let end_vpn = VirtualAddr::from(end).ceil();
+        for area in self.areas.iter() {
+            for vpn in area.date_frames.keys() {
+                if *vpn >= start_vpn && *vpn < end_vpn {
+                    area.write_back(*vpn);
                 }
-                break;
             }
         }
-        if index.is_none() {
-            return Err(());
+        Ok(())
+    }
+}
+
+/// Swap out pages of every process until a frame can be allocated again, `FRAME_RESERVED`
+/// included. Address spaces locked by the caller, e.g. the one it is forking or faulting in, are
+/// skipped, as are those of processes that are being created or torn down.
+pub fn reclaim_frames() {
+    for process in processes() {
+        if frame_remaining() > FRAME_RESERVED {
+            break;
         }
-        let index = index.unwrap();
+        if let Some(mut inner) = process.inner.try_lock() {
+            inner.memory_set.reclaim(1);
+        }
+    }
+}
 
-        match flag {
-            0b00 => {
-                let area = unsafe { self.areas.get_unchecked_mut(index) };
-                let mut map_area = MapArea::new(
-                    end_va,
-                    area.vpn_range.get_end().into(),
-                    area.map_type,
-                    area.map_perm,
-                );
+// the sizes of an Elf64_Phdr, an Elf64_Dyn and an Elf64_Rela
+const PH_SIZE: usize = 56;
+const DYN_SIZE: u64 = 16;
+const RELA_SIZE: usize = 24;
+const R_RISCV_RELATIVE: u64 = 3;
+// segments end below the sigreturn trampoline, under the stack
+const ELF_END: usize = SIGRETURN_TRAMPOLINE;
 
-                for vpn in VPNRange::new(start_vpn, end_vpn) {
-                    area.unmap_one(&mut self.page_table, vpn)
-                }
+/// Parse the header of a riscv64 executable, and check that every program header and the file
+/// range of every segment is in the file, xmas_elf indexes the file with them as they are
+fn parse_elf(elf_data: &[u8]) -> Result<ElfFile, ElfError> {
+    let elf = ElfFile::new(elf_data).map_err(|_| ElfError::NotExecutable)?;
+    let (pt1, pt2) = (&elf.header.pt1, &elf.header.pt2);
+    if pt1.class() != Class::SixtyFour
+        || pt1.data() != Data::LittleEndian
+        || pt2.machine().as_machine() != Machine::RISC_V
+        || !matches!(
+            pt2.type_().as_type(),
+            xmas_elf::header::Type::Executable | xmas_elf::header::Type::SharedObject
+        )
+    {
+        return Err(ElfError::NotExecutable);
+    }
+    let ph_offset = pt2.ph_offset() as usize;
+    let ph_end = (pt2.ph_count() as usize * PH_SIZE).checked_add(ph_offset);
+    if pt2.ph_entry_size() as usize != PH_SIZE
+        || ph_offset % 8 != 0
+        || ph_end.map_or(true, |end| end > elf_data.len())
+    {
+        return Err(ElfError::Invalid);
+    }
+    for ph in elf.program_iter() {
+        let file_end = ph.offset().checked_add(ph.file_size());
+        if file_end.map_or(true, |end| end > elf_data.len() as u64) {
+            return Err(ElfError::Invalid);
+        }
+    }
+    Ok(elf)
+}
 
-                for vpn in VPNRange::new(end_vpn, area.vpn_range.get_end()) {
-                    if let Some(frame_tracker) = area.umap_one_with_data(&mut self.page_table, vpn)
-                    {
-                        map_area.date_frames.insert(vpn, frame_tracker);
-                    }
-                }
+/// The virtual address range of the PT_LOAD segment `ph` loaded at `base`
+fn load_range(ph: &ProgramHeader, base: usize) -> Result<(usize, usize), ElfError> {
+    if ph.file_size() > ph.mem_size()
+        || ph.virtual_addr() % PAGE_SIZE as u64 != ph.offset() % PAGE_SIZE as u64
+    {
+        return Err(ElfError::Invalid);
+    }
+    let start = (ph.virtual_addr() as usize)
+        .checked_add(base)
+        .ok_or(ElfError::Invalid)?;
+    match start.checked_add(ph.mem_size() as usize) {
+        Some(end) if end <= ELF_END => Ok((start, end)),
+        _ => Err(ElfError::Invalid),
+    }
+}
 
-                area.vpn_range = VPNRange::new(area.vpn_range.get_start(), start_vpn);
-                self.push(map_area, None);
-            }
-            0b01 => {
-                let area = unsafe { self.areas.get_unchecked_mut(index) };
-                area.shrink_to(&mut self.page_table, end_vpn);
-            }
-            0b10 => {
-                let area = unsafe { self.areas.get_unchecked_mut(index) };
-                for vpn in VPNRange::new(area.vpn_range.get_start(), start_vpn) {
-                    area.unmap_one(&mut self.page_table, vpn);
-                }
-                area.vpn_range = VPNRange::new(start_vpn, end_vpn);
-            }
-            0b11 => {
-                println!("0b11 {:?} {:?}", start_va, end_va);
-                // Shrink
-                unsafe {
-                    self.areas
-                        .get_unchecked_mut(index)
-                        .unmap(&mut self.page_table)
-                }
-                // Remove
-                self.areas.remove(index);
-            }
-            _ => unreachable!(),
-        }
-        Ok(())
+/// The file offset of the virtual address `va` in a PT_LOAD segment
+fn elf_offset(elf: &ElfFile, va: usize) -> Option<usize> {
+    elf.program_iter()
+        .filter(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Load))
+        .find(|ph| (ph.virtual_addr()..ph.virtual_addr() + ph.file_size()).contains(&(va as u64)))
+        .map(|ph| (va as u64 - ph.virtual_addr() + ph.offset()) as usize)
+}
+
+/// The path in PT_INTERP, without the trailing nul
+fn elf_interp<'a>(elf: &ElfFile<'a>) -> Option<&'a str> {
+    let ph = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Interp))?;
+    let path = elf
+        .input
+        .get(ph.offset() as usize..(ph.offset() + ph.file_size()) as usize)?;
+    let len = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+    core::str::from_utf8(&path[..len]).ok()
+}
+
+/// The virtual address of the program headers, before adding the load base.
+/// Like Linux, it is only meaningful if a PT_LOAD segment covers them.
+fn elf_phdr(elf: &ElfFile) -> usize {
+    if let Some(phdr) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Phdr))
+    {
+        return phdr.virtual_addr() as usize;
     }
+    let phoff = elf.header.pt2.ph_offset();
+    elf.program_iter()
+        .filter(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Load))
+        .find(|ph| (ph.offset()..ph.offset() + ph.file_size()).contains(&phoff))
+        .map_or(0, |ph| (phoff - ph.offset() + ph.virtual_addr()) as usize)
 }
 
 fn get_permission(ph: &ProgramHeader) -> MapPermission {
@@ -581,5 +1400,3 @@ fn get_permission(ph: &ProgramHeader) -> MapPermission {
     }
     perm
 }
-
-//mmap
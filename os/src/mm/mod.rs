@@ -4,15 +4,19 @@ mod heap_allocator;
 pub mod kernel_space;
 mod memory_set;
 mod page_table;
+mod swap;
+mod user_access;
 mod user_buffer;
 
 pub use address::*;
-pub use frame_allocator::{frame_alloc, frame_dealloc, FrameTracker};
-pub use memory_set::{MapPermission, MemorySet};
 #[allow(unused_imports)]
-pub use page_table::{
-    transfer_byte_buffer, translate_ref, translate_refmut, translate_str, PageTable,
+pub use frame_allocator::{
+    frame_alloc, frame_alloc_contiguous, frame_dealloc, frame_remaining, frame_total, FrameTracker,
 };
+pub use memory_set::{Aux, ElfError, FaultError, MapPermission, MemorySet, MmapFlags};
+pub use page_table::PageTable;
+pub use swap::swap_usage;
+pub use user_access::BadAddress;
 pub use user_buffer::UserBuffer;
 
 #[allow(unused)]
@@ -26,4 +30,5 @@ pub fn test() {
 pub fn init() {
     heap_allocator::init_heap();
     kernel_space::init();
+    swap::init();
 }
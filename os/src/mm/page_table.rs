@@ -3,21 +3,15 @@ use super::{
     frame_allocator::{frame_alloc, FrameTracker},
 };
 use crate::{
-    config::{PAGE_SIZE, SV39_PPN_WIDTH},
-    mm::{
-        address::{PhysicalAddr, StepByOne},
-        VirtualAddr,
-    },
-    process::processor::get_current_user_token,
-};
-use alloc::{
-    string::{String, ToString},
-    vec::*,
+    config::SV39_PPN_WIDTH,
+    mm::{address::PhysicalAddr, VirtualAddr},
 };
+use alloc::vec::*;
 use bitflags::*;
 use log::info;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy)]
     pub struct PTEFlags: u8 {
         const V = 1 << 0;
         const R = 1 << 1;
@@ -30,6 +24,42 @@ bitflags! {
     }
 }
 
+/// Size of the page a leaf pte maps, sv39 allows leaves on every level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageSize {
+    Size4K,
+    Size2M,
+    Size1G,
+}
+
+impl PageSize {
+    /// Number of 4KiB pages covered
+    pub fn pages(&self) -> usize {
+        match self {
+            PageSize::Size4K => 1,
+            PageSize::Size2M => 1 << 9,
+            PageSize::Size1G => 1 << 18,
+        }
+    }
+
+    // the index of the level holding the leaf, as in `VirtualPageNumber::get_indexes`
+    fn depth(&self) -> usize {
+        match self {
+            PageSize::Size4K => 2,
+            PageSize::Size2M => 1,
+            PageSize::Size1G => 0,
+        }
+    }
+
+    fn from_depth(depth: usize) -> Self {
+        match depth {
+            0 => PageSize::Size1G,
+            1 => PageSize::Size2M,
+            _ => PageSize::Size4K,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct PageTableEntry {
@@ -59,16 +89,21 @@ impl PageTable {
         }
     }
 
-    fn find_pte_create(&mut self, vpn: VirtualPageNumber) -> Option<&mut PageTableEntry> {
+    fn find_pte_create(
+        &mut self,
+        vpn: VirtualPageNumber,
+        size: PageSize,
+    ) -> Option<&mut PageTableEntry> {
         let mut ppn = self.ppn;
         let mut result: Option<&mut PageTableEntry> = None;
         for (i, offset) in vpn.get_indexes().iter().enumerate() {
             let pte = ppn.get_pte(*offset);
-            if i == 2 {
+            if i == size.depth() {
                 result = Some(pte);
                 break;
             }
 
+            assert!(!pte.is_leaf(), "vpn {:?} is inside a huge page", vpn);
             if !pte.is_valid() {
                 let alloca = frame_alloc().unwrap();
                 *pte = PageTableEntry::new(alloca.ppn, PTEFlags::V);
@@ -79,13 +114,14 @@ impl PageTable {
         result
     }
 
-    fn find_pte(&self, vpn: VirtualPageNumber) -> Option<&mut PageTableEntry> {
+    /// Find the leaf pte of `vpn`, stopping early at a megapage or gigapage.
+    fn find_leaf(&self, vpn: VirtualPageNumber) -> Option<(&mut PageTableEntry, PageSize)> {
         let mut ppn = self.ppn;
-        let mut result: Option<&mut PageTableEntry> = None;
+        let mut result: Option<(&mut PageTableEntry, PageSize)> = None;
         for (i, offset) in vpn.get_indexes().iter().enumerate() {
             let pte = ppn.get_pte(*offset);
-            if i == 2 {
-                result = Some(pte);
+            if i == 2 || pte.is_leaf() {
+                result = Some((pte, PageSize::from_depth(i)));
                 break;
             }
 
@@ -97,25 +133,88 @@ impl PageTable {
         result
     }
 
+    fn find_pte(&self, vpn: VirtualPageNumber) -> Option<&mut PageTableEntry> {
+        self.find_leaf(vpn).map(|(pte, size)| {
+            assert_eq!(
+                size,
+                PageSize::Size4K,
+                "vpn {:?} is inside a huge page",
+                vpn
+            );
+            pte
+        })
+    }
+
     pub fn map(&mut self, vpn: VirtualPageNumber, ppn: PhysicalPageNumber, flags: PTEFlags) {
-        let pte = self.find_pte_create(vpn).unwrap();
+        self.map_sized(vpn, ppn, flags, PageSize::Size4K);
+    }
+
+    /// Map a page of `size`, both `vpn` and `ppn` must be aligned to it.
+    pub fn map_sized(
+        &mut self,
+        vpn: VirtualPageNumber,
+        ppn: PhysicalPageNumber,
+        flags: PTEFlags,
+        size: PageSize,
+    ) {
+        assert!(
+            vpn.0 % size.pages() == 0 && ppn.0 % size.pages() == 0,
+            "vpn {:?} or ppn {:?} is not aligned to {:?}",
+            vpn,
+            ppn,
+            size
+        );
+        let pte = self.find_pte_create(vpn, size).unwrap();
         // the pte will be allocated must be a invaild..
         assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
         *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
     }
 
-    pub fn unmap(&mut self, vpn: VirtualPageNumber) {
+    /// Overwrite a mapped pte, e.g. to change its flags or point it to another frame.
+    pub fn remap(&mut self, vpn: VirtualPageNumber, ppn: PhysicalPageNumber, flags: PTEFlags) {
         let pte = self.find_pte(vpn).unwrap();
         assert!(
             pte.is_valid(),
+            "vpn {:?} is never mapped before remapping",
+            vpn
+        );
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
+
+    /// Unmap the page starting at `vpn`, return its size.
+    pub fn unmap(&mut self, vpn: VirtualPageNumber) -> PageSize {
+        let (pte, size) = self.find_leaf(vpn).unwrap();
+        assert!(
+            pte.is_valid() || pte.is_swapped(),
             "vpn {:?} is never mapped before unmapping",
             vpn
         );
+        assert!(
+            vpn.0 % size.pages() == 0,
+            "vpn {:?} is not the start of a {:?} page",
+            vpn,
+            size
+        );
         *pte = PageTableEntry::empty();
+        size
     }
 
+    /// Record that the page of `vpn` lives in swap slot `slot` instead of a frame.
+    pub fn swap_out(&mut self, vpn: VirtualPageNumber, slot: usize) {
+        let pte = self.find_pte_create(vpn, PageSize::Size4K).unwrap();
+        *pte = PageTableEntry::new_swapped(slot);
+    }
+
+    /// The pte of `vpn`. Inside a huge page, it is made up as if the 4KiB page was mapped alone.
     pub fn translate(&self, vpn: VirtualPageNumber) -> Option<PageTableEntry> {
-        self.find_pte(vpn).map(|pte| *pte)
+        self.find_leaf(vpn).map(|(pte, size)| {
+            if size == PageSize::Size4K {
+                *pte
+            } else {
+                let ppn = pte.get_ppn().0 + vpn.0 % size.pages();
+                PageTableEntry::new(ppn.into(), pte.flags())
+            }
+        })
     }
 
     pub fn translate_va(&self, va: VirtualAddr) -> Option<PhysicalAddr> {
@@ -123,40 +222,15 @@ impl PageTable {
             .map(|ppn| (PhysicalAddr::from(ppn.get_ppn()).0 + va.get_offset()).into())
     }
 
-    // FIXME Assume that the str is within a page.
-    pub fn translate_str(&self, ptr: *const u8) -> &str {
-        let bytes = &self
-            .translate(VirtualAddr::from(ptr as usize).into())
-            .unwrap()
-            .get_ppn()
-            .get_bytes_array()[ptr as usize % PAGE_SIZE..];
-
-        let mut len = 0;
-        while unsafe { *bytes.get_unchecked(len) } != 0 {
-            len += 1;
-        }
-        unsafe { core::str::from_utf8_unchecked(&bytes[0..len]) }
-    }
-
-    // sizeof T
-    pub fn translate_ref<T>(&self, ptr: *const T) -> &'static T {
-        self.translate_va(VirtualAddr::from(ptr as usize))
-            .unwrap()
-            .get_mut()
-    }
-
-    pub fn translate_refmut<T>(&self, ptr: *const T) -> &'static mut T {
-        self.translate_va(VirtualAddr::from(ptr as usize))
-            .unwrap()
-            .get_mut()
-    }
-
     pub fn get_token(&self) -> usize {
         // 8 for sv39 mode
         8usize << 60 | self.ppn.0
     }
 }
 
+// The first RSW bit, a swapped-out page has V cleared, this bit set and its swap slot in PPN
+const PTE_SWAPPED: u64 = 1 << 8;
+
 impl PageTableEntry {
     pub fn new(ppn: PhysicalPageNumber, pte_flag: PTEFlags) -> Self {
         Self {
@@ -164,6 +238,12 @@ impl PageTableEntry {
         }
     }
 
+    pub fn new_swapped(slot: usize) -> Self {
+        Self {
+            bits: (slot << 10) as u64 | PTE_SWAPPED,
+        }
+    }
+
     pub fn empty() -> Self {
         Self { bits: 0 }
     }
@@ -181,6 +261,26 @@ impl PageTableEntry {
         self.flags().contains(PTEFlags::V)
     }
 
+    /// A valid pte is a leaf if any of R, W, X is set, otherwise it points to the next level
+    pub fn is_leaf(&self) -> bool {
+        self.is_valid()
+            && self
+                .flags()
+                .intersects(PTEFlags::R | PTEFlags::W | PTEFlags::X)
+    }
+
+    pub fn is_swapped(&self) -> bool {
+        !self.is_valid() && self.bits & PTE_SWAPPED != 0
+    }
+
+    pub fn swap_slot(&self) -> usize {
+        self.get_ppn().0
+    }
+
+    pub fn accessed(&self) -> bool {
+        self.flags().contains(PTEFlags::A)
+    }
+
     pub fn readable(&self) -> bool {
         self.flags().contains(PTEFlags::R)
     }
@@ -205,51 +305,14 @@ pub fn translate_test() {
         translated,
         PhysicalAddr::from(PhysicalAddr::from(PhysicalPageNumber::from(200)).0 + 3)
     );
+    // a megapage, every 4KiB page inside it translates to the matching frame
+    let vpn = VirtualPageNumber(0x400);
+    pt.map_sized(vpn, 0x200.into(), PTEFlags::R, PageSize::Size2M);
+    assert_eq!(
+        pt.translate(VirtualPageNumber(0x403)).unwrap().get_ppn().0,
+        0x203
+    );
+    assert_eq!(pt.unmap(vpn), PageSize::Size2M);
+    assert!(pt.translate(VirtualPageNumber(0x403)).is_none());
     info!("translate_test PASSED!");
 }
-
-pub fn transfer_byte_buffer(ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
-    let page_table = PageTable::from_token(get_current_user_token());
-    let mut v = Vec::new();
-    let mut start = ptr as usize;
-    let end = start + len;
-
-    while start < end {
-        let start_va = VirtualAddr::from(start);
-        let mut vpn = start_va.floor();
-
-        vpn.step();
-        // The end_va may bigger than end
-        let mut end_va: VirtualAddr = vpn.into();
-        end_va = end_va.min(VirtualAddr::from(end));
-
-        v.push(
-            &mut page_table
-                .translate(start_va.floor())
-                .unwrap()
-                .get_ppn()
-                .get_bytes_array()[start_va.get_offset()..end_va.get_offset()],
-        );
-        start = end_va.into();
-    }
-    v
-}
-
-#[allow(unused)]
-pub fn translate_str(token: usize, ptr: *const u8) -> String {
-    let pgtbl = PageTable::from_token(token);
-    pgtbl.translate_str(ptr).to_string()
-}
-
-#[allow(unused)]
-/// size_of T must be power of 2 and less than 4096
-pub fn translate_ref<T>(token: usize, ptr: *const T) -> &'static T {
-    let pgtbl = PageTable::from_token(token);
-    pgtbl.translate_ref(ptr)
-}
-
-/// size_of T must be power of 2 and less than 4096
-pub fn translate_refmut<T>(token: usize, ptr: *const T) -> &'static mut T {
-    let pgtbl = PageTable::from_token(token);
-    pgtbl.translate_refmut(ptr)
-}
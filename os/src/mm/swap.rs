@@ -0,0 +1,113 @@
+use core::fmt::Debug;
+
+use alloc::vec::Vec;
+use fs::BLOCK_SZ;
+use lazy_static::*;
+use log::info;
+
+use super::address::PhysicalPageNumber;
+use crate::{
+    config::{PAGE_SIZE, SWAP_PAGES},
+    drivers::SWAP_DEVICE,
+    sync::Mutex,
+};
+
+const BLOCKS_PER_PAGE: usize = PAGE_SIZE / BLOCK_SZ;
+
+lazy_static! {
+    static ref SWAP_ALLOCATOR: Mutex<SwapAllocator> = Mutex::new(SwapAllocator::new(SWAP_PAGES));
+}
+
+/// Probe the swap device at boot, the virtio queue needs contiguous frames which are hard to
+/// find once memory runs short.
+pub fn init() {
+    if SWAP_DEVICE.is_some() {
+        info!("swap enabled, {} pages", SWAP_PAGES);
+    } else {
+        info!("no swap device, swap disabled");
+    }
+}
+
+/// The number of pages of the swap area and how many of them are free, none without a device
+pub fn swap_usage() -> (usize, usize) {
+    if SWAP_DEVICE.is_none() {
+        return (0, 0);
+    }
+    let allocator = SWAP_ALLOCATOR.lock();
+    (
+        allocator.end,
+        allocator.end - allocator.current + allocator.recycled.len(),
+    )
+}
+
+/// A page-sized slot in the swap area, it is freed when dropped
+pub struct SwapSlot {
+    pub id: usize,
+}
+
+impl Debug for SwapSlot {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!("SwapSlot: id={:#x}", self.id))
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        SWAP_ALLOCATOR.lock().dealloca(self.id)
+    }
+}
+
+impl SwapSlot {
+    /// Read the page back into the frame `ppn`
+    pub fn read(&self, ppn: PhysicalPageNumber) {
+        let device = SWAP_DEVICE.as_ref().unwrap();
+        for (i, block) in ppn.get_bytes_array().chunks_mut(BLOCK_SZ).enumerate() {
+            device.read_block(self.id * BLOCKS_PER_PAGE + i, block);
+        }
+    }
+}
+
+/// Write the frame `ppn` to a free slot.
+/// Return None if there is no swap device or the swap area is full.
+pub fn swap_out(ppn: PhysicalPageNumber) -> Option<SwapSlot> {
+    let device = SWAP_DEVICE.as_ref()?;
+    let slot = SwapSlot {
+        id: SWAP_ALLOCATOR.lock().alloca()?,
+    };
+    for (i, block) in ppn.get_bytes_array().chunks(BLOCK_SZ).enumerate() {
+        device.write_block(slot.id * BLOCKS_PER_PAGE + i, block);
+    }
+    Some(slot)
+}
+
+struct SwapAllocator {
+    current: usize,
+    end: usize,
+    recycled: Vec<usize>,
+}
+
+impl SwapAllocator {
+    fn new(pages: usize) -> Self {
+        Self {
+            current: 0,
+            end: pages,
+            recycled: Vec::new(),
+        }
+    }
+
+    fn alloca(&mut self) -> Option<usize> {
+        if let Some(id) = self.recycled.pop() {
+            Some(id)
+        } else {
+            if self.current == self.end {
+                return None;
+            }
+            self.current += 1;
+            Some(self.current - 1)
+        }
+    }
+
+    fn dealloca(&mut self, id: usize) {
+        self.recycled.push(id)
+    }
+}
@@ -0,0 +1,152 @@
+//! Checked access to user memory.
+//!
+//! The kernel reaches user memory through physical addresses, so every user pointer must be
+//! translated page by page, with lazy, swapped and copy-on-write pages faulted in first and the
+//! U/R/W bits checked. A bad pointer makes the syscall fail with EFAULT instead of a panic.
+//! Anything at or above `USER_STACK_TOP` is refused before the walk, so a kernel address or a
+//! non-canonical pointer cannot alias a user page once the upper bits are masked off.
+
+use alloc::{string::String, vec::Vec};
+use core::mem::size_of;
+
+use super::{
+    address::{PhysicalPageNumber, VirtualAddr, VirtualPageNumber},
+    page_table::PTEFlags,
+    MapPermission, MemorySet,
+};
+use crate::config::{PAGE_SIZE, USER_STACK_TOP};
+
+/// A user pointer to memory that is unmapped or lacks the permission, i.e. EFAULT
+#[derive(Debug, Clone, Copy)]
+pub struct BadAddress;
+
+impl MemorySet {
+    /// Whether `vpn` is mapped for user `access` right now, without faulting it in
+    fn page_allows(&self, vpn: VirtualPageNumber, access: MapPermission) -> bool {
+        let required = PTEFlags::from_bits(access.bits()).unwrap() | PTEFlags::U;
+        match self.page_table.translate(vpn) {
+            Some(pte) => pte.is_valid() && pte.flags().contains(required),
+            None => false,
+        }
+    }
+
+    /// The frame of `vpn` for user `access`, faulting the page in if needed
+    fn user_page(
+        &mut self,
+        vpn: VirtualPageNumber,
+        access: MapPermission,
+    ) -> Result<PhysicalPageNumber, BadAddress> {
+        if !self.page_allows(vpn, access) && self.handle_page_fault(vpn, access).is_err() {
+            return Err(BadAddress);
+        }
+        if self.page_allows(vpn, access) {
+            Ok(self.page_table.translate(vpn).unwrap().get_ppn())
+        } else {
+            Err(BadAddress)
+        }
+    }
+
+    /// Split `[start, start + len)` into per page pieces of (vpn, offset in page, length), the
+    /// range must lie below the top of user space
+    fn user_pages(
+        start: usize,
+        len: usize,
+    ) -> Result<impl Iterator<Item = (VirtualPageNumber, usize, usize)>, BadAddress> {
+        let end = match start.checked_add(len) {
+            Some(end) if end <= USER_STACK_TOP => end,
+            _ => return Err(BadAddress),
+        };
+        let mut current = start;
+        Ok(core::iter::from_fn(move || {
+            if current >= end {
+                return None;
+            }
+            let va = VirtualAddr::from(current);
+            let n = (PAGE_SIZE - va.get_offset()).min(end - current);
+            current += n;
+            Some((va.floor(), va.get_offset(), n))
+        }))
+    }
+
+    pub fn copy_from_user(&mut self, src: usize, dst: &mut [u8]) -> Result<(), BadAddress> {
+        let mut copied = 0;
+        for (vpn, offset, n) in Self::user_pages(src, dst.len())? {
+            let ppn = self.user_page(vpn, MapPermission::R)?;
+            dst[copied..copied + n].copy_from_slice(&ppn.get_bytes_array()[offset..offset + n]);
+            copied += n;
+        }
+        Ok(())
+    }
+
+    pub fn copy_to_user(&mut self, dst: usize, src: &[u8]) -> Result<(), BadAddress> {
+        let mut copied = 0;
+        for (vpn, offset, n) in Self::user_pages(dst, src.len())? {
+            let ppn = self.user_page(vpn, MapPermission::W)?;
+            ppn.get_bytes_array()[offset..offset + n].copy_from_slice(&src[copied..copied + n]);
+            copied += n;
+        }
+        Ok(())
+    }
+
+    /// Read a plain `#[repr(C)]` struct such as an iovec, any bit pattern must be valid for `T`
+    pub fn read_user<T: Copy + Default>(&mut self, src: usize) -> Result<T, BadAddress> {
+        let mut value = T::default();
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, size_of::<T>())
+        };
+        self.copy_from_user(src, bytes)?;
+        Ok(value)
+    }
+
+    /// Write a plain `#[repr(C)]` struct such as a `struct stat`
+    pub fn write_user<T: Copy>(&mut self, dst: usize, value: &T) -> Result<(), BadAddress> {
+        let bytes =
+            unsafe { core::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+        self.copy_to_user(dst, bytes)
+    }
+
+    /// Read a nul-terminated string, which may cross page boundaries. `None` if there is no nul
+    /// within `max` bytes, the nul included, so userspace cannot make the kernel read forever.
+    pub fn read_user_cstr(&mut self, ptr: usize, max: usize) -> Result<Option<String>, BadAddress> {
+        let mut bytes = Vec::new();
+        let mut va = ptr;
+        loop {
+            if bytes.len() >= max {
+                return Ok(None);
+            }
+            if va >= USER_STACK_TOP {
+                return Err(BadAddress);
+            }
+            let vpn = VirtualAddr::from(va).floor();
+            let offset = VirtualAddr::from(va).get_offset();
+            let page = self.user_page(vpn, MapPermission::R)?.get_bytes_array();
+            // no more than `max` bytes are looked at, the nul included
+            let page = &page[offset..(offset + max - bytes.len()).min(PAGE_SIZE)];
+            match page.iter().position(|&b| b == 0) {
+                Some(len) => {
+                    bytes.extend_from_slice(&page[..len]);
+                    break;
+                }
+                None => {
+                    bytes.extend_from_slice(page);
+                    va += page.len();
+                }
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    /// Check that `[ptr, ptr + len)` allows user `access`, e.g. the buffer of read or write before
+    /// the file is touched. The pages may change again once the address space is unlocked.
+    pub fn check_user(
+        &mut self,
+        ptr: usize,
+        len: usize,
+        access: MapPermission,
+    ) -> Result<(), BadAddress> {
+        for (vpn, _, _) in Self::user_pages(ptr, len)? {
+            self.user_page(vpn, access)?;
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,14 @@
 use alloc::vec::Vec;
 
+/// The data of a read or write, in kernel memory. The syscall copies it from or to the user
+/// buffer while it holds the address space, so the file may block in between.
 #[derive(Debug)]
-pub struct UserBuffer {
-    pub buffers: Vec<&'static mut [u8]>,
+pub struct UserBuffer<'a> {
+    pub buffers: Vec<&'a mut [u8]>,
 }
 
-impl UserBuffer {
-    pub fn new(buffers: Vec<&'static mut [u8]>) -> Self {
+impl<'a> UserBuffer<'a> {
+    pub fn new(buffers: Vec<&'a mut [u8]>) -> Self {
         Self { buffers }
     }
 
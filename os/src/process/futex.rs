@@ -0,0 +1,103 @@
+//! Futexes, wait queues keyed on a user word. The threads of a process find the same one by its
+//! virtual address, and processes sharing a MAP_SHARED mapping by its physical address.
+
+use alloc::collections::BTreeMap;
+use lazy_static::lazy_static;
+
+use crate::{sync::Mutex, syscall::Errno};
+
+use super::{
+    current_has_signal, processor::get_current_process, processor::schedule, wait_queue::WaitQueue,
+    MapPermission, MemorySet, VirtualAddr,
+};
+
+// futex ops
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+// like Linux, a futex is keyed by the mapping it is in, so private ones are no different
+pub const FUTEX_PRIVATE_FLAG: usize = 128;
+pub const FUTEX_CLOCK_REALTIME: usize = 256;
+
+/// Where a futex word is. A private page may move to another frame, by copy-on-write after a
+/// fork or by swapping, so it is keyed by the address space, i.e. its page table, and the
+/// virtual address. The frame of a shared page stays put while it is mapped.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum FutexKey {
+    Private { token: usize, uaddr: usize },
+    Shared { pa: usize },
+}
+
+lazy_static! {
+    // futexes with waiters
+    static ref FUTEXES: Mutex<BTreeMap<FutexKey, WaitQueue>> = Mutex::new(BTreeMap::new());
+}
+
+/// The key of the futex word at `uaddr` of `memory_set`, whose page is faulted in for writing
+fn futex_key(memory_set: &mut MemorySet, uaddr: usize) -> Result<FutexKey, Errno> {
+    if uaddr % 4 != 0 {
+        return Err(Errno::EINVAL);
+    }
+    memory_set.check_user(uaddr, 4, MapPermission::W)?;
+    let va = VirtualAddr::from(uaddr);
+    if memory_set.is_shared(va.floor()) {
+        let pa = memory_set.page_table.translate_va(va).unwrap();
+        return Ok(FutexKey::Shared { pa: pa.0 });
+    }
+    Ok(FutexKey::Private {
+        token: memory_set.get_token(),
+        uaddr,
+    })
+}
+
+/// FUTEX_WAIT: block the current task until a FUTEX_WAKE on `uaddr`, as long as the word there
+/// still holds `val`. EAGAIN if it does not, EINTR if a signal comes first.
+pub fn futex_wait(uaddr: usize, val: u32) -> Result<(), Errno> {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    let key = futex_key(&mut inner.memory_set, uaddr)?;
+    let value: u32 = inner.memory_set.read_user(uaddr)?;
+    drop(inner);
+    drop(process);
+    if value != val {
+        return Err(Errno::EAGAIN);
+    }
+    if current_has_signal() {
+        return Err(Errno::EINTR);
+    }
+
+    FUTEXES
+        .lock()
+        .entry(key)
+        .or_insert_with(WaitQueue::new)
+        .block_current();
+    schedule();
+
+    // still queued, so it was not FUTEX_WAKE but a signal or an exit that woke it
+    let mut futexes = FUTEXES.lock();
+    let Some(waiters) = futexes.get_mut(&key) else {
+        return Ok(());
+    };
+    let interrupted = waiters.remove_current();
+    if waiters.is_empty() {
+        futexes.remove(&key);
+    }
+    if interrupted {
+        return Err(Errno::EINTR);
+    }
+    Ok(())
+}
+
+/// FUTEX_WAKE: wake up to `n` tasks waiting on `uaddr` of `memory_set`, return how many were
+/// woken
+pub fn futex_wake(memory_set: &mut MemorySet, uaddr: usize, n: usize) -> Result<usize, Errno> {
+    let key = futex_key(memory_set, uaddr)?;
+    let mut futexes = FUTEXES.lock();
+    let Some(waiters) = futexes.get_mut(&key) else {
+        return Ok(0);
+    };
+    let woken = waiters.wake(n);
+    if waiters.is_empty() {
+        futexes.remove(&key);
+    }
+    Ok(woken)
+}
@@ -1,11 +1,18 @@
 use crate::sync::Mutex;
-use alloc::{collections::VecDeque, sync::Arc};
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
 
-use super::task::TaskControlBlock;
+use super::{pcb::ProcessControlBlock, task::TaskControlBlock};
 use lazy_static::lazy_static;
 
 lazy_static! {
     static ref TASK_MANAGER: Mutex<TaskManager> = Mutex::new(TaskManager::new());
+    // every process that has not exited, to find the target of a signal
+    static ref PID2PROCESS: Mutex<BTreeMap<usize, Arc<ProcessControlBlock>>> =
+        Mutex::new(BTreeMap::new());
 }
 
 // FCFS, first come first served
@@ -36,3 +43,33 @@ pub fn add_task(task: Arc<TaskControlBlock>) {
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
     TASK_MANAGER.lock().fetch()
 }
+
+pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
+    PID2PROCESS.lock().insert(pid, process);
+}
+
+pub fn remove_from_pid2process(pid: usize) {
+    PID2PROCESS.lock().remove(&pid);
+}
+
+pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    PID2PROCESS.lock().get(&pid).cloned()
+}
+
+/// The process with a running thread `tid`
+pub fn tid2process(tid: usize) -> Option<Arc<ProcessControlBlock>> {
+    processes().into_iter().find(|process| {
+        process
+            .inner
+            .lock()
+            .threads
+            .iter()
+            .flatten()
+            .any(|thread| thread.tid == tid && !thread.is_zombie())
+    })
+}
+
+/// All the processes that have not exited
+pub fn processes() -> Vec<Arc<ProcessControlBlock>> {
+    PID2PROCESS.lock().values().cloned().collect()
+}
@@ -1,54 +1,120 @@
 mod context;
+pub mod futex;
 mod manager;
+mod pcb;
 mod pid;
 pub mod processor;
+pub mod signal;
 mod state;
 mod switch;
 mod task;
+pub mod wait_queue;
 
-use alloc::sync::Arc;
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+};
 use lazy_static::lazy_static;
-use manager::add_task;
+use manager::{add_task, insert_into_pid2process, remove_from_pid2process};
+pub use manager::{pid2process, processes};
 
 use crate::config::*;
-use crate::fs::{open_file, OpenFlags};
+use crate::fs::{open_file, OpenFlags, ROOT_INODE};
 use crate::mm::*;
 use crate::sbi::shutdown;
+use crate::syscall::Errno;
 use context::TaskContext;
 use core::arch::global_asm;
-use processor::get_current_task;
-use state::TaskState;
-use task::TaskControlBlock;
+use fs::Inode;
+use futex::futex_wake;
+use log::error;
+pub use pcb::ProcessControlBlock;
+use processor::{get_current_process, get_current_task};
+use signal::Signal;
+pub use state::TaskState;
 
 lazy_static! {
-    static ref INIT_PROC: Arc<TaskControlBlock> = Arc::new(TaskControlBlock::from_elf({
-        let inode = open_file("init_proc", OpenFlags::RDONLY).expect("cannot found init_proc");
-        &inode.read_all()
-    }));
+    static ref INIT_PROC: Arc<ProcessControlBlock> = ProcessControlBlock::from_elf(
+        &open_file(&ROOT_INODE, "init_proc", OpenFlags::RDONLY)
+            .expect("cannot found init_proc")
+            .read_all(),
+        &[String::from("init_proc")],
+        &[],
+    );
 }
 
 pub fn add_init_proc() {
-    add_task(INIT_PROC.clone())
+    insert_into_pid2process(INIT_PROC.get_pid(), INIT_PROC.clone());
+    let task = INIT_PROC.inner.lock().threads[0].clone().unwrap();
+    add_task(task)
 }
 
 const IDLE_PID: usize = 0;
 
-// just mark, not really exit
+// just mark, not really exit.
+// Only the current thread exits, the process exits with `exit_code` if it was the last one.
 pub fn mark_current_exit(exit_code: i32) {
+    exit_current((exit_code & 0xff) << 8, false);
+}
+
+/// Mark the current process exited with all its threads, like exit_group
+pub fn mark_current_exit_group(exit_code: i32) {
+    exit_current((exit_code & 0xff) << 8, true);
+}
+
+/// Mark the current process killed by the signal `signum`
+pub fn mark_current_killed(signum: usize) {
+    exit_current(signum as i32, true);
+}
+
+// `wait_status` is what wait4 reports to the parent. With `group` the other threads exit as well,
+// each one on its way back to user mode, and the last one to exit makes the process a zombie.
+fn exit_current(wait_status: i32, group: bool) {
     // after this line, the processor's current task will be None
-    let current = get_current_task().unwrap();
-    if current.get_pid() == IDLE_PID {
-        if exit_code == 0 {
-            shutdown(false);
-        } else {
-            shutdown(true);
-        }
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    if process.get_pid() == IDLE_PID {
+        shutdown(wait_status != 0);
     }
 
-    let mut inner = current.inner.lock();
+    let mut task_inner = task.inner.lock();
     // set task state to Exited
-    inner.state = TaskState::Zombie;
-    inner.exit_code = exit_code;
+    task_inner.state = TaskState::Zombie;
+    let clear_child_tid = core::mem::take(&mut task_inner.clear_child_tid);
+    drop(task_inner);
+
+    let mut inner = process.inner.lock();
+    if clear_child_tid != 0 {
+        // like Linux, a bad address is ignored, and a thread joining this one is woken
+        if inner.memory_set.write_user(clear_child_tid, &0i32).is_ok() {
+            let _ = futex_wake(&mut inner.memory_set, clear_child_tid, 1);
+        }
+    }
+    if !inner.exiting {
+        inner.wait_status = wait_status;
+        inner.exiting = group;
+        if group {
+            inner.interrupt_threads();
+        }
+    }
+    if inner
+        .threads
+        .iter()
+        .flatten()
+        .any(|thread| !thread.is_zombie())
+    {
+        return;
+    }
+
+    inner.zombie = true;
+    // the files are closed now rather than when it is reaped, so a pipe reader sees the end
+    inner.fd_table.clear();
+    remove_from_pid2process(process.get_pid());
+    if let Some(parent) = inner.parent.as_ref().and_then(Weak::upgrade) {
+        let mut parent_inner = parent.inner.lock();
+        parent_inner.raise(Signal::SIGCHLD);
+        parent_inner.child_exited.wake(usize::MAX);
+    }
 
     {
         let mut init = INIT_PROC.inner.lock();
@@ -56,6 +122,8 @@ pub fn mark_current_exit(exit_code: i32) {
             child.inner.lock().parent = Some(Arc::downgrade(&INIT_PROC));
             init.children.push(child.clone());
         }
+        // some of them may have exited already
+        init.child_exited.wake(usize::MAX);
     }
 
     // TODO is this necessary
@@ -66,6 +134,15 @@ pub fn mark_current_exit(exit_code: i32) {
     // drop(inner);
 }
 
+/// Whether the current thread has to exit before it returns to user mode, because of exit_group,
+/// a fatal signal or exec in another thread
+pub fn current_should_exit() -> bool {
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let exiting = process.inner.lock().exiting;
+    exiting || task.inner.lock().killed
+}
+
 pub fn mark_current_suspend() {
     let current = get_current_task().unwrap();
 
@@ -75,20 +152,84 @@ pub fn mark_current_suspend() {
     add_task(current);
 }
 
-pub fn mmap(start: usize, len: usize, prot: usize) -> Result<(), ()> {
-    get_current_task()
+/// Resolve a page fault of current task, or return the signal it gets: SIGSEGV if the access is
+/// illegal, SIGBUS if there is no memory left for the page
+pub fn handle_page_fault(va: usize, access: MapPermission) -> Result<(), usize> {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    match inner
+        .memory_set
+        .handle_page_fault(VirtualAddr::from(va).floor(), access)
+    {
+        Ok(()) => Ok(()),
+        Err(FaultError::NoMemory) => {
+            error!("Out of memory, stval=0x{:x}", va);
+            Err(Signal::SIGBUS)
+        }
+        Err(FaultError::Illegal) => {
+            if inner.memory_set.is_stack_guard(va) {
+                error!("Stack overflow, stval=0x{:x}", va);
+            }
+            Err(Signal::SIGSEGV)
+        }
+    }
+}
+
+/// Raise `signum` for a fault of the current task, which is delivered on its way back to user mode
+pub fn current_fault(signum: usize) {
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let mut inner = process.inner.lock();
+    inner.signals.force(signum, &mut task.inner.lock().blocked);
+}
+
+/// Whether the current task has a signal to handle or has to exit, blocking syscalls give up then
+pub fn current_has_signal() -> bool {
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let blocked = task.inner.lock().blocked;
+    let pending = process.inner.lock().signals.has_pending(blocked);
+    pending || current_should_exit()
+}
+
+pub fn mmap(
+    start: usize,
+    len: usize,
+    prot: usize,
+    flags: MmapFlags,
+    file: Option<(Arc<Inode>, usize)>,
+) -> Result<usize, Errno> {
+    get_current_process()
         .unwrap()
         .inner
         .lock()
         .memory_set
-        .mmap(start, len, prot)
+        .mmap(start, len, prot, flags, file)
 }
 
-pub fn munmap(start: usize, len: usize) -> Result<(), ()> {
-    get_current_task()
+pub fn munmap(start: usize, len: usize) -> Result<(), Errno> {
+    get_current_process()
         .unwrap()
         .inner
         .lock()
         .memory_set
         .munmap(start, len)
 }
+
+pub fn msync(start: usize, len: usize) -> Result<(), Errno> {
+    get_current_process()
+        .unwrap()
+        .inner
+        .lock()
+        .memory_set
+        .msync(start, len)
+}
+
+pub fn brk(new_brk: usize) -> usize {
+    get_current_process()
+        .unwrap()
+        .inner
+        .lock()
+        .memory_set
+        .brk(new_brk)
+}
@@ -0,0 +1,475 @@
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::mem::size_of;
+
+use fs::Inode;
+
+use crate::{
+    config::FD_LIMIT,
+    fs::{FileDescriptor, Stdin, Stdout, ROOT_INODE},
+    process::{current_has_signal, mark_current_suspend, processor::schedule},
+    sync::Mutex,
+    syscall::{Errno, SyscallResult},
+    timer::get_time,
+    trap::TrapContext,
+};
+
+use super::{
+    manager::{add_task, insert_into_pid2process},
+    pid::{pid_alloc, PidHandle},
+    signal::{SigSet, SignalState},
+    task::{map_trap_context, TaskControlBlock},
+    wait_queue::WaitQueue,
+    Aux, ElfError, MemorySet, MmapFlags,
+};
+
+pub struct ProcessControlBlock {
+    // immutable
+    pub pid: PidHandle,
+
+    // mutable
+    pub inner: Mutex<ProcessControlBlockInner>,
+}
+
+pub struct ProcessControlBlockInner {
+    // the file name of the program it runs, `comm` of /proc/<pid>/stat
+    pub name: String,
+    pub memory_set: MemorySet,
+    // Application data can only appear in the region where the application address space is less than base_size bytes. With it, we can clearly know how much data of the application resides in memory.
+    base_size: usize,
+
+    pub parent: Option<Weak<ProcessControlBlock>>,
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    // the status wait4 reports once the process is a zombie
+    pub wait_status: i32,
+    // exit_group or a fatal signal, the threads exit before they return to user mode
+    pub exiting: bool,
+    // every thread has exited, the parent may reap it
+    pub zombie: bool,
+    // threads in wait4, woken when a child exits
+    pub child_exited: WaitQueue,
+    pub signals: SignalState,
+    pub fd_table: Vec<Option<FileDescriptor>>,
+    // the working directory of all its threads, where relative paths start
+    pub cwd: Arc<Inode>,
+    // the absolute path of the working directory for getcwd only, renames do not update it
+    pub cwd_path: String,
+    // indexed by the slot of the trap context, the main thread is in slot 0.
+    // An exited thread stays until its slot is reused or the process is reaped, it may still be
+    // running on its kernel stack until the next schedule.
+    pub threads: Vec<Option<Arc<TaskControlBlock>>>,
+}
+
+impl ProcessControlBlock {
+    pub fn from_elf(elf_data: &[u8], args: &[String], envs: &[String]) -> Arc<Self> {
+        let (mut mm_set, user_sp, entry_point, auxv) =
+            MemorySet::from_elf(elf_data).expect("cannot load init_proc");
+        let sp = init_user_stack(&mut mm_set, user_sp, args, envs, &auxv)
+            .expect("cannot load init_proc");
+
+        let process = Arc::new(Self {
+            pid: pid_alloc(),
+            inner: Mutex::new(ProcessControlBlockInner {
+                name: comm(&args[0]),
+                memory_set: mm_set,
+                base_size: user_sp,
+                parent: None,
+                children: Vec::new(),
+                wait_status: 0,
+                exiting: false,
+                zombie: false,
+                child_exited: WaitQueue::new(),
+                signals: SignalState::new(),
+                fd_table: vec![
+                    // 0 -> stdin
+                    Some(FileDescriptor::new(Arc::new(Stdin), false)),
+                    // 1 -> stdout
+                    Some(FileDescriptor::new(Arc::new(Stdout), false)),
+                    // 2 -> stderr
+                    Some(FileDescriptor::new(Arc::new(Stdout), false)),
+                ],
+                cwd: ROOT_INODE.clone(),
+                cwd_path: String::from("/"),
+                threads: Vec::new(),
+            }),
+        });
+
+        let mut inner = process.inner.lock();
+        let task = Arc::new(TaskControlBlock::new(
+            &process,
+            &mut inner.memory_set,
+            0,
+            None,
+            SigSet::default(),
+        ));
+        *task.get_trap_context() =
+            TrapContext::app_init_context(entry_point, task.kernel_stack_top(), sp);
+        inner.threads.push(Some(task));
+        drop(inner);
+        process
+    }
+
+    pub fn get_user_token(&self) -> usize {
+        self.inner.lock().memory_set.get_token()
+    }
+
+    pub fn is_zombie(&self) -> bool {
+        self.inner.lock().zombie
+    }
+
+    pub fn get_pid(&self) -> usize {
+        self.pid.0
+    }
+
+    // TODO
+    //Map a new area
+    pub fn mmap(
+        &self,
+        start: usize,
+        len: usize,
+        port: usize,
+        flags: MmapFlags,
+        file: Option<(Arc<Inode>, usize)>,
+    ) -> Result<usize, ()> {
+        self.inner
+            .lock()
+            .memory_set
+            .mmap(start, len, port, flags, file)
+    }
+
+    /// Unmap a area.
+    pub fn munmap(&self, start: usize, len: usize) -> Result<(), ()> {
+        self.inner.lock().memory_set.munmap(start, len)
+    }
+
+    /// Replace the address space with the program in `data`, read from `path`, `task` goes on
+    /// to run it. If it can not be loaded, the old address space is left as it is.
+    /// Like Linux, the other threads are gone once it is loaded.
+    pub fn exec(
+        &self,
+        task: &TaskControlBlock,
+        path: &str,
+        data: &[u8],
+        args: &[String],
+        envs: &[String],
+    ) -> Result<(), ElfError> {
+        let (mut mm_set, user_sp, entry_point, auxv) = MemorySet::from_elf(data)?;
+        let sp = init_user_stack(&mut mm_set, user_sp, args, envs, &auxv)?;
+        let trap_context_ppn = map_trap_context(&mut mm_set, task.slot);
+
+        // the other threads may still be using the old address space in the kernel, so wait for
+        // them to exit
+        for thread in self.inner.lock().threads.iter().flatten() {
+            if thread.tid != task.tid {
+                thread.inner.lock().killed = true;
+                thread.interrupt();
+            }
+        }
+        loop {
+            let inner = self.inner.lock();
+            if inner.exiting || task.inner.lock().killed {
+                // another thread exits the process or runs exec first, this one exits instead
+                return Ok(());
+            }
+            if inner
+                .threads
+                .iter()
+                .flatten()
+                .all(|thread| thread.tid == task.tid || thread.is_zombie())
+            {
+                break;
+            }
+            drop(inner);
+            mark_current_suspend();
+            schedule();
+        }
+
+        let mut inner = self.inner.lock();
+        for (slot, thread) in inner.threads.iter_mut().enumerate() {
+            if slot != task.slot {
+                *thread = None;
+            }
+        }
+        inner.name = comm(path);
+        inner.memory_set = mm_set;
+        inner.base_size = user_sp;
+        inner.signals.exec();
+        for fd in inner.fd_table.iter_mut() {
+            if fd.as_ref().is_some_and(|fd| fd.cloexec) {
+                *fd = None;
+            }
+        }
+        let mut task_inner = task.inner.lock();
+        task_inner.trap_context_ppn = trap_context_ppn;
+        task_inner.clear_child_tid = 0;
+        *task_inner.get_trap_context() =
+            TrapContext::app_init_context(entry_point, task.kernel_stack_top(), sp);
+        Ok(())
+    }
+
+    /// A child process with a copy of the address space, whose only thread continues from the
+    /// trap context of `task`. Return that thread.
+    pub fn fork(self: &Arc<Self>, task: &TaskControlBlock) -> Arc<TaskControlBlock> {
+        let mut parent_inner = self.inner.lock();
+
+        let mm_set = MemorySet::from_other_proc(&mut parent_inner.memory_set);
+
+        let new_fd_table = parent_inner
+            .fd_table
+            .iter()
+            .map(|file| file.clone())
+            .collect();
+
+        let child = Arc::new(Self {
+            pid: pid_alloc(),
+            inner: Mutex::new(ProcessControlBlockInner {
+                name: parent_inner.name.clone(),
+                memory_set: mm_set,
+                base_size: parent_inner.base_size,
+                parent: Some(Arc::downgrade(self)),
+                children: Vec::new(),
+                wait_status: 0,
+                exiting: false,
+                zombie: false,
+                child_exited: WaitQueue::new(),
+                signals: parent_inner.signals.fork(),
+                fd_table: new_fd_table,
+                cwd: parent_inner.cwd.clone(),
+                cwd_path: parent_inner.cwd_path.clone(),
+                threads: Vec::new(),
+            }),
+        });
+
+        let mut child_inner = child.inner.lock();
+        let thread = Arc::new(TaskControlBlock::new(
+            &child,
+            &mut child_inner.memory_set,
+            0,
+            None,
+            task.inner.lock().blocked,
+        ));
+        let trap_cx = thread.get_trap_context();
+        *trap_cx = task.get_trap_context().clone();
+        trap_cx.kernel_sp = thread.kernel_stack_top();
+        child_inner.threads.push(Some(thread.clone()));
+        drop(child_inner);
+
+        insert_into_pid2process(child.get_pid(), child.clone());
+        add_task(thread.clone());
+
+        parent_inner.children.push(child);
+        thread
+    }
+
+    /// A new thread sharing everything with `task` but its tid and kernel stack, which continues
+    /// from a copy of the trap context of `task`
+    pub fn create_thread(self: &Arc<Self>, task: &TaskControlBlock) -> Arc<TaskControlBlock> {
+        let mut inner = self.inner.lock();
+        let slot = inner
+            .threads
+            .iter()
+            .position(|thread| thread.as_ref().is_none_or(|thread| thread.is_zombie()))
+            .unwrap_or_else(|| {
+                inner.threads.push(None);
+                inner.threads.len() - 1
+            });
+        // free the tid and kernel stack of the exited thread first
+        inner.threads[slot] = None;
+
+        let thread = Arc::new(TaskControlBlock::new(
+            self,
+            &mut inner.memory_set,
+            slot,
+            Some(pid_alloc()),
+            task.inner.lock().blocked,
+        ));
+        let trap_cx = thread.get_trap_context();
+        *trap_cx = task.get_trap_context().clone();
+        trap_cx.kernel_sp = thread.kernel_stack_top();
+        inner.threads[slot] = Some(thread.clone());
+        drop(inner);
+
+        add_task(thread.clone());
+        thread
+    }
+
+    /// Wait for the child `pid` to exit and reap it like wait4, any child if `pid` is -1.
+    /// There are no process groups, so 0 and other negative `pid`s also wait for any child.
+    /// Return 0 if `nohang` and no child has exited yet.
+    /// EFAULT if `wstatus` is bad, the child is reaped anyway.
+    /// EINTR if a signal arrives while waiting.
+    pub fn wait4(&self, pid: isize, wstatus: *mut i32, nohang: bool) -> SyscallResult {
+        let matches = |p: &Arc<ProcessControlBlock>| pid <= 0 || p.get_pid() == pid as usize;
+        let mut inner = self.inner.lock();
+        // there is no such child to wait for
+        if !inner.children.iter().any(matches) {
+            return Err(Errno::ECHILD);
+        }
+        drop(inner);
+        let idx;
+        loop {
+            inner = self.inner.lock();
+            if let Some(index) = inner
+                .children
+                .iter()
+                .position(|p| matches(p) && p.is_zombie())
+            {
+                idx = index;
+                break;
+            } else if nohang {
+                return Ok(0);
+            } else {
+                drop(inner);
+                if current_has_signal() {
+                    return Err(Errno::EINTR);
+                }
+                self.inner.lock().child_exited.block_current();
+                schedule();
+            }
+        }
+        // Found
+        let child = inner.children.remove(idx);
+        let child_inner = child.inner.lock();
+        assert_eq!(Arc::strong_count(&child), 1);
+        if !wstatus.is_null() {
+            inner
+                .memory_set
+                .write_user(wstatus as usize, &child_inner.wait_status)?;
+        }
+        Ok(child.get_pid())
+    }
+
+    /// The pid of the parent, 0 if it has gone
+    pub fn get_ppid(&self) -> usize {
+        match &self.inner.lock().parent {
+            Some(parent) => parent.upgrade().map_or(0, |p| p.get_pid()),
+            None => 0,
+        }
+    }
+}
+
+/// Lay out the initial stack at `sp` like the Linux ELF ABI: argc, the argv pointers and the envp
+/// pointers, each ending with NULL, and the auxiliary vector, with the strings and the AT_RANDOM
+/// bytes above them. Return the new sp, which points at argc, or NoMemory if it does not fit.
+fn init_user_stack(
+    memory_set: &mut MemorySet,
+    mut sp: usize,
+    args: &[String],
+    envs: &[String],
+    auxv: &[(usize, usize)],
+) -> Result<usize, ElfError> {
+    // push the strings, return the pointers to them
+    let mut push_strings = |strings: &[String]| -> Result<Vec<usize>, ElfError> {
+        strings
+            .iter()
+            .map(|string| {
+                sp -= string.len() + 1;
+                memory_set
+                    .copy_to_user(sp, string.as_bytes())
+                    .and_then(|_| memory_set.copy_to_user(sp + string.len(), &[0]))
+                    .map(|_| sp)
+                    .map_err(|_| ElfError::NoMemory)
+            })
+            .collect()
+    };
+    let argv = push_strings(args)?;
+    let envp = push_strings(envs)?;
+
+    // 16 random bytes for libc, e.g. the stack protector canary
+    sp -= 16;
+    memory_set
+        .copy_to_user(sp, &random_bytes())
+        .map_err(|_| ElfError::NoMemory)?;
+    let random = sp;
+
+    let mut words = vec![args.len()];
+    words.extend(argv);
+    words.push(0);
+    words.extend(envp);
+    words.push(0);
+    for &(key, value) in auxv.iter().chain(&[(Aux::RANDOM, random), (Aux::NULL, 0)]) {
+        words.extend([key, value]);
+    }
+
+    sp = (sp - words.len() * size_of::<usize>()) & !0xf;
+    for (i, word) in words.iter().enumerate() {
+        memory_set
+            .copy_to_user(sp + i * size_of::<usize>(), &word.to_ne_bytes())
+            .map_err(|_| ElfError::NoMemory)?;
+    }
+    Ok(sp)
+}
+
+// There is no entropy source, mix the time with a xorshift instead
+fn random_bytes() -> [u8; 16] {
+    let mut x = get_time() as u64 | 1;
+    let mut bytes = [0; 16];
+    for chunk in bytes.chunks_mut(8) {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        chunk.copy_from_slice(&x.to_ne_bytes());
+    }
+    bytes
+}
+
+/// The name of the program at `path`, its file name cut to 15 bytes like Linux's `comm`
+fn comm(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let mut len = name.len().min(15);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    String::from(&name[..len])
+}
+
+impl Drop for ProcessControlBlockInner {
+    fn drop(&mut self) {
+        self.memory_set.recycle_data_pages();
+    }
+}
+
+impl ProcessControlBlockInner {
+    /// Make `signum` pending, it is discarded if it is ignored and no thread blocks it.
+    /// The blocked threads wake up if they have a signal to handle now.
+    pub fn raise(&mut self, signum: usize) {
+        let blocked = self
+            .threads
+            .iter()
+            .flatten()
+            .fold(SigSet::default(), |set, thread| {
+                SigSet(set.0 | thread.inner.lock().blocked.0)
+            });
+        self.signals.send(signum, blocked);
+        for thread in self.threads.iter().flatten() {
+            let blocked = thread.inner.lock().blocked;
+            if self.signals.has_pending(blocked) {
+                thread.interrupt();
+            }
+        }
+    }
+
+    /// Wake the blocked threads, their blocking syscalls find out why
+    pub fn interrupt_threads(&self) {
+        for thread in self.threads.iter().flatten() {
+            thread.interrupt();
+        }
+    }
+
+    /// The lowest free descriptor, None if all FD_LIMIT of them are taken
+    pub fn alloc_fd(&mut self) -> Option<usize> {
+        self.alloc_fd_from(0)
+    }
+
+    /// The lowest free descriptor not below `min`, None if it would be FD_LIMIT or more
+    pub fn alloc_fd_from(&mut self, min: usize) -> Option<usize> {
+        let fd = (min..FD_LIMIT).find(|&fd| self.fd_table.get(fd).is_none_or(Option::is_none))?;
+        if fd >= self.fd_table.len() {
+            self.fd_table.resize(fd + 1, None);
+        }
+        Some(fd)
+    }
+}
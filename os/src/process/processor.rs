@@ -1,7 +1,8 @@
-use crate::{sync::Mutex, trap::TrapContext};
+use crate::{fs::poll_console, sync::Mutex, trap::TrapContext};
 
 use super::{
-    context::TaskContext, manager::fetch_task, state::TaskState, switch::__switch, TaskControlBlock,
+    context::TaskContext, manager::fetch_task, pcb::ProcessControlBlock, state::TaskState,
+    switch::__switch, task::TaskControlBlock,
 };
 
 use alloc::sync::Arc;
@@ -48,18 +49,30 @@ pub fn get_current_task() -> Option<Arc<TaskControlBlock>> {
     PROCESSOR.lock().current()
 }
 
+/// The process of the current task
+pub fn get_current_process() -> Option<Arc<ProcessControlBlock>> {
+    get_current_task().and_then(|task| task.process.upgrade())
+}
+
 pub fn get_current_user_token() -> usize {
-    get_current_task().unwrap().get_user_token()
+    get_current_process().unwrap().get_user_token()
 }
 
 pub fn get_current_trap_context() -> &'static mut TrapContext {
     get_current_task().unwrap().get_trap_context()
 }
 
+/// Where the trap context of the current task is in user space
+pub fn get_current_trap_context_va() -> usize {
+    get_current_task().unwrap().trap_context_va()
+}
+
 pub fn schedule() {
     // fetch
     let mut next = fetch_task();
     while next.is_none() {
+        // every task is blocked, but Ctrl-C can still interrupt them
+        poll_console();
         next = fetch_task();
     }
     let next_task = next.unwrap();
@@ -0,0 +1,404 @@
+//! Signals with the numbers, `struct sigaction` and signal frame of Linux on riscv64
+
+use core::mem::size_of;
+
+use alloc::sync::Arc;
+
+use crate::{config::SIGRETURN_TRAMPOLINE, syscall::Errno};
+
+use super::{
+    current_should_exit,
+    manager::{pid2process, processes, tid2process},
+    mark_current_exit, mark_current_killed,
+    pcb::ProcessControlBlock,
+    processor::{get_current_process, get_current_task, schedule},
+    IDLE_PID,
+};
+
+/// Signal numbers, see include/uapi/asm-generic/signal.h
+pub struct Signal;
+
+#[allow(unused)]
+impl Signal {
+    pub const SIGHUP: usize = 1;
+    pub const SIGINT: usize = 2;
+    pub const SIGQUIT: usize = 3;
+    pub const SIGILL: usize = 4;
+    pub const SIGTRAP: usize = 5;
+    pub const SIGABRT: usize = 6;
+    pub const SIGBUS: usize = 7;
+    pub const SIGFPE: usize = 8;
+    pub const SIGKILL: usize = 9;
+    pub const SIGUSR1: usize = 10;
+    pub const SIGSEGV: usize = 11;
+    pub const SIGUSR2: usize = 12;
+    pub const SIGPIPE: usize = 13;
+    pub const SIGALRM: usize = 14;
+    pub const SIGTERM: usize = 15;
+    pub const SIGCHLD: usize = 17;
+    pub const SIGCONT: usize = 18;
+    pub const SIGSTOP: usize = 19;
+    pub const SIGTSTP: usize = 20;
+    pub const SIGTTIN: usize = 21;
+    pub const SIGTTOU: usize = 22;
+    pub const SIGURG: usize = 23;
+    pub const SIGWINCH: usize = 28;
+    /// Signals are numbered from 1 to NSIG
+    pub const NSIG: usize = 64;
+}
+
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+// sigprocmask `how`
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+// sa_flags
+pub const SA_NODEFER: usize = 0x4000_0000;
+pub const SA_RESETHAND: usize = 0x8000_0000;
+
+/// A set of signals, bit `n - 1` for signal `n` like `sigset_t` of the kernel
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SigSet(pub u64);
+
+impl SigSet {
+    /// SIGKILL and SIGSTOP can not be blocked, caught or ignored
+    const UNBLOCKABLE: SigSet = SigSet((1 << (Signal::SIGKILL - 1)) | (1 << (Signal::SIGSTOP - 1)));
+
+    pub fn contains(&self, signum: usize) -> bool {
+        self.0 & 1 << (signum - 1) != 0
+    }
+
+    pub fn insert(&mut self, signum: usize) {
+        self.0 |= 1 << (signum - 1);
+    }
+
+    pub fn remove(&mut self, signum: usize) {
+        self.0 &= !(1 << (signum - 1));
+    }
+
+    /// The lowest signal in the set but not in `blocked`
+    pub fn first_unblocked(&self, blocked: SigSet) -> Option<usize> {
+        let set = self.0 & !blocked.0;
+        (set != 0).then(|| set.trailing_zeros() as usize + 1)
+    }
+
+    /// Without the signals that can not be blocked
+    pub fn blockable(self) -> SigSet {
+        SigSet(self.0 & !Self::UNBLOCKABLE.0)
+    }
+}
+
+/// `struct sigaction` of the kernel, riscv has no sa_restorer
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    pub mask: SigSet,
+}
+
+/// `siginfo_t`, only the signal number is filled in
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SigInfo {
+    signo: i32,
+    errno: i32,
+    code: i32,
+    fields: [i32; 29],
+}
+
+/// `struct ucontext` of riscv
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UContext {
+    flags: usize,
+    link: usize,
+    stack: [usize; 3],
+    sigmask: SigSet,
+    unused: [u8; 120],
+    mcontext: SigContext,
+}
+
+/// `struct sigcontext` of riscv: pc and x1-x31, then the floating point registers, which the
+/// kernel does not save
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+struct SigContext {
+    regs: [usize; 32],
+    fpregs: [u64; 66],
+}
+
+/// The signal frame pushed on the user stack, sigreturn finds it at sp
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SignalFrame {
+    info: SigInfo,
+    uc: UContext,
+}
+
+enum DefaultAction {
+    Terminate,
+    Ignore,
+}
+
+fn default_action(signum: usize) -> DefaultAction {
+    match signum {
+        // there is no job control, so stopping is ignored as well
+        Signal::SIGCHLD
+        | Signal::SIGCONT
+        | Signal::SIGURG
+        | Signal::SIGWINCH
+        | Signal::SIGSTOP
+        | Signal::SIGTSTP
+        | Signal::SIGTTIN
+        | Signal::SIGTTOU => DefaultAction::Ignore,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// Signal state of a process, which its threads share.
+/// Each thread blocks signals of its own, see `TaskControlBlockInner::blocked`.
+pub struct SignalState {
+    pub pending: SigSet,
+    pub actions: [SigAction; Signal::NSIG],
+}
+
+impl SignalState {
+    pub fn new() -> Self {
+        Self {
+            pending: SigSet::default(),
+            actions: [SigAction::default(); Signal::NSIG],
+        }
+    }
+
+    /// The state of a forked child, which has no pending signals
+    pub fn fork(&self) -> Self {
+        Self {
+            pending: SigSet::default(),
+            actions: self.actions,
+        }
+    }
+
+    /// Handlers are gone after exec, the ignored signals stay ignored
+    pub fn exec(&mut self) {
+        for action in self.actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SigAction::default();
+            }
+        }
+    }
+
+    pub fn action(&self, signum: usize) -> SigAction {
+        self.actions[signum - 1]
+    }
+
+    /// Set the action of `signum`, a pending one is discarded if it is ignored now
+    pub fn set_action(&mut self, signum: usize, action: SigAction) {
+        self.actions[signum - 1] = action;
+        if self.ignores(signum) {
+            self.pending.remove(signum);
+        }
+    }
+
+    fn ignores(&self, signum: usize) -> bool {
+        match self.action(signum).handler {
+            SIG_IGN => true,
+            SIG_DFL => matches!(default_action(signum), DefaultAction::Ignore),
+            _ => false,
+        }
+    }
+
+    /// Make `signum` pending. Like Linux, an ignored signal is discarded right away unless it is
+    /// in `blocked`, because the action may change before it is unblocked.
+    pub fn send(&mut self, signum: usize, blocked: SigSet) {
+        if self.ignores(signum) && !blocked.contains(signum) {
+            return;
+        }
+        self.pending.insert(signum);
+    }
+
+    /// Send a signal raised by a fault of the thread blocking `blocked`, which can not be
+    /// blocked or ignored
+    pub fn force(&mut self, signum: usize, blocked: &mut SigSet) {
+        if blocked.contains(signum) || self.action(signum).handler == SIG_IGN {
+            self.set_action(signum, SigAction::default());
+            blocked.remove(signum);
+        }
+        self.pending.insert(signum);
+    }
+
+    /// Whether a signal not in `blocked` is waiting to be delivered, blocking syscalls of the
+    /// thread blocking `blocked` fail with EINTR then
+    pub fn has_pending(&self, blocked: SigSet) -> bool {
+        self.pending.first_unblocked(blocked).is_some()
+    }
+}
+
+/// Send `signum` to `pid` like kill, 0 only checks that `pid` exists.
+/// -1 sends to every process but init and the caller.
+pub fn send_signal(pid: isize, signum: usize) -> Result<(), Errno> {
+    if signum > Signal::NSIG {
+        return Err(Errno::EINVAL);
+    }
+    match pid {
+        -1 => {
+            let current = get_current_process().unwrap().get_pid();
+            processes()
+                .iter()
+                .filter(|process| process.get_pid() != IDLE_PID && process.get_pid() != current)
+                .for_each(|process| send(process, signum));
+            Ok(())
+        }
+        // there are no process groups
+        ..=0 => Err(Errno::ESRCH),
+        _ => {
+            let process = pid2process(pid as usize).ok_or(Errno::ESRCH)?;
+            send(&process, signum);
+            Ok(())
+        }
+    }
+}
+
+/// Send `signum` to the thread `tid` like tgkill, which must be in the process `tgid` if it is
+/// given. Pending signals are not per thread, so the whole process gets it.
+pub fn send_thread_signal(tgid: Option<usize>, tid: usize, signum: usize) -> Result<(), Errno> {
+    if signum > Signal::NSIG {
+        return Err(Errno::EINVAL);
+    }
+    let process = tid2process(tid)
+        .filter(|process| tgid.is_none_or(|tgid| process.get_pid() == tgid))
+        .ok_or(Errno::ESRCH)?;
+    send(&process, signum);
+    Ok(())
+}
+
+// 0 only checks the target
+fn send(process: &Arc<ProcessControlBlock>, signum: usize) {
+    if signum != 0 {
+        process.inner.lock().raise(signum);
+    }
+}
+
+/// Ctrl-C on the console. Without process groups every process but init is in the foreground,
+/// so they all get SIGINT.
+pub fn console_interrupt() {
+    processes()
+        .iter()
+        .filter(|process| process.get_pid() != IDLE_PID)
+        .for_each(|process| process.inner.lock().raise(Signal::SIGINT));
+}
+
+/// Deliver a pending signal of the current task before it returns to user mode.
+/// A handler runs on the user stack with a signal frame holding the interrupted context, and
+/// returns to the sigreturn trampoline.
+/// A thread whose process is exiting exits here instead.
+pub fn handle_signals() {
+    if current_should_exit() {
+        // the status is the one of the exit_group or signal ending the process
+        mark_current_exit(0);
+        schedule();
+        unreachable!()
+    }
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let mut inner = process.inner.lock();
+    let blocked = task.inner.lock().blocked;
+    let (signum, action) = loop {
+        let signals = &mut inner.signals;
+        let Some(signum) = signals.pending.first_unblocked(blocked) else {
+            return;
+        };
+        signals.pending.remove(signum);
+        let action = signals.action(signum);
+        match action.handler {
+            SIG_IGN => continue,
+            SIG_DFL => match default_action(signum) {
+                DefaultAction::Ignore => continue,
+                DefaultAction::Terminate => {
+                    drop(inner);
+                    drop(process);
+                    drop(task);
+                    mark_current_killed(signum);
+                    schedule();
+                    unreachable!()
+                }
+            },
+            _ => break (signum, action),
+        }
+    };
+
+    let trap_cx = task.get_trap_context();
+    let mut regs = trap_cx.x;
+    regs[0] = trap_cx.sepc;
+    let frame = SignalFrame {
+        info: SigInfo {
+            signo: signum as i32,
+            errno: 0,
+            code: 0,
+            fields: [0; 29],
+        },
+        uc: UContext {
+            flags: 0,
+            link: 0,
+            stack: [0; 3],
+            sigmask: blocked,
+            unused: [0; 120],
+            mcontext: SigContext {
+                regs,
+                fpregs: [0; 66],
+            },
+        },
+    };
+    let sp = trap_cx.x[2].wrapping_sub(size_of::<SignalFrame>()) & !0xf;
+    if inner.memory_set.write_user(sp, &frame).is_err() {
+        // there is no room for the frame, like Linux kill it with SIGSEGV
+        drop(inner);
+        drop(process);
+        drop(task);
+        mark_current_killed(Signal::SIGSEGV);
+        schedule();
+        unreachable!()
+    }
+
+    let mut task_inner = task.inner.lock();
+    task_inner.blocked.0 |= action.mask.blockable().0;
+    if action.flags & SA_NODEFER == 0 {
+        task_inner.blocked.insert(signum);
+    }
+    drop(task_inner);
+    if action.flags & SA_RESETHAND != 0 {
+        inner.signals.set_action(signum, SigAction::default());
+    }
+
+    // handler(signum, &info, &ucontext), returning to the trampoline
+    trap_cx.sepc = action.handler;
+    trap_cx.x[1] = SIGRETURN_TRAMPOLINE;
+    trap_cx.x[2] = sp;
+    trap_cx.x[10] = signum;
+    trap_cx.x[11] = sp;
+    trap_cx.x[12] = sp + size_of::<SigInfo>();
+}
+
+/// Restore the context saved in the signal frame at sp, return the restored a0
+pub fn sigreturn() -> Result<usize, Errno> {
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let mut inner = process.inner.lock();
+    let trap_cx = task.get_trap_context();
+    let uc = trap_cx.x[2].wrapping_add(size_of::<SigInfo>());
+    let regs: [usize; 32] = inner
+        .memory_set
+        .read_user(uc + core::mem::offset_of!(UContext, mcontext))?;
+    let sigmask: SigSet = inner
+        .memory_set
+        .read_user(uc + core::mem::offset_of!(UContext, sigmask))?;
+    trap_cx.x[1..].copy_from_slice(&regs[1..]);
+    trap_cx.sepc = regs[0];
+    drop(inner);
+    task.inner.lock().blocked = sigmask.blockable();
+    Ok(trap_cx.x[10])
+}
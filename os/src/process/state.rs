@@ -2,5 +2,7 @@
 pub enum TaskState {
     Runnable,
     Running,
+    // off the runnable queue until it is woken, see `WaitQueue`
+    Blocked,
     Zombie,
 }
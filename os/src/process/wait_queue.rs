@@ -0,0 +1,80 @@
+//! Tasks blocked until an event, kept off the runnable queue of the scheduler
+
+use alloc::{
+    collections::VecDeque,
+    sync::{Arc, Weak},
+};
+
+use super::{
+    manager::add_task, processor::get_current_task, state::TaskState, task::TaskControlBlock,
+};
+
+/// A blocked task and the `wait_seq` it blocked with. The entry is stale once the task was woken
+/// some other way, e.g. interrupted by a signal, and maybe blocked again elsewhere.
+type Waiter = (Weak<TaskControlBlock>, usize);
+
+#[derive(Default)]
+pub struct WaitQueue {
+    waiters: VecDeque<Waiter>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block the current task on this queue. It is off the runnable queue from now on, the caller
+    /// releases its locks and calls `schedule`.
+    pub fn block_current(&mut self) {
+        self.waiters.push_back(mark_current_blocked());
+    }
+
+    /// Wake up to `n` tasks in the order they blocked, return how many were woken
+    pub fn wake(&mut self, n: usize) -> usize {
+        let mut woken = 0;
+        while woken < n {
+            let Some((task, seq)) = self.waiters.pop_front() else {
+                break;
+            };
+            if task.upgrade().is_some_and(|task| wake_task(&task, seq)) {
+                woken += 1;
+            }
+        }
+        woken
+    }
+
+    /// Remove the entry of the current task, return false if it was woken through the queue
+    pub fn remove_current(&mut self) -> bool {
+        let task = get_current_task().unwrap();
+        let len = self.waiters.len();
+        self.waiters
+            .retain(|(waiter, _)| !core::ptr::eq(waiter.as_ptr(), Arc::as_ptr(&task)));
+        self.waiters.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+}
+
+/// Take the current task off the runnable queue until it is woken, return the `wait_seq` to wake
+/// it with. The caller calls `schedule` next.
+pub fn mark_current_blocked() -> Waiter {
+    let task = get_current_task().unwrap();
+    let mut inner = task.inner.lock();
+    inner.state = TaskState::Blocked;
+    inner.wait_seq += 1;
+    (Arc::downgrade(&task), inner.wait_seq)
+}
+
+// make `task` runnable if it still waits for what blocked it with `seq`
+fn wake_task(task: &Arc<TaskControlBlock>, seq: usize) -> bool {
+    let mut inner = task.inner.lock();
+    if inner.state != TaskState::Blocked || inner.wait_seq != seq {
+        return false;
+    }
+    inner.state = TaskState::Runnable;
+    drop(inner);
+    add_task(task.clone());
+    true
+}
@@ -3,4 +3,5 @@ pub type BlockDeviceImpl = crate::drivers::block::VirtIOBlock;
 pub const MMIO: &[(usize, usize)] = &[
     (0x0010_0000, 0x00_2000), // VIRT_TEST/RTC  in virt machine
     (0x1000_1000, 0x00_1000), // Virtio Block in virt machine
+    (0x1000_2000, 0x00_1000), // Virtio Block for swap in virt machine
 ];
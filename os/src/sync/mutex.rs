@@ -41,6 +41,14 @@ impl<T> Mutex<T> {
         MutexGuard { mutex: self }
     }
 
+    /// Take the lock only if nobody holds it
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard { mutex: self })
+    }
+
     pub fn unlock(&self) {
         self.locked.store(false, Ordering::Release)
     }
@@ -0,0 +1,57 @@
+use crate::mm::{BadAddress, ElfError};
+
+/// Linux error numbers, a failed syscall returns the negated value
+/// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/errno-base.h
+#[allow(unused)]
+#[repr(isize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    EIO = 5,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EACCES = 13,
+    EFAULT = 14,
+    EBUSY = 16,
+    EEXIST = 17,
+    EXDEV = 18,
+    ENOTDIR = 20,
+    EISDIR = 21,
+    EINVAL = 22,
+    EMFILE = 24,
+    ENOTTY = 25,
+    ENOSPC = 28,
+    ESPIPE = 29,
+    EPIPE = 32,
+    ERANGE = 34,
+    ENAMETOOLONG = 36,
+    ENOSYS = 38,
+    ENOTEMPTY = 39,
+    ELOOP = 40,
+}
+
+pub type SyscallResult = Result<usize, Errno>;
+
+impl From<BadAddress> for Errno {
+    fn from(_: BadAddress) -> Self {
+        Errno::EFAULT
+    }
+}
+
+impl From<ElfError> for Errno {
+    fn from(err: ElfError) -> Self {
+        match err {
+            ElfError::NotExecutable => Errno::ENOEXEC,
+            ElfError::Invalid => Errno::EINVAL,
+            ElfError::NoMemory => Errno::ENOMEM,
+            ElfError::NoInterpreter => Errno::ENOENT,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Constants and structures of the Linux syscall ABI, as musl sees them on riscv64
+
+use bitflags::bitflags;
+
+/// `dirfd` for the current working directory
+pub const AT_FDCWD: isize = -100;
+/// newfstatat on `dirfd` itself when the path is empty
+pub const AT_EMPTY_PATH: usize = 0x1000;
+// unlinkat removes a directory
+pub const AT_REMOVEDIR: usize = 0x200;
+// renameat2 fails rather than replace the target
+pub const RENAME_NOREPLACE: usize = 1;
+
+/// open and pipe2 flag, close the descriptor on exec
+pub const O_CLOEXEC: u32 = 0o2000000;
+
+// wait4 options
+pub const WNOHANG: usize = 1;
+pub const WUNTRACED: usize = 2;
+pub const WCONTINUED: usize = 8;
+
+// terminal ioctls
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TCSETSW: usize = 0x5403;
+pub const TCSETSF: usize = 0x5404;
+pub const TIOCGWINSZ: usize = 0x5413;
+
+// fcntl commands
+pub const F_DUPFD: usize = 0;
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const F_DUPFD_CLOEXEC: usize = 1030;
+/// The descriptor flag of F_GETFD and F_SETFD
+pub const FD_CLOEXEC: usize = 1;
+
+// poll events
+pub const POLLIN: i16 = 0x1;
+pub const POLLOUT: i16 = 0x4;
+pub const POLLNVAL: i16 = 0x20;
+
+bitflags! {
+    pub struct CloneFlags: usize {
+        /// The signal sent to the parent on exit, SIGCHLD for fork
+        const CSIGNAL = 0xff;
+        const VM = 0x100;
+        const FS = 0x200;
+        const FILES = 0x400;
+        const SIGHAND = 0x800;
+        const VFORK = 0x4000;
+        const THREAD = 0x10000;
+        const SYSVSEM = 0x40000;
+        const SETTLS = 0x80000;
+        const PARENT_SETTID = 0x100000;
+        const CHILD_CLEARTID = 0x200000;
+        const DETACHED = 0x400000;
+        const CHILD_SETTID = 0x1000000;
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IoVec {
+    pub base: usize,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: i16,
+    pub revents: i16,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct WinSize {
+    pub row: u16,
+    pub col: u16,
+    pub xpixel: u16,
+    pub ypixel: u16,
+}
+
+/// `struct termios` of TCGETS, the console is always in raw mode
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Termios {
+    pub iflag: u32,
+    pub oflag: u32,
+    pub cflag: u32,
+    pub lflag: u32,
+    pub line: u8,
+    pub cc: [u8; 19],
+}
+
+impl Termios {
+    const B38400: u32 = 0o17;
+    const CS8: u32 = 0o60;
+    const CREAD: u32 = 0o200;
+    const VMIN: usize = 6;
+
+    pub fn raw() -> Self {
+        let mut cc = [0; 19];
+        cc[Self::VMIN] = 1;
+        Self {
+            iflag: 0,
+            oflag: 0,
+            cflag: Self::B38400 | Self::CS8 | Self::CREAD,
+            lflag: 0,
+            line: 0,
+            cc,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Utsname {
+    pub sysname: [u8; 65],
+    pub nodename: [u8; 65],
+    pub release: [u8; 65],
+    pub version: [u8; 65],
+    pub machine: [u8; 65],
+    pub domainname: [u8; 65],
+}
+
+impl Utsname {
+    pub fn new() -> Self {
+        fn field(s: &str) -> [u8; 65] {
+            let mut buf = [0; 65];
+            buf[..s.len()].copy_from_slice(s.as_bytes());
+            buf
+        }
+        Self {
+            // programs only know how to talk to Linux
+            sysname: field("Linux"),
+            nodename: field("localhost"),
+            release: field("5.15.0"),
+            version: field(env!("CARGO_PKG_VERSION")),
+            machine: field("riscv64"),
+            domainname: field("(none)"),
+        }
+    }
+}
+
+/// The size of `struct rusage`, which is all zero for now
+pub const RUSAGE_SIZE: usize = 144;
+
+/// `struct statfs` of asm-generic/statfs.h, with 64-bit words
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatFs {
+    pub fs_type: u64,
+    pub bsize: u64,
+    pub blocks: u64,
+    pub bfree: u64,
+    pub bavail: u64,
+    pub files: u64,
+    pub ffree: u64,
+    pub fsid: [i32; 2],
+    pub namelen: u64,
+    pub frsize: u64,
+    pub flags: u64,
+    pub spare: [u64; 4],
+}
+
+impl StatFs {
+    // f_type of easy-fs, its magic number, and of /proc
+    pub const EASY_FS_MAGIC: u64 = 0x3b800001;
+    pub const PROC_SUPER_MAGIC: u64 = 0x9fa0;
+    // f_flags of a read-only filesystem
+    pub const ST_RDONLY: u64 = 1;
+}
+
+/// `struct sysinfo` of linux/sysinfo.h
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SysInfo {
+    pub uptime: i64,
+    pub loads: [u64; 3],
+    pub totalram: u64,
+    pub freeram: u64,
+    pub sharedram: u64,
+    pub bufferram: u64,
+    pub totalswap: u64,
+    pub freeswap: u64,
+    pub procs: u16,
+    pub pad: u16,
+    pub totalhigh: u64,
+    pub freehigh: u64,
+    pub mem_unit: u32,
+}
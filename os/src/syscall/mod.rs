@@ -1,178 +1,965 @@
+mod errno;
+mod linux;
+
+pub use errno::{Errno, SyscallResult};
+
 use crate::{
-    fs::{open_file, OpenFlags},
-    mm::{transfer_byte_buffer, translate_str, UserBuffer},
+    config::{FD_LIMIT, PAGE_SIZE},
+    fs::{
+        absolute_path, fs_usage, make_dir, make_pipe, open_dir, open_file, open_path, remove_path,
+        rename_path, stat_path, File, FileDescriptor, OpenFlags, Stat,
+    },
+    mm::{frame_remaining, frame_total, swap_usage, MapPermission, MmapFlags, UserBuffer},
     process::{
-        mark_current_exit, mark_current_suspend, mmap, munmap,
-        processor::{get_current_task, get_current_user_token, schedule},
+        brk,
+        futex::{
+            futex_wait, futex_wake, FUTEX_CLOCK_REALTIME, FUTEX_PRIVATE_FLAG, FUTEX_WAIT,
+            FUTEX_WAKE,
+        },
+        mark_current_exit, mark_current_exit_group, mark_current_suspend, mmap, msync, munmap,
+        processes,
+        processor::{get_current_process, get_current_task, schedule},
+        signal::{
+            send_signal, send_thread_signal, sigreturn, SigAction, SigSet, Signal, SIG_BLOCK,
+            SIG_SETMASK, SIG_UNBLOCK,
+        },
     },
-    timer::get_time_ms,
+    timer::{get_time_spec, get_time_val},
 };
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::mem::size_of;
+use fs::{Inode, BLOCK_SZ, NAME_LENGTH_LIMIT};
+use linux::*;
+use log::warn;
+
 pub struct Syscall;
 
 // https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/unistd.h
 impl Syscall {
-    const OPEN: usize = 56;
+    const GETCWD: usize = 17;
+    const DUP: usize = 23;
+    const DUP3: usize = 24;
+    const FCNTL: usize = 25;
+    const IOCTL: usize = 29;
+    const MKDIRAT: usize = 34;
+    const UNLINKAT: usize = 35;
+    const STATFS: usize = 43;
+    const CHDIR: usize = 49;
+    const OPENAT: usize = 56;
     const CLOSE: usize = 57;
+    const PIPE2: usize = 59;
+    const GETDENTS64: usize = 61;
     const READ: usize = 63;
     const WRITE: usize = 64;
+    const READV: usize = 65;
+    const WRITEV: usize = 66;
+    const PPOLL: usize = 73;
+    const NEWFSTATAT: usize = 79;
+    const FSTAT: usize = 80;
     const EXIT: usize = 93;
-    const YIELD: usize = 124;
-    const GETTIME: usize = 169;
+    const EXIT_GROUP: usize = 94;
+    const SET_TID_ADDRESS: usize = 96;
+    const FUTEX: usize = 98;
+    const CLOCK_GETTIME: usize = 113;
+    const SCHED_YIELD: usize = 124;
+    const KILL: usize = 129;
+    const TKILL: usize = 130;
+    const TGKILL: usize = 131;
+    const RT_SIGACTION: usize = 134;
+    const RT_SIGPROCMASK: usize = 135;
+    const RT_SIGRETURN: usize = 139;
+    const UNAME: usize = 160;
+    const GETTIMEOFDAY: usize = 169;
     const GETPID: usize = 172;
-    const FORK: usize = 220; // clone ???
-    const EXEC: usize = 221;
-    const WAITPID: usize = 260; // wait4 in unistd.h
-    const MMAP: usize = 270;
-    const MUNMAP: usize = 271;
-}
-
-// a0-a2 for arguments, a7 for syscall id
-// return in a0
-pub fn syscall(id: usize, args: [usize; 3]) -> isize {
-    match id {
-        Syscall::OPEN => sys_open(args[0] as *const u8, args[1] as u32),
+    const GETPPID: usize = 173;
+    const GETUID: usize = 174;
+    const GETEUID: usize = 175;
+    const GETGID: usize = 176;
+    const GETEGID: usize = 177;
+    const GETTID: usize = 178;
+    const SYSINFO: usize = 179;
+    const BRK: usize = 214;
+    const MUNMAP: usize = 215;
+    const CLONE: usize = 220;
+    const EXECVE: usize = 221;
+    const MMAP: usize = 222;
+    const MSYNC: usize = 227;
+    const WAIT4: usize = 260;
+    const RENAMEAT2: usize = 276;
+}
+
+// a0-a5 for arguments, a7 for syscall id
+// return in a0, a negated errno on failure
+pub fn syscall(id: usize, args: [usize; 6]) -> isize {
+    let ret = match id {
+        Syscall::GETCWD => sys_getcwd(args[0], args[1]),
+        Syscall::DUP => sys_dup(args[0]),
+        Syscall::DUP3 => sys_dup3(args[0], args[1], args[2] as u32),
+        Syscall::FCNTL => sys_fcntl(args[0], args[1], args[2]),
+        Syscall::IOCTL => sys_ioctl(args[0], args[1], args[2]),
+        Syscall::MKDIRAT => sys_mkdirat(args[0] as isize, args[1] as *const u8),
+        Syscall::UNLINKAT => sys_unlinkat(args[0] as isize, args[1] as *const u8, args[2]),
+        Syscall::CHDIR => sys_chdir(args[0] as *const u8),
+        Syscall::STATFS => sys_statfs(args[0] as *const u8, args[1]),
+        Syscall::OPENAT => sys_openat(args[0] as isize, args[1] as *const u8, args[2] as u32),
         Syscall::CLOSE => sys_close(args[0]),
+        Syscall::PIPE2 => sys_pipe2(args[0], args[1] as u32),
+        Syscall::GETDENTS64 => sys_getdents64(args[0], args[1], args[2]),
         Syscall::WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
         Syscall::READ => sys_read(args[0], args[1] as *const u8, args[2]),
+        Syscall::READV => sys_readv(args[0], args[1], args[2]),
+        Syscall::WRITEV => sys_writev(args[0], args[1], args[2]),
+        Syscall::PPOLL => sys_ppoll(args[0], args[1]),
+        Syscall::NEWFSTATAT => {
+            sys_newfstatat(args[0] as isize, args[1] as *const u8, args[2], args[3])
+        }
+        Syscall::FSTAT => sys_fstat(args[0], args[1]),
         Syscall::EXIT => sys_exit(args[0] as i32),
-        Syscall::YIELD => sys_yield(),
-        Syscall::GETTIME => sys_get_time(),
+        Syscall::EXIT_GROUP => sys_exit_group(args[0] as i32),
+        Syscall::SET_TID_ADDRESS => sys_set_tid_address(args[0]),
+        Syscall::FUTEX => sys_futex(args[0], args[1], args[2], args[3]),
+        Syscall::CLOCK_GETTIME => sys_clock_gettime(args[0], args[1]),
+        Syscall::SCHED_YIELD => sys_yield(),
+        Syscall::KILL => sys_kill(args[0] as isize, args[1]),
+        Syscall::TKILL => sys_tkill(args[0] as isize, args[1]),
+        Syscall::TGKILL => sys_tgkill(args[0] as isize, args[1] as isize, args[2]),
+        Syscall::RT_SIGACTION => sys_rt_sigaction(args[0], args[1], args[2], args[3]),
+        Syscall::RT_SIGPROCMASK => sys_rt_sigprocmask(args[0], args[1], args[2], args[3]),
+        Syscall::RT_SIGRETURN => sigreturn(),
+        Syscall::UNAME => sys_uname(args[0]),
+        Syscall::GETTIMEOFDAY => sys_gettimeofday(args[0]),
         Syscall::GETPID => sys_getpid(),
-        Syscall::FORK => sys_fork(),
-        Syscall::EXEC => sys_exec(args[0] as *const u8),
-        Syscall::WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
-        Syscall::MMAP => sys_mmap(args[0], args[1], args[2]),
+        Syscall::GETTID => sys_gettid(),
+        Syscall::SYSINFO => sys_sysinfo(args[0]),
+        Syscall::GETPPID => sys_getppid(),
+        // there is only root
+        Syscall::GETUID | Syscall::GETEUID | Syscall::GETGID | Syscall::GETEGID => Ok(0),
+        Syscall::CLONE => sys_clone(args[0], args[1], args[2], args[3], args[4]),
+        Syscall::EXECVE => sys_execve(args[0] as *const u8, args[1], args[2]),
+        Syscall::WAIT4 => sys_wait4(args[0] as isize, args[1] as *mut i32, args[2], args[3]),
+        Syscall::RENAMEAT2 => sys_renameat2(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as isize,
+            args[3] as *const u8,
+            args[4],
+        ),
+        Syscall::BRK => sys_brk(args[0]),
+        Syscall::MMAP => sys_mmap(args[0], args[1], args[2], args[3], args[4], args[5]),
         Syscall::MUNMAP => sys_munmap(args[0], args[1]),
+        Syscall::MSYNC => sys_msync(args[0], args[1]),
 
-        _ => panic!("unsupport system call!!!"),
+        _ => {
+            warn!("unsupported syscall {}", id);
+            Err(Errno::ENOSYS)
+        }
+    };
+    match ret {
+        Ok(value) => value as isize,
+        Err(errno) => -(errno as isize),
     }
 }
 
-fn sys_open(path: *const u8, flags: u32) -> isize {
-    let task = get_current_task().unwrap();
-    let token = get_current_user_token();
-    let path = translate_str(token, path);
-
-    if let Some(inode) = open_file(&path, OpenFlags::from_bits(flags).unwrap()) {
-        let mut inner = task.inner.lock();
-        let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
-        fd as isize
-    } else {
-        -1
+// copy `value` to the current task at `dst`
+fn write_user<T: Copy>(dst: usize, value: &T) -> Result<(), Errno> {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    Ok(inner.memory_set.write_user(dst, value)?)
+}
+
+// read a `T` from the current task at `src`
+fn read_user<T: Copy + Default>(src: usize) -> Result<T, Errno> {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    Ok(inner.memory_set.read_user(src)?)
+}
+
+// the longest path, including the nul
+const PATH_MAX: usize = 4096;
+
+// read a path from the current task, ENAMETOOLONG past `PATH_MAX`
+fn read_user_cstr(ptr: *const u8) -> Result<String, Errno> {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    inner
+        .memory_set
+        .read_user_cstr(ptr as usize, PATH_MAX)?
+        .ok_or(Errno::ENAMETOOLONG)
+}
+
+// the directory a relative `path` starts from and the path, `dirfd` must be a directory, or
+// AT_FDCWD for the working directory. An absolute path ignores the directory.
+fn resolve_path(dirfd: isize, path: &str) -> Result<(Arc<Inode>, String), Errno> {
+    if path.is_empty() {
+        return Err(Errno::ENOENT);
+    }
+    let cwd = get_current_process().unwrap().inner.lock().cwd.clone();
+    if path.starts_with('/') || dirfd == AT_FDCWD {
+        return Ok((cwd, path.into()));
+    }
+    let dir = get_file(dirfd as usize)?;
+    if dir.stat().mode & Stat::S_IFDIR == 0 {
+        return Err(Errno::ENOTDIR);
+    }
+    match (dir.inode(), dir.path()) {
+        (Some(inode), _) => Ok((inode, path.into())),
+        // a directory in /proc
+        (None, Some(dir)) => Ok((cwd, absolute_path(&dir, path))),
+        (None, None) => Err(Errno::ENOTDIR),
     }
 }
 
-fn sys_close(fd: usize) -> isize {
-    let task = get_current_task().unwrap();
-    let mut inner = task.inner.lock();
-    if fd >= inner.fd_table.len() {
-        return -1;
+// the mode is ignored, files have no permissions
+fn sys_openat(dirfd: isize, path: *const u8, flags: u32) -> SyscallResult {
+    let (base, path) = resolve_path(dirfd, &read_user_cstr(path)?)?;
+    // flags we don't know, such as O_LARGEFILE, are ignored
+    let cloexec = flags & O_CLOEXEC != 0;
+    let flags = OpenFlags::from_bits_truncate(flags);
+
+    let file = open_path(&base, &path, flags)?;
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    let fd = inner.alloc_fd().ok_or(Errno::EMFILE)?;
+    inner.fd_table[fd] = Some(FileDescriptor::new(file, cloexec));
+    Ok(fd)
+}
+fn sys_close(fd: usize) -> SyscallResult {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    match inner.fd_table.get_mut(fd) {
+        Some(file) if file.is_some() => {
+            file.take();
+            Ok(0)
+        }
+        _ => Err(Errno::EBADF),
+    }
+}
+
+// the read end goes to pipefd[0] and the write end to pipefd[1]
+fn sys_pipe2(pipefd: usize, flags: u32) -> SyscallResult {
+    if flags & !(O_CLOEXEC | OpenFlags::NONBLOCK.bits()) != 0 {
+        return Err(Errno::EINVAL);
     }
-    if inner.fd_table[fd].is_none() {
-        return -1;
+    let cloexec = flags & O_CLOEXEC != 0;
+    let (read_end, write_end) = make_pipe(OpenFlags::from_bits_truncate(flags).status());
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    let read_fd = inner.alloc_fd().ok_or(Errno::EMFILE)?;
+    inner.fd_table[read_fd] = Some(FileDescriptor::new(read_end, cloexec));
+    let Some(write_fd) = inner.alloc_fd() else {
+        inner.fd_table[read_fd] = None;
+        return Err(Errno::EMFILE);
+    };
+    inner.fd_table[write_fd] = Some(FileDescriptor::new(write_end, cloexec));
+    let fds = [read_fd as i32, write_fd as i32];
+    if let Err(err) = inner.memory_set.write_user(pipefd, &fds) {
+        inner.fd_table[read_fd] = None;
+        inner.fd_table[write_fd] = None;
+        return Err(err.into());
     }
-    inner.fd_table[fd].take();
-    0
+    Ok(0)
 }
 
-fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
-    let task = get_current_task().unwrap();
-    let inner = task.inner.lock();
-    if fd >= inner.fd_table.len() {
-        return -1;
+// the next entries of the directory opened as `fd`, return how many bytes they take
+fn sys_getdents64(fd: usize, dirp: usize, count: usize) -> SyscallResult {
+    let dirents = get_file(fd)?.getdents(count)?;
+    get_current_process()
+        .unwrap()
+        .inner
+        .lock()
+        .memory_set
+        .copy_to_user(dirp, &dirents)?;
+    Ok(dirents.len())
+}
+
+// the mode is ignored, files have no permissions
+fn sys_mkdirat(dirfd: isize, path: *const u8) -> SyscallResult {
+    let (base, path) = resolve_path(dirfd, &read_user_cstr(path)?)?;
+    make_dir(&base, &path)?;
+    Ok(0)
+}
+
+// remove a file, or an empty directory with AT_REMOVEDIR
+fn sys_unlinkat(dirfd: isize, path: *const u8, flags: usize) -> SyscallResult {
+    if flags & !AT_REMOVEDIR != 0 {
+        return Err(Errno::EINVAL);
+    }
+    let (base, path) = resolve_path(dirfd, &read_user_cstr(path)?)?;
+    remove_path(&base, &path, flags & AT_REMOVEDIR != 0)?;
+    Ok(0)
+}
+
+// only RENAME_NOREPLACE of the flags, which keeps the target if there is one
+fn sys_renameat2(
+    olddirfd: isize,
+    oldpath: *const u8,
+    newdirfd: isize,
+    newpath: *const u8,
+    flags: usize,
+) -> SyscallResult {
+    if flags & !RENAME_NOREPLACE != 0 {
+        return Err(Errno::EINVAL);
+    }
+    let (oldbase, oldpath) = resolve_path(olddirfd, &read_user_cstr(oldpath)?)?;
+    let (newbase, newpath) = resolve_path(newdirfd, &read_user_cstr(newpath)?)?;
+    if flags & RENAME_NOREPLACE != 0 && stat_path(&newbase, &newpath).is_ok() {
+        return Err(Errno::EEXIST);
+    }
+    rename_path(&oldbase, &oldpath, &newbase, &newpath)?;
+    Ok(0)
+}
+
+// the lowest free descriptor for the file of `oldfd`, without FD_CLOEXEC
+fn sys_dup(oldfd: usize) -> SyscallResult {
+    dup_from(oldfd, 0, false)
+}
+
+// `newfd` for the file of `oldfd`, closing what `newfd` was before
+fn sys_dup3(oldfd: usize, newfd: usize, flags: u32) -> SyscallResult {
+    if flags & !O_CLOEXEC != 0 || oldfd == newfd {
+        return Err(Errno::EINVAL);
     }
-    if let Some(file) = &inner.fd_table[fd] {
-        let file = file.clone();
-        if !file.readable() {
-            return -1;
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    let file = match inner.fd_table.get(oldfd) {
+        Some(Some(fd)) => fd.file.clone(),
+        _ => return Err(Errno::EBADF),
+    };
+    if newfd >= FD_LIMIT {
+        return Err(Errno::EBADF);
+    }
+    if newfd >= inner.fd_table.len() {
+        inner.fd_table.resize(newfd + 1, None);
+    }
+    inner.fd_table[newfd] = Some(FileDescriptor::new(file, flags & O_CLOEXEC != 0));
+    Ok(newfd)
+}
+
+// the lowest free descriptor not below `min` for the file of `oldfd`
+fn dup_from(oldfd: usize, min: usize, cloexec: bool) -> SyscallResult {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    let file = match inner.fd_table.get(oldfd) {
+        Some(Some(fd)) => fd.file.clone(),
+        _ => return Err(Errno::EBADF),
+    };
+    let newfd = inner.alloc_fd_from(min).ok_or(Errno::EMFILE)?;
+    inner.fd_table[newfd] = Some(FileDescriptor::new(file, cloexec));
+    Ok(newfd)
+}
+
+fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> SyscallResult {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    let Some(Some(entry)) = inner.fd_table.get_mut(fd) else {
+        return Err(Errno::EBADF);
+    };
+    match cmd {
+        F_DUPFD | F_DUPFD_CLOEXEC => {
+            if arg >= FD_LIMIT {
+                return Err(Errno::EINVAL);
+            }
+            drop(inner);
+            dup_from(fd, arg, cmd == F_DUPFD_CLOEXEC)
         }
-        drop(inner);
-        file.read(UserBuffer::new(transfer_byte_buffer(buf, len))) as isize
-    } else {
-        -1
+        F_GETFD => Ok(if entry.cloexec { FD_CLOEXEC } else { 0 }),
+        F_SETFD => {
+            entry.cloexec = arg & FD_CLOEXEC != 0;
+            Ok(0)
+        }
+        F_GETFL => {
+            let access = match (entry.file.readable(), entry.file.writable()) {
+                (true, true) => OpenFlags::RDWR,
+                (false, true) => OpenFlags::WRONLY,
+                _ => OpenFlags::RDONLY,
+            };
+            Ok((access | entry.file.status_flags()).bits() as usize)
+        }
+        // the access mode and the creation flags stay as they are
+        F_SETFL => {
+            let flags = OpenFlags::from_bits_truncate(arg as u32).status();
+            entry.file.set_status_flags(flags);
+            Ok(0)
+        }
+        _ => Err(Errno::EINVAL),
     }
 }
 
-fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    let task = get_current_task().unwrap();
-    let inner = task.inner.lock();
-    if fd >= inner.fd_table.len() {
-        return -1;
+// the file opened as `fd` by the current process
+fn get_file(fd: usize) -> Result<Arc<dyn File + Send + Sync>, Errno> {
+    let process = get_current_process().unwrap();
+    let inner = process.inner.lock();
+    match inner.fd_table.get(fd) {
+        Some(Some(fd)) => Ok(fd.file.clone()),
+        _ => Err(Errno::EBADF),
     }
-    if let Some(file) = &inner.fd_table[fd] {
-        let file = file.clone();
-        if !file.writable() {
-            return -1;
+}
+
+// The most one read or write moves, a larger one is short like one above MAX_RW_COUNT on Linux.
+// The data goes through a kernel buffer, as another thread may unmap the user buffer or fork
+// while the file blocks.
+const RW_MAX: usize = 0x10000;
+
+fn sys_read(fd: usize, buf: *const u8, len: usize) -> SyscallResult {
+    let file = get_file(fd)?;
+    if !file.readable() {
+        return Err(Errno::EBADF);
+    }
+    let len = len.min(RW_MAX);
+    let process = get_current_process().unwrap();
+    process
+        .inner
+        .lock()
+        .memory_set
+        .check_user(buf as usize, len, MapPermission::W)?;
+    let mut data = vec![0u8; len];
+    let n = file.read(UserBuffer::new(vec![&mut data[..]]))?;
+    process
+        .inner
+        .lock()
+        .memory_set
+        .copy_to_user(buf as usize, &data[..n])?;
+    Ok(n)
+}
+
+fn sys_write(fd: usize, buf: *const u8, len: usize) -> SyscallResult {
+    let file = get_file(fd)?;
+    if !file.writable() {
+        return Err(Errno::EBADF);
+    }
+    let mut data = vec![0u8; len.min(RW_MAX)];
+    get_current_process()
+        .unwrap()
+        .inner
+        .lock()
+        .memory_set
+        .copy_from_user(buf as usize, &mut data)?;
+    file.write(UserBuffer::new(vec![&mut data[..]]))
+}
+
+// the most iovecs readv and writev take
+const IOV_MAX: usize = 1024;
+
+// read into each iovec in turn, stop at a short read
+fn sys_readv(fd: usize, iov: usize, iovcnt: usize) -> SyscallResult {
+    if iovcnt > IOV_MAX {
+        return Err(Errno::EINVAL);
+    }
+    let mut total = 0;
+    for i in 0..iovcnt {
+        let iov: IoVec = read_user(iov + i * size_of::<IoVec>())?;
+        let n = sys_read(fd, iov.base as *const u8, iov.len)?;
+        total += n;
+        if n < iov.len {
+            break;
         }
-        drop(inner);
-        file.write(UserBuffer::new(transfer_byte_buffer(buf, len))) as isize
-    } else {
-        -1
     }
+    Ok(total)
+}
+
+// write each iovec in turn, stop at a short write
+fn sys_writev(fd: usize, iov: usize, iovcnt: usize) -> SyscallResult {
+    if iovcnt > IOV_MAX {
+        return Err(Errno::EINVAL);
+    }
+    let mut total = 0;
+    for i in 0..iovcnt {
+        let iov: IoVec = read_user(iov + i * size_of::<IoVec>())?;
+        let n = sys_write(fd, iov.base as *const u8, iov.len)?;
+        total += n;
+        if n < iov.len {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+// Reads and writes block until they can be done, so a file is always ready for what it is
+// opened for and ppoll never waits. The timeout and the signal mask are ignored.
+fn sys_ppoll(fds: usize, nfds: usize) -> SyscallResult {
+    let mut ready = 0;
+    for i in 0..nfds {
+        let ptr = fds + i * size_of::<PollFd>();
+        let mut pollfd: PollFd = read_user(ptr)?;
+        pollfd.revents = match get_file(pollfd.fd as usize) {
+            _ if pollfd.fd < 0 => 0,
+            Ok(file) => {
+                let mut revents = 0;
+                if file.readable() {
+                    revents |= POLLIN;
+                }
+                if file.writable() {
+                    revents |= POLLOUT;
+                }
+                revents & pollfd.events
+            }
+            Err(_) => POLLNVAL,
+        };
+        if pollfd.revents != 0 {
+            ready += 1;
+        }
+        write_user(ptr, &pollfd)?;
+    }
+    Ok(ready)
+}
+
+fn sys_ioctl(fd: usize, request: usize, arg: usize) -> SyscallResult {
+    if !get_file(fd)?.is_tty() {
+        return Err(Errno::ENOTTY);
+    }
+    match request {
+        TIOCGWINSZ => write_user(
+            arg,
+            &WinSize {
+                row: 24,
+                col: 80,
+                xpixel: 0,
+                ypixel: 0,
+            },
+        )?,
+        TCGETS => write_user(arg, &Termios::raw())?,
+        // the console mode cannot be changed
+        TCSETS | TCSETSW | TCSETSF => {}
+        _ => return Err(Errno::ENOTTY),
+    }
+    Ok(0)
+}
+
+fn sys_fstat(fd: usize, statbuf: usize) -> SyscallResult {
+    let stat = get_file(fd)?.stat();
+    write_user(statbuf, &stat)?;
+    Ok(0)
+}
+
+fn sys_newfstatat(dirfd: isize, path: *const u8, statbuf: usize, flags: usize) -> SyscallResult {
+    let path = read_user_cstr(path)?;
+    if path.is_empty() && flags & AT_EMPTY_PATH != 0 {
+        return sys_fstat(dirfd as usize, statbuf);
+    }
+    let (base, path) = resolve_path(dirfd, &path)?;
+    let stat = stat_path(&base, &path)?;
+    write_user(statbuf, &stat)?;
+    Ok(0)
 }
 
-fn sys_exit(exit_code: i32) -> isize {
+// Every path but those in /proc is on the disk
+fn sys_statfs(path: *const u8, buf: usize) -> SyscallResult {
+    let (base, path) = resolve_path(AT_FDCWD, &read_user_cstr(path)?)?;
+    let statfs = match fs_usage(&base, &path)? {
+        Some(usage) => StatFs {
+            fs_type: StatFs::EASY_FS_MAGIC,
+            blocks: usage.blocks as u64,
+            bfree: usage.free_blocks as u64,
+            bavail: usage.free_blocks as u64,
+            files: usage.inodes as u64,
+            ffree: usage.free_inodes as u64,
+            ..StatFs::default()
+        },
+        None => StatFs {
+            fs_type: StatFs::PROC_SUPER_MAGIC,
+            flags: StatFs::ST_RDONLY,
+            ..StatFs::default()
+        },
+    };
+    write_user(
+        buf,
+        &StatFs {
+            bsize: BLOCK_SZ as u64,
+            frsize: BLOCK_SZ as u64,
+            namelen: NAME_LENGTH_LIMIT as u64,
+            ..statfs
+        },
+    )?;
+    Ok(0)
+}
+
+// the working directory and its nul, return their length
+fn sys_getcwd(buf: usize, size: usize) -> SyscallResult {
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    let cwd = format!("{}\0", inner.cwd_path);
+    if size < cwd.len() {
+        return Err(Errno::ERANGE);
+    }
+    inner.memory_set.copy_to_user(buf, cwd.as_bytes())?;
+    Ok(cwd.len())
+}
+
+// the working directory is on the disk, /proc cannot be one
+fn sys_chdir(path: *const u8) -> SyscallResult {
+    let (base, path) = resolve_path(AT_FDCWD, &read_user_cstr(path)?)?;
+    let dir = open_dir(&base, &path)?;
+    let process = get_current_process().unwrap();
+    let mut inner = process.inner.lock();
+    inner.cwd_path = absolute_path(&inner.cwd_path, &path);
+    inner.cwd = dir;
+    Ok(0)
+}
+
+// exit the current thread
+fn sys_exit(exit_code: i32) -> SyscallResult {
     // mark current task to exit and schedule
     mark_current_exit(exit_code);
     schedule();
     unreachable!()
 }
 
-fn sys_yield() -> isize {
+// exit every thread of the current process
+fn sys_exit_group(exit_code: i32) -> SyscallResult {
+    mark_current_exit_group(exit_code);
+    schedule();
+    unreachable!()
+}
+
+// 0 is written at `tidptr` when the current thread exits, e.g. for pthread_join
+fn sys_set_tid_address(tidptr: usize) -> SyscallResult {
+    let task = get_current_task().unwrap();
+    task.inner.lock().clear_child_tid = tidptr;
+    Ok(task.tid)
+}
+
+// Only FUTEX_WAIT without a timeout and FUTEX_WAKE
+fn sys_futex(uaddr: usize, op: usize, val: usize, timeout: usize) -> SyscallResult {
+    match op & !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME) {
+        FUTEX_WAIT if timeout == 0 => {
+            futex_wait(uaddr, val as u32)?;
+            Ok(0)
+        }
+        FUTEX_WAKE => {
+            let process = get_current_process().unwrap();
+            let mut inner = process.inner.lock();
+            futex_wake(&mut inner.memory_set, uaddr, val)
+        }
+        _ => Err(Errno::ENOSYS),
+    }
+}
+
+fn sys_yield() -> SyscallResult {
     mark_current_suspend();
     schedule();
-    0
+    Ok(0)
 }
 
-fn sys_get_time() -> isize {
-    get_time_ms() as isize
+fn sys_kill(pid: isize, signum: usize) -> SyscallResult {
+    send_signal(pid, signum)?;
+    Ok(0)
 }
 
-fn sys_getpid() -> isize {
-    get_current_task().unwrap().get_pid() as isize
+fn sys_tkill(tid: isize, signum: usize) -> SyscallResult {
+    if tid <= 0 {
+        return Err(Errno::EINVAL);
+    }
+    send_thread_signal(None, tid as usize, signum)?;
+    Ok(0)
 }
 
-fn sys_fork() -> isize {
-    let current = get_current_task().unwrap();
+fn sys_tgkill(tgid: isize, tid: isize, signum: usize) -> SyscallResult {
+    if tgid <= 0 || tid <= 0 {
+        return Err(Errno::EINVAL);
+    }
+    send_thread_signal(Some(tgid as usize), tid as usize, signum)?;
+    Ok(0)
+}
+
+fn sys_rt_sigaction(signum: usize, act: usize, oldact: usize, sigsetsize: usize) -> SyscallResult {
+    if sigsetsize != size_of::<SigSet>() || !(1..=Signal::NSIG).contains(&signum) {
+        return Err(Errno::EINVAL);
+    }
+    let process = get_current_process().unwrap();
+    let old = process.inner.lock().signals.action(signum);
+    if act != 0 {
+        if signum == Signal::SIGKILL || signum == Signal::SIGSTOP {
+            return Err(Errno::EINVAL);
+        }
+        let mut action: SigAction = read_user(act)?;
+        action.mask = action.mask.blockable();
+        process.inner.lock().signals.set_action(signum, action);
+    }
+    if oldact != 0 {
+        write_user(oldact, &old)?;
+    }
+    Ok(0)
+}
+
+fn sys_rt_sigprocmask(how: usize, set: usize, oldset: usize, sigsetsize: usize) -> SyscallResult {
+    if sigsetsize != size_of::<SigSet>() {
+        return Err(Errno::EINVAL);
+    }
+    let task = get_current_task().unwrap();
+    let old = task.inner.lock().blocked;
+    if set != 0 {
+        let set: SigSet = read_user(set)?;
+        let blocked = match how {
+            SIG_BLOCK => old.0 | set.0,
+            SIG_UNBLOCK => old.0 & !set.0,
+            SIG_SETMASK => set.0,
+            _ => return Err(Errno::EINVAL),
+        };
+        task.inner.lock().blocked = SigSet(blocked).blockable();
+    }
+    if oldset != 0 {
+        write_user(oldset, &old)?;
+    }
+    Ok(0)
+}
 
-    let child = current.fork();
-    let child_pid = child.get_pid();
-    let child_inner = child.inner.lock();
-    child_inner.get_trap_context().x[10] = 0;
+fn sys_uname(buf: usize) -> SyscallResult {
+    write_user(buf, &Utsname::new())?;
+    Ok(0)
+}
 
-    child_pid as isize
+// There are no load averages, and the memory is counted in bytes
+fn sys_sysinfo(info: usize) -> SyscallResult {
+    let (swap_pages, swap_free) = swap_usage();
+    write_user(
+        info,
+        &SysInfo {
+            uptime: get_time_val().sec as i64,
+            totalram: (frame_total() * PAGE_SIZE) as u64,
+            freeram: (frame_remaining() * PAGE_SIZE) as u64,
+            totalswap: (swap_pages * PAGE_SIZE) as u64,
+            freeswap: (swap_free * PAGE_SIZE) as u64,
+            procs: processes().len() as u16,
+            mem_unit: 1,
+            ..SysInfo::default()
+        },
+    )?;
+    Ok(0)
 }
 
-fn sys_exec(path: *const u8) -> isize {
-    // open file
-    let token = get_current_user_token();
-    let path = translate_str(token, path);
-    if let Some(inode) = open_file(&path, OpenFlags::RDONLY) {
-        let data = inode.read_all();
-        get_current_task().unwrap().exec(&data)
-    } else {
-        -1
+// The timezone is ignored
+fn sys_gettimeofday(tv: usize) -> SyscallResult {
+    if tv != 0 {
+        write_user(tv, &get_time_val())?;
     }
+    Ok(0)
+}
+
+// Every clock is the time since boot
+fn sys_clock_gettime(_clockid: usize, tp: usize) -> SyscallResult {
+    write_user(tp, &get_time_spec())?;
+    Ok(0)
+}
+
+fn sys_getpid() -> SyscallResult {
+    Ok(get_current_process().unwrap().get_pid())
 }
 
-// we dont support option
-fn sys_waitpid(pid: isize, wstatus: *mut i32) -> isize {
-    get_current_task().unwrap().waitpid(pid, wstatus)
+fn sys_getppid() -> SyscallResult {
+    Ok(get_current_process().unwrap().get_ppid())
 }
 
-pub fn sys_mmap(start: usize, len: usize, port: usize) -> isize {
-    if mmap(start, len, port).is_ok() {
-        0
+fn sys_gettid() -> SyscallResult {
+    Ok(get_current_task().unwrap().tid)
+}
+
+// With CLONE_THREAD a thread of the current process, which shares the address space, the files
+// and the signal handlers, so CLONE_VM, CLONE_FILES and CLONE_SIGHAND are required.
+// Otherwise a fork: the child gets a copy of the address space, so CLONE_VM is only accepted with
+// CLONE_VFORK, whose child just calls execve or exits, and nothing else can be shared.
+// The exit signal, CLONE_FS, CLONE_SYSVSEM and CLONE_DETACHED are ignored.
+fn sys_clone(flags: usize, stack: usize, ptid: usize, tls: usize, ctid: usize) -> SyscallResult {
+    let flags = CloneFlags::from_bits(flags).ok_or(Errno::EINVAL)?;
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+
+    let child = if flags.contains(CloneFlags::THREAD) {
+        if !flags.contains(CloneFlags::VM | CloneFlags::FILES | CloneFlags::SIGHAND) {
+            return Err(Errno::EINVAL);
+        }
+        process.create_thread(&task)
     } else {
-        -1
+        if flags.intersects(CloneFlags::FILES | CloneFlags::SIGHAND)
+            || (flags.contains(CloneFlags::VM) && !flags.contains(CloneFlags::VFORK))
+        {
+            return Err(Errno::EINVAL);
+        }
+        process.fork(&task)
+    };
+    let child_tid = child.tid;
+    let trap_context = child.get_trap_context();
+    trap_context.x[10] = 0;
+    if stack != 0 {
+        trap_context.x[2] = stack;
     }
+    if flags.contains(CloneFlags::SETTLS) {
+        trap_context.x[4] = tls;
+    }
+    // the child is already runnable, so like Linux a bad ctid or ptid is ignored rather than
+    // failing a clone that has happened
+    if flags.contains(CloneFlags::CHILD_SETTID) {
+        let child_process = child.process.upgrade().unwrap();
+        let _ = child_process
+            .inner
+            .lock()
+            .memory_set
+            .write_user(ctid, &(child_tid as i32));
+    }
+    if flags.contains(CloneFlags::CHILD_CLEARTID) {
+        child.inner.lock().clear_child_tid = ctid;
+    }
+    if flags.contains(CloneFlags::PARENT_SETTID) {
+        let _ = write_user(ptid, &(child_tid as i32));
+    }
+
+    Ok(child_tid)
 }
 
-pub fn sys_munmap(start: usize, len: usize) -> isize {
-    if munmap(start, len).is_ok() {
-        0
-    } else {
-        -1
+// the most bytes of argv and envp, the strings and the pointers to them
+const ARG_MAX: usize = 0x20000;
+
+// read a NULL-terminated array of strings like argv, a NULL `ptr` is an empty array
+fn read_user_cstr_array(ptr: usize, size: &mut usize) -> Result<Vec<String>, Errno> {
+    let mut strings = Vec::new();
+    if ptr == 0 {
+        return Ok(strings);
     }
+    loop {
+        let entry = ptr
+            .checked_add(strings.len() * size_of::<usize>())
+            .ok_or(Errno::EFAULT)?;
+        let str_ptr: usize = read_user(entry)?;
+        if str_ptr == 0 {
+            return Ok(strings);
+        }
+        *size += size_of::<usize>();
+        // the string may use what is left of `ARG_MAX`, its nul included
+        let string = {
+            let process = get_current_process().unwrap();
+            let mut inner = process.inner.lock();
+            inner
+                .memory_set
+                .read_user_cstr(str_ptr, ARG_MAX.saturating_sub(*size))?
+                .ok_or(Errno::E2BIG)?
+        };
+        *size += string.len() + 1;
+        strings.push(string);
+    }
+}
+
+// like Linux, a script may name another script as its interpreter, up to 4 levels
+const SHEBANG_DEPTH: usize = 4;
+// the longest `#!` line, including `#!`
+const SHEBANG_MAX: usize = 256;
+
+fn sys_execve(path: *const u8, argv: usize, envp: usize) -> SyscallResult {
+    let task = get_current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let mut path = read_user_cstr(path)?;
+    // a script is named after itself rather than its interpreter
+    let name = path.clone();
+    let mut size = 0;
+    let mut args = read_user_cstr_array(argv, &mut size)?;
+    let envs = read_user_cstr_array(envp, &mut size)?;
+    // open file
+    let (base, file) = resolve_path(AT_FDCWD, &path)?;
+    let mut data = open_file(&base, &file, OpenFlags::RDONLY)?.read_all();
+    // run a script with its interpreter and arguments:
+    // interpreter [argument] script argv[1..]
+    for depth in 0.. {
+        let Some((interp, arg)) = parse_shebang(&data)? else {
+            break;
+        };
+        if depth == SHEBANG_DEPTH {
+            return Err(Errno::ELOOP);
+        }
+        let prefix: Vec<String> = [interp.clone()]
+            .into_iter()
+            .chain(arg)
+            .chain([path])
+            .collect();
+        size += prefix
+            .iter()
+            .map(|s| size_of::<usize>() + s.len() + 1)
+            .sum::<usize>();
+        if size > ARG_MAX {
+            return Err(Errno::E2BIG);
+        }
+        args.splice(..args.len().min(1), prefix);
+        let (base, file) = resolve_path(AT_FDCWD, &interp)?;
+        data = open_file(&base, &file, OpenFlags::RDONLY)?.read_all();
+        path = interp;
+    }
+    process.exec(&task, &name, &data, &args, &envs)?;
+    Ok(0)
+}
+
+// the interpreter and its optional argument in the first line of a script starting with `#!`
+fn parse_shebang(data: &[u8]) -> Result<Option<(String, Option<String>)>, Errno> {
+    let Some(line) = data.strip_prefix(b"#!") else {
+        return Ok(None);
+    };
+    let line = match line.iter().position(|&b| b == b'\n') {
+        Some(end) => &line[..end],
+        None => line,
+    };
+    if line.len() + 2 > SHEBANG_MAX {
+        return Err(Errno::ENOEXEC);
+    }
+    let line = core::str::from_utf8(line)
+        .map_err(|_| Errno::ENOEXEC)?
+        .trim_matches([' ', '\t']);
+    // everything after the interpreter is a single argument, as on Linux
+    let (interp, arg) = match line.split_once([' ', '\t']) {
+        Some((interp, arg)) => (interp, Some(arg.trim_start_matches([' ', '\t']))),
+        None => (line, None),
+    };
+    if interp.is_empty() {
+        return Err(Errno::ENOEXEC);
+    }
+    Ok(Some((String::from(interp), arg.map(String::from))))
+}
+
+// Children only exit, WUNTRACED and WCONTINUED never report anything.
+// The resource usage is all zero.
+fn sys_wait4(pid: isize, wstatus: *mut i32, options: usize, rusage: usize) -> SyscallResult {
+    if options & !(WNOHANG | WUNTRACED | WCONTINUED) != 0 {
+        return Err(Errno::EINVAL);
+    }
+    let pid = get_current_process()
+        .unwrap()
+        .wait4(pid, wstatus, options & WNOHANG != 0)?;
+    if pid != 0 && rusage != 0 {
+        write_user(rusage, &[0u8; RUSAGE_SIZE])?;
+    }
+    Ok(pid)
+}
+
+pub fn sys_mmap(
+    start: usize,
+    len: usize,
+    port: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> SyscallResult {
+    let flags = MmapFlags::from_bits(flags as u32).ok_or(Errno::EINVAL)?;
+    let file = if flags.contains(MmapFlags::ANONYMOUS) {
+        None
+    } else {
+        let file = get_file(fd)?;
+        // a shared writable mapping writes to the file
+        if !file.readable()
+            || (flags.contains(MmapFlags::SHARED) && port & 0b10 != 0 && !file.writable())
+        {
+            return Err(Errno::EACCES);
+        }
+        Some((file.inode().ok_or(Errno::EACCES)?, offset))
+    };
+    mmap(start, len, port, flags, file)
+}
+
+// return the new program break, or the current one if it cannot be moved
+pub fn sys_brk(new_brk: usize) -> SyscallResult {
+    Ok(brk(new_brk))
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> SyscallResult {
+    munmap(start, len)?;
+    Ok(0)
+}
+
+pub fn sys_msync(start: usize, len: usize) -> SyscallResult {
+    msync(start, len)?;
+    Ok(0)
 }
@@ -6,10 +6,38 @@ pub fn get_time() -> usize {
     time::read()
 }
 
-pub fn get_time_ms() -> usize {
-    time::read() / (TIMEBASE_FREQUENCY / MSEC_PER_SEC)
-}
-
 pub fn set_next_trigger() {
     sbi::set_timer(get_time() + TIMEBASE_FREQUENCY / TICK_PER_SEC)
 }
+
+/// `struct timeval` of gettimeofday
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+/// `struct timespec` of clock_gettime
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+pub fn get_time_val() -> TimeVal {
+    let time = get_time();
+    TimeVal {
+        sec: time / TIMEBASE_FREQUENCY,
+        usec: time % TIMEBASE_FREQUENCY * USEC_PER_SEC / TIMEBASE_FREQUENCY,
+    }
+}
+
+pub fn get_time_spec() -> TimeSpec {
+    let time = get_time();
+    TimeSpec {
+        sec: time / TIMEBASE_FREQUENCY,
+        nsec: time % TIMEBASE_FREQUENCY * NSEC_PER_SEC / TIMEBASE_FREQUENCY,
+    }
+}
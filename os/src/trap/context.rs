@@ -3,7 +3,7 @@ use crate::mm::kernel_space::get_kernel_token;
 use super::*;
 use riscv::register::sstatus::{self, SPP};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[repr(C)] //? why is this necessary
 pub struct TrapContext {
     /// general register
@@ -25,6 +25,18 @@ impl TrapContext {
         self.x[2] = sp;
     }
 
+    /// The syscall number, in a7
+    pub fn syscall_id(&self) -> usize {
+        self.x[17]
+    }
+
+    /// The six syscall arguments, in a0-a5
+    pub fn syscall_args(&self) -> [usize; 6] {
+        [
+            self.x[10], self.x[11], self.x[12], self.x[13], self.x[14], self.x[15],
+        ]
+    }
+
     // kernel_sp for kernel stack's sp, for storing trap context, etc.
     // current each app have a kernel stack
     // sp for user stack's sp, user stack is in the user address space, for app runtime stack
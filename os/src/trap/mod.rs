@@ -1,10 +1,17 @@
 mod context;
 
-use crate::config::TRAP_CONTEXT;
-use crate::process::processor::{get_current_trap_context, get_current_user_token, schedule};
-use crate::process::{mark_current_exit, mark_current_suspend};
+use crate::fs::poll_console;
+use crate::mm::MapPermission;
+use crate::process::processor::{
+    get_current_trap_context, get_current_trap_context_va, get_current_user_token, schedule,
+};
+use crate::process::signal::{handle_signals, Signal};
+use crate::process::{current_fault, handle_page_fault, mark_current_suspend};
 use crate::timer::set_next_trigger;
-use crate::{config::TRAMPOLINE, syscall::syscall};
+use crate::{
+    config::{is_kernel_stack_guard, TRAMPOLINE},
+    syscall::syscall,
+};
 use core::arch::{asm, global_asm};
 use log::error;
 use riscv::register::sepc;
@@ -36,44 +43,61 @@ pub fn trap_handler() {
     match scause.cause() {
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
+            poll_console();
             mark_current_suspend();
             schedule();
         }
         Trap::Exception(Exception::IllegalInstruction) => {
             error!("Illegal instruction");
-            mark_current_exit(-1);
-            schedule();
-            unreachable!("{}:{}", file!(), line!());
+            current_fault(Signal::SIGILL);
         }
         Trap::Exception(Exception::UserEnvCall) => {
             context.sepc += 4;
             // current proccess maybe changed
-            let ret =
-                syscall(context.x[17], [context.x[10], context.x[11], context.x[12]]) as usize;
+            let ret = syscall(context.syscall_id(), context.syscall_args()) as usize;
             let context = get_current_trap_context();
             context.x[10] = ret;
         }
         Trap::Exception(Exception::LoadPageFault) => {
-            error!("Load page fault");
-            mark_current_exit(-1);
-            schedule();
-            unreachable!("{}:{}", file!(), line!());
+            if let Err(signum) = handle_page_fault(stval::read(), MapPermission::R) {
+                error!("Load page fault");
+                current_fault(signum);
+            }
+        }
+        Trap::Exception(Exception::InstructionPageFault) => {
+            if let Err(signum) = handle_page_fault(stval::read(), MapPermission::X) {
+                error!(
+                    "Instruction page fault. sepc=0x{:x}, stval=0x{:x}",
+                    sepc::read(),
+                    stval::read()
+                );
+                current_fault(signum);
+            }
         }
         Trap::Exception(Exception::StorePageFault) => {
-            error!(
-                "Store page fault. sepc=0x{:x}, stval=0x{:x}",
-                sepc::read(),
-                stval::read()
-            );
-            mark_current_exit(-1);
-            schedule();
-            unreachable!("{}:{}", file!(), line!());
+            if let Err(signum) = handle_page_fault(stval::read(), MapPermission::W) {
+                error!(
+                    "Store page fault. sepc=0x{:x}, stval=0x{:x}",
+                    sepc::read(),
+                    stval::read()
+                );
+                current_fault(signum);
+            }
         }
         Trap::Exception(Exception::StoreFault) => {
             error!("Store fault");
-            mark_current_exit(-1);
-            schedule();
-            unreachable!("{}:{}", file!(), line!());
+            current_fault(Signal::SIGSEGV);
+        }
+        Trap::Exception(Exception::LoadFault | Exception::InstructionFault) => {
+            error!("Access fault, stval=0x{:x}", stval::read());
+            current_fault(Signal::SIGSEGV);
+        }
+        Trap::Exception(Exception::InstructionMisaligned | Exception::StoreMisaligned) => {
+            error!("Misaligned access, stval=0x{:x}", stval::read());
+            current_fault(Signal::SIGBUS);
+        }
+        Trap::Exception(Exception::Breakpoint) => {
+            current_fault(Signal::SIGTRAP);
         }
         _ => {
             error!(
@@ -82,9 +106,7 @@ pub fn trap_handler() {
                 stval::read(),
                 context
             );
-            mark_current_exit(-1);
-            schedule();
-            unreachable!("{}:{}", file!(), column!());
+            current_fault(Signal::SIGILL);
         }
     }
     trap_return();
@@ -92,6 +114,7 @@ pub fn trap_handler() {
 
 #[no_mangle]
 pub fn trap_return() -> ! {
+    handle_signals();
     set_user_trap_entry();
     extern "C" {
         // strampoline == TRAMPOLINE
@@ -99,7 +122,7 @@ pub fn trap_return() -> ! {
         fn __restore();
     }
     let restore_va = __restore as usize - __alltraps as usize + TRAMPOLINE;
-    let trap_cx_ptr = TRAP_CONTEXT;
+    let trap_cx_ptr = get_current_trap_context_va();
     let user_satp = get_current_user_token();
     unsafe {
         asm!(
@@ -121,11 +144,26 @@ fn set_user_trap_entry() {
 }
 
 fn set_kernel_trap_entry() {
-    unsafe { stvec::write(trap_from_kernel as usize, stvec::TrapMode::Direct) }
+    extern "C" {
+        fn __trap_from_kernel();
+    }
+    unsafe { stvec::write(__trap_from_kernel as usize, stvec::TrapMode::Direct) }
 }
 
+// Called by `__trap_from_kernel` on a stack of its own
 #[no_mangle]
 fn trap_from_kernel() -> ! {
+    let page_fault = matches!(
+        scause::read().cause(),
+        Trap::Exception(Exception::LoadPageFault | Exception::StorePageFault)
+    );
+    if page_fault && is_kernel_stack_guard(stval::read()) {
+        panic!(
+            "Kernel stack overflow, sepc=0x{:x}, stval=0x{:x}",
+            sepc::read(),
+            stval::read()
+        );
+    }
     panic!(
         "sepc=0x{:x}, stval={:?}, scause={:?}",
         sepc::read(),
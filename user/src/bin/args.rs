@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{args, env, exec, exit, fork, waitpid};
+
+/*
+ * argv and envp passed to exec reach the new program.
+ * expected: return 0
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut args = args();
+    assert_eq!(args.next(), Some("args"));
+    match args.next() {
+        // run again with arguments
+        None => {
+            let pid = fork();
+            if pid == 0 {
+                exec(
+                    "args\0",
+                    &["args\0", "hello\0", "world\0"],
+                    &["KEY=value\0", "EMPTY=\0"],
+                );
+                exit(-1);
+            }
+            let mut exit_code = -1;
+            assert_eq!(waitpid(pid, &mut exit_code), pid);
+            exit_code
+        }
+        Some(first) => {
+            assert_eq!(first, "hello");
+            assert_eq!(args.next(), Some("world"));
+            assert_eq!(args.next(), None);
+            assert_eq!(env("KEY"), Some("value"));
+            assert_eq!(env("EMPTY"), Some(""));
+            assert_eq!(env("KE"), None);
+            0
+        }
+    }
+}
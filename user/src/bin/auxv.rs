@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::auxv;
+
+/*
+ * the kernel passes an auxiliary vector after envp.
+ * expected: return 0
+*/
+
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_BASE: usize = 7;
+const AT_ENTRY: usize = 9;
+const AT_RANDOM: usize = 25;
+
+extern "C" {
+    fn _start();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(auxv(AT_PAGESZ), Some(4096));
+    assert_eq!(auxv(AT_PHENT), Some(56));
+    assert!(auxv(AT_PHNUM).unwrap() > 0);
+    // linked at a fixed address without an interpreter
+    assert_eq!(auxv(AT_ENTRY), Some(_start as usize));
+    assert_eq!(auxv(AT_BASE), Some(0));
+    let random = auxv(AT_RANDOM).unwrap() as *const [u8; 16];
+    assert_ne!(unsafe { *random }, [0; 16]);
+    assert_eq!(auxv(1000), None);
+    0
+}
@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{exit, fork, open, read, waitpid, write, Errno, OpenFlags};
+
+/*
+ * pass bad pointers to syscalls, the kernel returns -EFAULT instead of panicking, and
+ * -ENAMETOOLONG for a path without a nul in the first 4096 bytes.
+ * expected: return 0
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    // unmapped
+    let unmapped = unsafe { core::slice::from_raw_parts(0x1000 as *const u8, 16) };
+    assert_eq!(write(1, unmapped), Errno::EFAULT.as_ret());
+    // crosses from the stack into unmapped memory
+    let stack = [0u8; 16];
+    let across = unsafe { core::slice::from_raw_parts(stack.as_ptr(), 0x40_0000_0000) };
+    assert_eq!(write(1, across), Errno::EFAULT.as_ret());
+    // the trap context, a kernel page at the top of the address space
+    let kernel =
+        unsafe { core::slice::from_raw_parts(0usize.wrapping_sub(2 * 4096) as *const u8, 16) };
+    assert_eq!(write(1, kernel), Errno::EFAULT.as_ret());
+    // non-canonical, the stack again once the upper bits are dropped
+    let alias = (stack.as_ptr() as usize | (1 << 56)) as *const u8;
+    let alias = unsafe { core::slice::from_raw_parts(alias, 16) };
+    assert_eq!(write(1, alias), Errno::EFAULT.as_ret());
+    // no nul within PATH_MAX
+    let long = [b'a'; 5000];
+    let long = core::str::from_utf8(&long).unwrap();
+    assert_eq!(open(long, OpenFlags::RDONLY), Errno::ENAMETOOLONG.as_ret());
+    // read-only code
+    let text = unsafe { core::slice::from_raw_parts_mut(main as usize as *mut u8, 16) };
+    assert_eq!(read(0, text), Errno::EFAULT.as_ret());
+
+    let pid = fork();
+    if pid == 0 {
+        exit(0);
+    }
+    let exit_code = unsafe { &mut *(main as usize as *mut i32) };
+    assert_eq!(waitpid(pid, exit_code), Errno::EFAULT.as_ret());
+    0
+}
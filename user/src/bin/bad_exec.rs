@@ -0,0 +1,68 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{close, exec, open, read, write_file, Errno, OpenFlags};
+
+/*
+ * exec of a file that is not a valid riscv64 executable, or whose interpreter does not
+ * exist, fails and the caller keeps running in its old address space.
+ * expected: return 0
+*/
+
+// fill in a readable Elf64_Phdr of `p_type` for `size` bytes at `offset` in the file
+fn program_header(ph: &mut [u8], p_type: u32, offset: u64, vaddr: u64, size: u64) {
+    ph[0..4].copy_from_slice(&p_type.to_le_bytes());
+    // PF_R
+    ph[4..8].copy_from_slice(&4u32.to_le_bytes());
+    ph[8..16].copy_from_slice(&offset.to_le_bytes());
+    ph[16..24].copy_from_slice(&vaddr.to_le_bytes());
+    ph[24..32].copy_from_slice(&vaddr.to_le_bytes());
+    ph[32..40].copy_from_slice(&size.to_le_bytes());
+    ph[40..48].copy_from_slice(&size.to_le_bytes());
+    ph[48..56].copy_from_slice(&1u64.to_le_bytes());
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    write_file("bad_exec_text\0", b"echo hello\n");
+    assert_eq!(exec("bad_exec_text\0", &[], &[]), Errno::ENOEXEC.as_ret());
+
+    // the ELF header of this program without its program headers
+    let mut header = [0u8; 64];
+    let fd = open("bad_exec\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(read(fd as usize, &mut header), 64);
+    close(fd as usize);
+    write_file("bad_exec_truncated\0", &header);
+    assert_eq!(
+        exec("bad_exec_truncated\0", &[], &[]),
+        Errno::EINVAL.as_ret()
+    );
+
+    // e_machine is EM_X86_64
+    header[18] = 0x3e;
+    write_file("bad_exec_machine\0", &header);
+    assert_eq!(
+        exec("bad_exec_machine\0", &[], &[]),
+        Errno::ENOEXEC.as_ret()
+    );
+
+    // a PT_LOAD segment with the headers and a PT_INTERP naming a file that does not exist
+    header[18] = 0xf3;
+    let mut elf = [0u8; 192];
+    elf[..64].copy_from_slice(&header);
+    // e_type is ET_EXEC, then e_phoff and e_phnum
+    elf[16..18].copy_from_slice(&2u16.to_le_bytes());
+    elf[32..40].copy_from_slice(&64u64.to_le_bytes());
+    elf[56..58].copy_from_slice(&2u16.to_le_bytes());
+    program_header(&mut elf[64..120], 1, 0, 0x10000, 192);
+    program_header(&mut elf[120..176], 3, 176, 0x10000 + 176, 16);
+    elf[176..191].copy_from_slice(b"/no_such_loader");
+    write_file("bad_exec_interp\0", &elf);
+    assert_eq!(exec("bad_exec_interp\0", &[], &[]), Errno::ENOENT.as_ret());
+
+    assert_eq!(exec("no_such_file\0", &[], &[]), Errno::ENOENT.as_ret());
+    0
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{args, close, open, read, write_all, Errno, OpenFlags};
+
+/*
+ * cat [file..]: print the files one after another, stdin if there are none or for `-`.
+*/
+
+// copy `fd` to stdout
+fn copy(fd: usize) -> Result<(), Errno> {
+    let mut buf = [0u8; 512];
+    loop {
+        match Errno::from_ret(read(fd, &mut buf))? {
+            0 => return Ok(()),
+            n => write_all(1, &buf[..n])?,
+        }
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut paths: Vec<&str> = args().skip(1).collect();
+    if paths.is_empty() {
+        paths.push("-");
+    }
+    let mut status = 0;
+    for path in paths {
+        let ret = if path == "-" {
+            copy(0)
+        } else {
+            Errno::from_ret(open(path, OpenFlags::RDONLY)).and_then(|fd| {
+                let ret = copy(fd);
+                close(fd);
+                ret
+            })
+        };
+        if let Err(errno) = ret {
+            eprintln!("cat: {}: {}", path, errno.name());
+            status = 1;
+        }
+    }
+    status
+}
@@ -0,0 +1,173 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use user_lib::{fork, read_file, run_shell, signal::SIGTERM, sleep, waitpid, write_file};
+
+/*
+ * the coreutils print what they should on stdout and stderr, each run by the shell.
+ * expected: return 0
+*/
+
+// the utility, the script running it and its output, where `*` is any text
+const CASES: &[(&str, &str, &str)] = &[
+    ("echo", "echo -n a; echo b  c", "ab c\n"),
+    ("cat", "cat cu_a cu_b", "one\ntwo\nthree\n"),
+    ("cat", "cat cu_none; echo $?", "cat: cu_none: ENOENT\n1\n"),
+    ("wc", "wc cu_a", "       2       2       8 cu_a\n"),
+    (
+        "wc",
+        "wc -l cu_a cu_b",
+        "       2 cu_a\n       1 cu_b\n       3 total\n",
+    ),
+    ("head", "head cu_n", "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n"),
+    (
+        "head",
+        "head -n 2 cu_n cu_a",
+        "==> cu_n <==\n1\n2\n\n==> cu_a <==\none\ntwo\n",
+    ),
+    ("tail", "tail -n 3 cu_n", "13\n14\n15\n"),
+    ("grep", "grep ^ap cu_g", "apple\napricot\n"),
+    ("grep", "grep -in an cu_g", "2:Banana\n"),
+    ("grep", "grep -c p cu_g", "3\n"),
+    ("grep", "grep 'e.*pie$' cu_g", "cherry pie\n"),
+    ("grep", "grep -v a cu_g; echo $?", "cherry pie\n0\n"),
+    ("grep", "grep zzz cu_g; echo $?", "1\n"),
+    (
+        "hexdump",
+        "hexdump -C cu_a",
+        "00000000  6f 6e 65 0a 74 77 6f 0a * |one.two.|\n00000008\n",
+    ),
+    ("cp", "cp cu_a cu_c; cat cu_c", "one\ntwo\n"),
+    (
+        "mv",
+        "mv cu_c cu_d; cat cu_d cu_c",
+        "one\ntwo\ncat: cu_c: ENOENT\n",
+    ),
+    (
+        "rm",
+        "rm cu_d; rm cu_d; rm -f cu_d; echo $?",
+        "rm: cu_d: ENOENT\n0\n",
+    ),
+    (
+        "touch",
+        "touch cu_t; wc -c cu_t; rm cu_t",
+        "       0 cu_t\n",
+    ),
+    (
+        "mkdir",
+        "mkdir cu_dir; mkdir cu_dir; ls -a cu_dir",
+        "mkdir: cu_dir: EEXIST\n.\n..\n",
+    ),
+    (
+        "rmdir",
+        "rm cu_dir; rmdir cu_dir; ls cu_dir",
+        "rm: cu_dir: EISDIR\nls: cu_dir: ENOENT\n",
+    ),
+    (
+        "cp",
+        "mkdir cu_dir; cp cu_a cu_dir; mv cu_b cu_dir/cu_c; ls cu_dir",
+        "cu_a\ncu_c\n",
+    ),
+    (
+        "mv",
+        "mv cu_dir/cu_c cu_b; rm cu_dir/cu_a; rmdir cu_dir; cat cu_b",
+        "three\n",
+    ),
+    (
+        "cd",
+        "mkdir cu_dir; cd cu_dir; pwd; touch f; ls; cd ..; rm cu_dir/f; rmdir cu_dir; pwd",
+        "/cu_dir\nf\n/\n",
+    ),
+    ("ls", "ls cu_a cu_b", "cu_a\ncu_b\n"),
+    ("ls", "ls -l cu_a", "-rw* 1        8 cu_a\n"),
+    (
+        "stat",
+        "stat cu_a",
+        "  File: cu_a\n  Size: 8 * regular file\nInode: * Links: 1\nAccess: (0*/-rw*)\n",
+    ),
+    (
+        "ps",
+        "ps",
+        "  PID  PPID S THR CMD\n* S   1 coreutils\n* S   1 shell\n* R   1 ps\n",
+    ),
+    ("kill", "kill -l", "*15 TERM\n*"),
+    (
+        "kill",
+        "kill -s NOPE 1; echo $?",
+        "kill: unknown signal\n2\n",
+    ),
+    ("kill", "kill 99999; echo $?", "kill: 99999: ESRCH\n1\n"),
+    (
+        "sleep",
+        "sleep 0.05; echo $?; sleep x",
+        "0\nusage: sleep seconds\n",
+    ),
+    (
+        "df",
+        "df",
+        "Filesystem  1K-blocks * Mounted on\neasy-fs * /\nproc * 0 *- /proc\n",
+    ),
+    ("free", "free", "* total * used * free\nMem: *\nSwap: *\n"),
+    ("uptime", "uptime", "up *:*:*, * processes\n"),
+];
+
+// whether `text` matches `pattern`, in which `*` is any text
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        Some((&c, rest)) => text.first() == Some(&c) && glob(rest, &text[1..]),
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    write_file("cu_a\0", b"one\ntwo\n");
+    write_file("cu_b\0", b"three\n");
+    let lines: alloc::string::String = (1..=15).map(|i| format!("{}\n", i)).collect();
+    write_file("cu_n\0", lines.as_bytes());
+    write_file("cu_g\0", b"apple\nBanana\ncherry pie\napricot\n");
+
+    let mut failed = 0;
+    for (utility, script, expected) in CASES {
+        run_shell(&["shell\0", "-c\0", &format!("{}\0", script)], "cu_out\0");
+        let output = read_file("cu_out\0").unwrap();
+        if !glob(expected.as_bytes(), &output) {
+            println!(
+                "{}: `{}` printed {:?}, not {:?}",
+                utility,
+                script,
+                core::str::from_utf8(&output),
+                expected
+            );
+            failed += 1;
+        }
+    }
+
+    // kill a process that is still running
+    let child = fork();
+    if child == 0 {
+        loop {
+            sleep(10);
+        }
+    }
+    let pid = format!("{}\0", child);
+    assert_eq!(
+        run_shell(&["shell\0", "-c\0", "kill $1\0", "x\0", &pid], "cu_out\0"),
+        0
+    );
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 128 + SIGTERM as i32);
+
+    for path in ["cu_a\0", "cu_b\0", "cu_n\0", "cu_g\0", "cu_out\0"] {
+        user_lib::unlink(path);
+    }
+    assert_eq!(failed, 0);
+    0
+}
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use user_lib::{args, close, open, read, stat::stat, write_all, Errno, OpenFlags};
+
+/*
+ * cp source target: copy the file source to target, or into target if it is a directory.
+*/
+
+// copy the file at `from` to `to`, both nul-terminated
+fn copy(from: &str, to: &str) -> Result<(), Errno> {
+    let src = Errno::from_ret(open(from, OpenFlags::RDONLY))?;
+    let dst = match Errno::from_ret(open(
+        to,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    )) {
+        Ok(dst) => dst,
+        Err(errno) => {
+            close(src);
+            return Err(errno);
+        }
+    };
+    let mut buf = [0u8; 512];
+    let ret = loop {
+        match Errno::from_ret(read(src, &mut buf)) {
+            Ok(0) => break Ok(()),
+            Ok(n) => {
+                if let Err(errno) = write_all(dst, &buf[..n]) {
+                    break Err(errno);
+                }
+            }
+            Err(errno) => break Err(errno),
+        }
+    };
+    close(src);
+    close(dst);
+    ret
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let args: Vec<&str> = args().skip(1).collect();
+    let [from, to] = args[..] else {
+        eprintln!("usage: cp source target");
+        return 2;
+    };
+    if stat(from).is_ok_and(|stat| stat.is_dir()) {
+        eprintln!("cp: {}: is a directory", from);
+        return 1;
+    }
+    let to: String = match stat(to) {
+        Ok(stat) if stat.is_dir() => format!("{}/{}\0", to, from.rsplit('/').next().unwrap()),
+        _ => format!("{}\0", to),
+    };
+    match copy(from, &to) {
+        Ok(()) => 0,
+        Err(errno) => {
+            eprintln!(
+                "cp: {} to {}: {}",
+                from,
+                to.trim_end_matches('\0'),
+                errno.name()
+            );
+            1
+        }
+    }
+}
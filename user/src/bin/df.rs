@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String};
+use user_lib::stat::statfs;
+
+/*
+ * df: show the size and the use of the disk and of /proc, in KiB.
+*/
+
+// the filesystems and where they are mounted, nul-terminated
+const MOUNTS: &[(&str, &str)] = &[("easy-fs", "/\0"), ("proc", "/proc\0")];
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut status = 0;
+    println!(
+        "{:<10} {:>10} {:>10} {:>10} {:>4} Mounted on",
+        "Filesystem", "1K-blocks", "Used", "Available", "Use%"
+    );
+    for &(name, mount) in MOUNTS {
+        let mount_point = mount.trim_end_matches('\0');
+        let info = match statfs(mount) {
+            Ok(info) => info,
+            Err(errno) => {
+                eprintln!("df: {}: {}", mount_point, errno.name());
+                status = 1;
+                continue;
+            }
+        };
+        let kib = |blocks: u64| blocks * info.frsize / 1024;
+        let used = kib(info.blocks - info.bfree);
+        let avail = kib(info.bavail);
+        // rounded up as df does, and `-` for a filesystem without blocks
+        let percent: String = match used + avail {
+            0 => "-".into(),
+            size => format!("{}%", (used * 100).div_ceil(size)),
+        };
+        println!(
+            "{:<10} {:>10} {:>10} {:>10} {:>4} {}",
+            name,
+            kib(info.blocks),
+            used,
+            avail,
+            percent,
+            mount_point
+        );
+    }
+    status
+}
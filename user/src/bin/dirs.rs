@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use user_lib::{
+    chdir, dir::read_dir, exit, fork, getcwd, mkdir, read_file, rename, rmdir, stat::stat, unlink,
+    waitpid, write_file, Errno,
+};
+
+/*
+ * paths go through directories, with `.` and `..`, from the root or the working directory,
+ * which chdir changes, a forked child inherits and a rename moves.
+ * expected: return 0
+*/
+
+fn errno(ret: isize) -> Errno {
+    Errno::from_ret(ret).unwrap_err()
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(getcwd().unwrap(), "/");
+    assert_eq!(mkdir("dirs_a\0"), 0);
+    assert_eq!(mkdir("dirs_a/b\0"), 0);
+    assert_eq!(mkdir("/dirs_a/b/c/\0"), 0);
+    assert_eq!(errno(mkdir("dirs_a/b\0")), Errno::EEXIST);
+    assert_eq!(errno(mkdir("dirs_a/none/c\0")), Errno::ENOENT);
+    write_file("dirs_a/b/file\0", b"nested");
+    assert_eq!(errno(mkdir("dirs_a/b/file/c\0")), Errno::ENOTDIR);
+    assert_eq!(
+        read_file("/./dirs_a/b/c/../../b//file\0").unwrap(),
+        b"nested"
+    );
+    assert!(stat("dirs_a/b/c\0").unwrap().is_dir());
+
+    // `..` is the parent, and the root directory is its own parent
+    let entries = read_dir("dirs_a\0").unwrap();
+    let names: alloc::vec::Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, [".", "..", "b"]);
+    assert_eq!(entries[0].ino, stat("dirs_a\0").unwrap().ino);
+    assert_eq!(entries[1].ino, stat("/\0").unwrap().ino);
+    let entries = read_dir("dirs_a/b\0").unwrap();
+    assert_eq!(entries[1].ino, stat("dirs_a\0").unwrap().ino);
+    let entries = read_dir("/\0").unwrap();
+    assert_eq!(entries[0].ino, entries[1].ino);
+
+    assert_eq!(chdir("dirs_a/b\0"), 0);
+    assert_eq!(getcwd().unwrap(), "/dirs_a/b");
+    assert_eq!(read_file("file\0").unwrap(), b"nested");
+    assert_eq!(read_file("./c/../file\0").unwrap(), b"nested");
+    assert_eq!(errno(chdir("file\0")), Errno::ENOTDIR);
+    assert_eq!(errno(chdir("none\0")), Errno::ENOENT);
+    assert_eq!(getcwd().unwrap(), "/dirs_a/b");
+
+    // the child starts where the parent is, and moves by itself
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(getcwd().unwrap(), "/dirs_a/b");
+        assert_eq!(chdir("/\0"), 0);
+        assert_eq!(getcwd().unwrap(), "/");
+        exit(0);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(getcwd().unwrap(), "/dirs_a/b");
+
+    // across directories, but not into itself
+    assert_eq!(rename("file\0", "../moved\0"), 0);
+    assert_eq!(errno(rename("/dirs_a\0", "c/a\0")), Errno::EINVAL);
+    assert_eq!(rename("c\0", "/dirs_a/c\0"), 0);
+    assert_eq!(chdir("../../..\0"), 0);
+    assert_eq!(getcwd().unwrap(), "/");
+    assert_eq!(read_file("dirs_a/moved\0").unwrap(), b"nested");
+    assert!(stat("dirs_a/c\0").unwrap().is_dir());
+    assert_eq!(stat("dirs_a/b/c\0").unwrap_err(), Errno::ENOENT);
+
+    assert_eq!(errno(rmdir("dirs_a\0")), Errno::ENOTEMPTY);
+    assert_eq!(unlink("dirs_a/moved\0"), 0);
+
+    // the working directory moves with a rename, and its `..` with it, but getcwd still gives
+    // the path it was entered by
+    assert_eq!(chdir("dirs_a/c\0"), 0);
+    assert_eq!(rename("/dirs_a/c\0", "/dirs_a/b/d\0"), 0);
+    write_file("here\0", b"moved");
+    assert_eq!(read_file("/dirs_a/b/d/here\0").unwrap(), b"moved");
+    assert!(stat("../d\0").unwrap().is_dir());
+    let entries = read_dir(".\0").unwrap();
+    assert_eq!(entries[1].ino, stat("/dirs_a/b\0").unwrap().ino);
+    assert_eq!(getcwd().unwrap(), "/dirs_a/c");
+    assert_eq!(errno(rmdir(".\0")), Errno::EINVAL);
+    assert_eq!(errno(rmdir("..\0")), Errno::ENOTEMPTY);
+    assert_eq!(chdir("/\0"), 0);
+    assert_eq!(unlink("dirs_a/b/d/here\0"), 0);
+    assert_eq!(rmdir("dirs_a/b/d\0"), 0);
+
+    assert_eq!(rmdir("dirs_a/b\0"), 0);
+    assert_eq!(rmdir("dirs_a\0"), 0);
+    assert!(stat("dirs_a\0").is_err());
+    0
+}
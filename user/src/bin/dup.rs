@@ -0,0 +1,115 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    args, close, dup, dup2, exec, exit, fcntl, fork, open, pipe, read, waitpid, write, Errno,
+    OpenFlags, FD_CLOEXEC, F_DUPFD, F_DUPFD_CLOEXEC, F_GETFD, F_GETFL, F_SETFD, F_SETFL,
+};
+
+/*
+ * duplicated descriptors share the file and its offset, stdout can be redirected to a file,
+ * fcntl changes the descriptor flags and the file status flags, exec closes FD_CLOEXEC ones.
+ * expected: return 0
+*/
+
+const FILE: &str = "dup_file\0";
+
+// the contents of FILE
+fn read_file(buf: &mut [u8]) -> &[u8] {
+    let fd = open(FILE, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let len = read(fd as usize, buf);
+    assert!(len >= 0);
+    close(fd as usize);
+    &buf[..len as usize]
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut args = args();
+    args.next();
+    if args.next() == Some("exec") {
+        // run again by exec below, with 10 close-on-exec and 11 not
+        assert_eq!(fcntl(10, F_GETFD, 0), Errno::EBADF.as_ret());
+        assert_eq!(fcntl(11, F_GETFD, 0), 0);
+        return 0;
+    }
+    let mut buf = [0u8; 64];
+
+    // redirect stdout to a file and back
+    let fd = open(
+        FILE,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let stdout = dup(1);
+    assert!(stdout > 0);
+    assert_eq!(dup2(fd, 1), 1);
+    println!("redirected");
+    assert_eq!(dup2(stdout as usize, 1), 1);
+    close(stdout as usize);
+    // they share the offset
+    let copy = dup(fd);
+    assert!(copy > 0);
+    assert_eq!(write(copy as usize, b"ok"), 2);
+    close(copy as usize);
+    close(fd);
+    assert_eq!(read_file(&mut buf), b"redirected\nok");
+
+    assert_eq!(dup(100), Errno::EBADF.as_ret());
+    assert_eq!(dup2(100, 5), Errno::EBADF.as_ret());
+    assert_eq!(dup2(1, 1), 1);
+    assert_eq!(dup2(1, 4096), Errno::EBADF.as_ret());
+
+    // descriptor flags
+    let fd = fcntl(1, F_DUPFD, 10);
+    assert!(fd >= 10);
+    assert_eq!(fcntl(fd as usize, F_GETFD, 0), 0);
+    assert_eq!(fcntl(fd as usize, F_SETFD, FD_CLOEXEC), 0);
+    assert_eq!(fcntl(fd as usize, F_GETFD, 0), FD_CLOEXEC as isize);
+    close(fd as usize);
+    assert_eq!(fcntl(fd as usize, F_GETFD, 0), Errno::EBADF.as_ret());
+
+    // file status flags
+    let fd = open(FILE, OpenFlags::RDWR | OpenFlags::APPEND);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let flags = OpenFlags::from_bits_truncate(fcntl(fd, F_GETFL, 0) as u32);
+    assert!(flags.contains(OpenFlags::RDWR | OpenFlags::APPEND));
+    assert_eq!(write(fd, b"!"), 1);
+    assert_eq!(fcntl(fd, F_SETFL, 0), 0);
+    assert_eq!(fcntl(fd, F_GETFL, 0), OpenFlags::RDWR.bits() as isize);
+    close(fd);
+    assert_eq!(read_file(&mut buf), b"redirected\nok!");
+
+    // a pipe that does not block, the flag is shared with the duplicate
+    let mut fds = [0i32; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let (rx, tx) = (fds[0] as usize, fds[1] as usize);
+    let copy = dup(rx) as usize;
+    assert_eq!(fcntl(rx, F_SETFL, OpenFlags::NONBLOCK.bits() as usize), 0);
+    let flags = OpenFlags::from_bits_truncate(fcntl(copy, F_GETFL, 0) as u32);
+    assert!(flags.contains(OpenFlags::NONBLOCK));
+    assert_eq!(read(copy, &mut buf), Errno::EAGAIN.as_ret());
+    assert_eq!(write(tx, b"x"), 1);
+    assert_eq!(read(rx, &mut buf), 1);
+    close(copy);
+    close(rx);
+    close(tx);
+
+    // exec closes the close-on-exec descriptors only
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(fcntl(1, F_DUPFD_CLOEXEC, 10), 10);
+        assert_eq!(fcntl(1, F_DUPFD, 11), 11);
+        exec("dup\0", &["dup\0", "exec\0"], &[]);
+        exit(-1);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    exit_code
+}
@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::args;
+
+/*
+ * echo [-n] [word..]: print the words with a space between them, and a newline unless -n.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut words: Vec<&str> = args().skip(1).collect();
+    let newline = words.first() != Some(&"-n");
+    if !newline {
+        words.remove(0);
+    }
+    print!("{}", words.join(" "));
+    if newline {
+        println!();
+    }
+    0
+}
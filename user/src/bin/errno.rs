@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use core::arch::asm;
+use user_lib::{close, errno, open, wait, Errno, OpenFlags};
+
+/*
+ * failed syscalls return a negated errno, which is also kept for `errno()`.
+ * expected: return 0
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(close(100), Errno::EBADF.as_ret());
+    assert_eq!(errno(), Errno::EBADF);
+    assert_eq!(
+        Errno::from_ret(open("no_such_file\0", OpenFlags::RDONLY)),
+        Err(Errno::ENOENT)
+    );
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), Errno::ECHILD.as_ret());
+
+    // no syscall has this number
+    let ret: isize;
+    unsafe {
+        asm!("ecall", lateout("a0") ret, in("a7") 4000);
+    }
+    assert_eq!(ret, Errno::ENOSYS.as_ret());
+    0
+}
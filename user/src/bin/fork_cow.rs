@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{exit, fork, waitpid};
+
+/*
+ * Parent and child share frames after fork, a write from either side must not be seen by the other.
+ * expected: return 0
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut data = vec![1u8; 6000];
+    let pid = fork();
+    if pid == 0 {
+        for byte in data.iter_mut() {
+            *byte = 2;
+        }
+        assert!(data.iter().all(|&byte| byte == 2));
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert!(data.iter().all(|&byte| byte == 1));
+    data[0] = 3;
+    assert_eq!(data[0], 3);
+    println!("Test fork_cow OK!");
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sysinfo, Errno, SysInfo};
+
+/*
+ * free: show the total, used and free memory and swap space, in KiB.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut info = SysInfo::default();
+    if let Err(errno) = Errno::from_ret(sysinfo(&mut info)) {
+        eprintln!("free: {}", errno.name());
+        return 1;
+    }
+    let kib = |units: u64| units * info.mem_unit as u64 / 1024;
+    println!("{:<5} {:>10} {:>10} {:>10}", "", "total", "used", "free");
+    for (name, total, free) in [
+        ("Mem:", info.totalram, info.freeram),
+        ("Swap:", info.totalswap, info.freeswap),
+    ] {
+        println!(
+            "{:<5} {:>10} {:>10} {:>10}",
+            name,
+            kib(total),
+            kib(total - free),
+            kib(free)
+        );
+    }
+    0
+}
@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use user_lib::{
+    exit, fork, futex_wait, futex_wake, getpid, kill, sigaction,
+    signal::{SigAction, SIGUSR1},
+    thread, waitpid, yield_, Errno,
+};
+
+/*
+ * futex_wait sleeps until futex_wake, a signal or the exit of the process.
+ * expected: return 0
+*/
+
+static WORD: AtomicU32 = AtomicU32::new(0);
+
+extern "C" fn ignore(_signum: usize) {}
+
+#[no_mangle]
+fn main() -> i32 {
+    // the word has changed already
+    assert_eq!(futex_wait(&WORD, 1), Errno::EAGAIN.as_ret());
+    assert_eq!(futex_wake(&WORD, 1), 0);
+
+    // a waiter sleeps until it is woken
+    let waiter = thread::spawn(|| {
+        while WORD.load(Ordering::Acquire) == 0 {
+            assert_eq!(futex_wait(&WORD, 0), 0);
+        }
+        0
+    })
+    .unwrap();
+    // let it block, then there is one to wake
+    yield_();
+    WORD.store(1, Ordering::Release);
+    assert_eq!(futex_wake(&WORD, 1), 1);
+    assert_eq!(waiter.join(), 0);
+
+    // a fork while a thread waits does not lose it, though the next write copies the page
+    WORD.store(0, Ordering::Relaxed);
+    let waiter = thread::spawn(|| {
+        while WORD.load(Ordering::Acquire) == 0 {
+            assert_eq!(futex_wait(&WORD, 0), 0);
+        }
+        0
+    })
+    .unwrap();
+    yield_();
+    let child = fork();
+    if child == 0 {
+        exit(0);
+    }
+    WORD.store(1, Ordering::Release);
+    assert_eq!(futex_wake(&WORD, 1), 1);
+    assert_eq!(waiter.join(), 0);
+    let mut exit_code = -1;
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 0);
+
+    // a signal interrupts the wait
+    WORD.store(0, Ordering::Relaxed);
+    assert_eq!(sigaction(SIGUSR1, Some(&SigAction::new(ignore)), None), 0);
+    let waiter = thread::spawn(|| futex_wait(&WORD, 0) as i32).unwrap();
+    yield_();
+    assert_eq!(kill(getpid(), SIGUSR1), 0);
+    assert_eq!(waiter.join(), Errno::EINTR.as_ret() as i32);
+
+    // so does the exit of the process
+    let child = fork();
+    if child == 0 {
+        thread::spawn(|| futex_wait(&WORD, 0) as i32).unwrap();
+        yield_();
+        return 5;
+    }
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 5);
+    0
+}
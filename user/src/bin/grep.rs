@@ -0,0 +1,139 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{args, read_input, write_all};
+
+/*
+ * grep [-ivnc] pattern [file..]: print the lines of the files, or of stdin, that match pattern.
+ * In a pattern `^` and `$` anchor it to the start and the end of the line, `.` is any character,
+ * `x*` is any number of x and `\x` is x itself.
+ * -i ignores case, -v prints the lines that don't match, -n numbers them, -c counts them instead.
+ * Exits with 0 if a line was picked, 1 if none was, 2 on errors.
+*/
+
+fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    if let Some(pattern) = pattern.strip_prefix(b"^") {
+        return match_here(pattern, text);
+    }
+    (0..=text.len()).any(|i| match_here(pattern, &text[i..]))
+}
+
+// whether `pattern` matches the start of `text`
+fn match_here(pattern: &[u8], text: &[u8]) -> bool {
+    let (c, rest) = match pattern {
+        [] => return true,
+        [b'$'] => return text.is_empty(),
+        [b'\\', c, rest @ ..] => (Some(*c), rest),
+        [b'.', rest @ ..] => (None, rest),
+        [c, rest @ ..] => (Some(*c), rest),
+    };
+    let is_c = |b: u8| c.is_none_or(|c| c == b);
+    if let Some(rest) = rest.strip_prefix(b"*") {
+        // as few as it takes
+        let mut i = 0;
+        loop {
+            if match_here(rest, &text[i..]) {
+                return true;
+            }
+            if i == text.len() || !is_c(text[i]) {
+                return false;
+            }
+            i += 1;
+        }
+    }
+    !text.is_empty() && is_c(text[0]) && match_here(rest, &text[1..])
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let (mut ignore_case, mut invert, mut number, mut count) = (false, false, false, false);
+    let mut args = args().skip(1).peekable();
+    while let Some(flags) = args.peek().and_then(|arg| arg.strip_prefix('-')) {
+        if flags.is_empty() {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'i' => ignore_case = true,
+                'v' => invert = true,
+                'n' => number = true,
+                'c' => count = true,
+                _ => {
+                    eprintln!("grep: unknown option -{}", flag);
+                    return 2;
+                }
+            }
+        }
+        args.next();
+    }
+    let Some(pattern) = args.next() else {
+        eprintln!("usage: grep [-ivnc] pattern [file..]");
+        return 2;
+    };
+    let pattern = match ignore_case {
+        true => pattern.to_ascii_lowercase().into_bytes(),
+        false => pattern.as_bytes().to_vec(),
+    };
+    let mut paths: Vec<&str> = args.collect();
+    if paths.is_empty() {
+        paths.push("-");
+    }
+    let mut status = 1;
+    let mut errors = false;
+    for path in paths.iter() {
+        let data = match read_input(path) {
+            Ok(data) => data,
+            Err(errno) => {
+                eprintln!("grep: {}: {}", path, errno.name());
+                errors = true;
+                continue;
+            }
+        };
+        // an empty file has no lines, rather than an empty one
+        let lines = match data.strip_suffix(b"\n").unwrap_or(&data) {
+            [] if data.is_empty() => Vec::new(),
+            body => body.split(|&b| b == b'\n').collect(),
+        };
+        let mut picked = 0;
+        for (i, line) in lines.into_iter().enumerate() {
+            let found = match ignore_case {
+                true => matches(&pattern, &line.to_ascii_lowercase()),
+                false => matches(&pattern, line),
+            };
+            if found == invert {
+                continue;
+            }
+            picked += 1;
+            if count {
+                continue;
+            }
+            if paths.len() > 1 {
+                print!("{}:", path);
+            }
+            if number {
+                print!("{}:", i + 1);
+            }
+            write_all(1, line).ok();
+            println!();
+        }
+        if count {
+            match paths.len() {
+                1 => println!("{}", picked),
+                _ => println!("{}:{}", path, picked),
+            }
+        }
+        if picked > 0 {
+            status = 0;
+        }
+    }
+    if errors {
+        2
+    } else {
+        status
+    }
+}
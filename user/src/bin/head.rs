@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{args, read_input, write_all};
+
+/*
+ * head [-n count] [file..]: print the first 10, or count, lines of the files, or of stdin.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut args = args().skip(1).peekable();
+    let mut count = 10;
+    if args.peek() == Some(&"-n") {
+        args.next();
+        match args.next().and_then(|n| n.parse().ok()) {
+            Some(n) => count = n,
+            None => {
+                eprintln!("usage: head [-n count] [file..]");
+                return 2;
+            }
+        }
+    }
+    let mut paths: Vec<&str> = args.collect();
+    if paths.is_empty() {
+        paths.push("-");
+    }
+    let mut status = 0;
+    for (i, path) in paths.iter().enumerate() {
+        let data = match read_input(path) {
+            Ok(data) => data,
+            Err(errno) => {
+                eprintln!("head: {}: {}", path, errno.name());
+                status = 1;
+                continue;
+            }
+        };
+        if paths.len() > 1 {
+            if i > 0 {
+                println!();
+            }
+            println!("==> {} <==", path);
+        }
+        // the end of the last line to print
+        let end = data
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == b'\n')
+            .nth(count.max(1) - 1)
+            .map_or(data.len(), |(i, _)| i + 1);
+        if count > 0 {
+            write_all(1, &data[..end]).ok();
+        }
+    }
+    status
+}
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::{brk, sbrk};
+
+/*
+ * allocate far more than the initial 16KiB heap, the allocator grows the heap through brk.
+ * expected: return 0
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let len: usize = 1024 * 1024;
+    let mut v: Vec<u8> = Vec::with_capacity(len);
+    for i in 0..len {
+        v.push(i as u8);
+    }
+    for (i, item) in v.iter().enumerate() {
+        assert_eq!(*item, i as u8);
+    }
+    drop(v);
+
+    let old = sbrk(0);
+    assert!(old > 0);
+    assert_eq!(sbrk(4096), old);
+    let ptr = old as *mut u8;
+    unsafe {
+        *ptr = 42;
+        assert_eq!(*ptr, 42);
+    }
+    assert_eq!(brk(old as usize), 0);
+    assert_eq!(sbrk(0), old);
+    0
+}
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+use user_lib::{args, read_input};
+
+/*
+ * hexdump [-C] [file..]: show the bytes of the files, or of stdin, 16 to a line in hex and as
+ * text, like `hexdump -C`. A `*` stands for lines the same as the one before.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut paths: Vec<&str> = args().skip(1).filter(|&arg| arg != "-C").collect();
+    if paths.is_empty() {
+        paths.push("-");
+    }
+    let mut data = Vec::new();
+    let mut status = 0;
+    for path in paths {
+        match read_input(path) {
+            Ok(bytes) => data.extend_from_slice(&bytes),
+            Err(errno) => {
+                eprintln!("hexdump: {}: {}", path, errno.name());
+                status = 1;
+            }
+        }
+    }
+    let mut previous: Option<&[u8]> = None;
+    let mut squeezed = false;
+    for (i, chunk) in data.chunks(16).enumerate() {
+        if previous == Some(chunk) && chunk.len() == 16 {
+            if !squeezed {
+                println!("*");
+                squeezed = true;
+            }
+            continue;
+        }
+        previous = Some(chunk);
+        squeezed = false;
+        let mut line = String::new();
+        write!(line, "{:08x} ", i * 16).unwrap();
+        for j in 0..16 {
+            if j % 8 == 0 {
+                line.push(' ');
+            }
+            match chunk.get(j) {
+                Some(byte) => write!(line, "{:02x} ", byte).unwrap(),
+                None => line.push_str("   "),
+            }
+        }
+        line.push_str(" |");
+        for &byte in chunk {
+            line.push(match byte {
+                0x20..=0x7e => byte as char,
+                _ => '.',
+            });
+        }
+        println!("{}|", line);
+    }
+    if !data.is_empty() {
+        println!("{:08x}", data.len());
+    }
+    status
+}
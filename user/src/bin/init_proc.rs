@@ -10,7 +10,7 @@ extern crate user_lib;
 unsafe fn main() -> i32 {
     let pid = fork();
     if pid == 0 {
-        exec("shell\0");
+        exec("shell\0", &["shell\0"], &[]);
     } else {
         loop {
             let mut exit_code = 0;
@@ -0,0 +1,96 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{args, kill, signal::*, Errno};
+
+/*
+ * kill [-signal | -s signal] pid..: send the signal, SIGTERM if none is given, to the processes.
+ * A signal is a number or a name with or without SIG. kill -l lists the names.
+*/
+
+const SIGNALS: &[(&str, usize)] = &[
+    ("HUP", SIGHUP),
+    ("INT", SIGINT),
+    ("QUIT", SIGQUIT),
+    ("ILL", SIGILL),
+    ("TRAP", SIGTRAP),
+    ("ABRT", SIGABRT),
+    ("BUS", SIGBUS),
+    ("FPE", SIGFPE),
+    ("KILL", SIGKILL),
+    ("USR1", SIGUSR1),
+    ("SEGV", SIGSEGV),
+    ("USR2", SIGUSR2),
+    ("PIPE", SIGPIPE),
+    ("ALRM", SIGALRM),
+    ("TERM", SIGTERM),
+    ("CHLD", SIGCHLD),
+    ("CONT", SIGCONT),
+    ("STOP", SIGSTOP),
+];
+
+fn parse_signal(s: &str) -> Option<usize> {
+    if let Ok(signum) = s.parse() {
+        return Some(signum);
+    }
+    let name = s.strip_prefix("SIG").unwrap_or(s);
+    SIGNALS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, signum)| signum)
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut args = args().skip(1).peekable();
+    let mut signum = SIGTERM;
+    match args.peek().copied() {
+        Some("-l") => {
+            for (name, signum) in SIGNALS {
+                println!("{:>2} {}", signum, name);
+            }
+            return 0;
+        }
+        Some("-s") => {
+            args.next();
+            signum = match args.next().and_then(parse_signal) {
+                Some(signum) => signum,
+                None => {
+                    eprintln!("kill: unknown signal");
+                    return 2;
+                }
+            };
+        }
+        Some(arg) if arg.starts_with('-') => {
+            args.next();
+            signum = match parse_signal(&arg[1..]) {
+                Some(signum) => signum,
+                None => {
+                    eprintln!("kill: {}: unknown signal", &arg[1..]);
+                    return 2;
+                }
+            };
+        }
+        _ => {}
+    }
+    if args.peek().is_none() {
+        eprintln!("usage: kill [-signal | -s signal] pid..");
+        return 2;
+    }
+    let mut status = 0;
+    for arg in args {
+        let Ok(pid) = arg.parse::<isize>() else {
+            eprintln!("kill: {}: not a pid", arg);
+            status = 1;
+            continue;
+        };
+        if let Err(errno) = Errno::from_ret(kill(pid, signum)) {
+            eprintln!("kill: {}: {}", pid, errno.name());
+            status = 1;
+        }
+    }
+    status
+}
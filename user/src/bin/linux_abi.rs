@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use core::arch::asm;
+use user_lib::{exit, fork, getpid, open, sleep, waitpid, Errno, OpenFlags};
+
+/*
+ * the syscalls musl needs to start and print behave like Linux.
+ * expected: return 0
+*/
+
+const IOCTL: usize = 29;
+const WRITEV: usize = 66;
+const SET_TID_ADDRESS: usize = 96;
+const CLOCK_GETTIME: usize = 113;
+const UNAME: usize = 160;
+const WAIT4: usize = 260;
+
+const TIOCGWINSZ: usize = 0x5413;
+const CLOCK_MONOTONIC: usize = 1;
+const WNOHANG: usize = 1;
+
+fn syscall(id: usize, args: [usize; 4]) -> isize {
+    let ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") args[0] => ret,
+            in("a1") args[1],
+            in("a2") args[2],
+            in("a3") args[3],
+            in("a7") id,
+        );
+    }
+    ret
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut utsname = [[0u8; 65]; 6];
+    assert_eq!(syscall(UNAME, [utsname.as_mut_ptr() as usize, 0, 0, 0]), 0);
+    assert!(utsname[0].starts_with(b"Linux\0"));
+    assert!(utsname[4].starts_with(b"riscv64\0"));
+
+    let first = b"hello, ";
+    let second = b"writev\n";
+    let iov = [
+        [first.as_ptr() as usize, first.len()],
+        [second.as_ptr() as usize, second.len()],
+    ];
+    assert_eq!(syscall(WRITEV, [1, iov.as_ptr() as usize, 2, 0]), 14);
+
+    // the console is a terminal, a file is not
+    let mut winsize = [0u16; 4];
+    assert_eq!(
+        syscall(IOCTL, [1, TIOCGWINSZ, winsize.as_mut_ptr() as usize, 0]),
+        0
+    );
+    assert!(winsize[0] > 0 && winsize[1] > 0);
+    let fd = open("linux_abi\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(
+        syscall(
+            IOCTL,
+            [fd as usize, TIOCGWINSZ, winsize.as_mut_ptr() as usize, 0]
+        ),
+        Errno::ENOTTY.as_ret()
+    );
+
+    let mut tid = 0i32;
+    assert_eq!(
+        syscall(SET_TID_ADDRESS, [&mut tid as *mut i32 as usize, 0, 0, 0]),
+        getpid()
+    );
+
+    let mut timespec = [0usize; 2];
+    assert_eq!(
+        syscall(
+            CLOCK_GETTIME,
+            [CLOCK_MONOTONIC, timespec.as_mut_ptr() as usize, 0, 0]
+        ),
+        0
+    );
+    assert!(timespec[1] < 1_000_000_000);
+
+    let pid = fork();
+    if pid == 0 {
+        sleep(100);
+        exit(3);
+    }
+    // the child is still sleeping
+    let mut wstatus = 0i32;
+    let status_ptr = &mut wstatus as *mut i32 as usize;
+    assert_eq!(syscall(WAIT4, [pid as usize, status_ptr, WNOHANG, 0]), 0);
+    assert_eq!(syscall(WAIT4, [pid as usize, status_ptr, 0, 0]), pid);
+    // WIFEXITED and WEXITSTATUS
+    assert_eq!(wstatus & 0x7f, 0);
+    assert_eq!((wstatus >> 8) & 0xff, 3);
+
+    let mut exit_code = 0;
+    let pid = fork();
+    if pid == 0 {
+        exit(-2);
+    }
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    0
+}
@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, string::String, vec::Vec};
+use user_lib::{
+    args,
+    dir::read_dir,
+    stat::{stat, Stat},
+    Errno,
+};
+
+/*
+ * ls [-al] [path..]: list the directories, the current one if there is none, one name to a line.
+ * A file is listed by itself. -a shows the names starting with `.`, -l the type, permissions,
+ * links and size as well.
+*/
+
+fn show(name: &str, stat: Option<&Stat>) {
+    match stat {
+        Some(stat) => println!(
+            "{} {:>2} {:>8} {}",
+            stat.mode_string(),
+            stat.nlink,
+            stat.size,
+            name
+        ),
+        None => println!("{}", name),
+    }
+}
+
+// list the directory at `path`, which is not nul-terminated
+fn list_dir(path: &str, all: bool, long: bool) -> Result<(), Errno> {
+    let mut entries = read_dir(&format!("{}\0", path))?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    for entry in entries {
+        if entry.name.starts_with('.') && !all {
+            continue;
+        }
+        // it may be gone already
+        let info = match long {
+            true => stat(&format!("{}/{}\0", path, entry.name)).ok(),
+            false => None,
+        };
+        show(&entry.name, info.as_ref());
+    }
+    Ok(())
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let (mut all, mut long) = (false, false);
+    let mut paths: Vec<String> = Vec::new();
+    for arg in args().skip(1) {
+        match arg.strip_prefix('-') {
+            Some(flags) if !flags.is_empty() => {
+                for flag in flags.chars() {
+                    match flag {
+                        'a' => all = true,
+                        'l' => long = true,
+                        _ => {
+                            eprintln!("ls: unknown option -{}", flag);
+                            return 2;
+                        }
+                    }
+                }
+            }
+            _ => paths.push(arg.into()),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(".".into());
+    }
+    let mut status = 0;
+    for (i, path) in paths.iter().enumerate() {
+        let ret = stat(&format!("{}\0", path)).and_then(|info| {
+            if !info.is_dir() {
+                show(path, long.then_some(&info));
+                return Ok(());
+            }
+            if paths.len() > 1 {
+                if i > 0 {
+                    println!();
+                }
+                println!("{}:", path);
+            }
+            list_dir(path, all, long)
+        });
+        if let Err(errno) = ret {
+            eprintln!("ls: {}: {}", path, errno.name());
+            status = 1;
+        }
+    }
+    status
+}
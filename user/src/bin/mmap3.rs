@@ -4,10 +4,10 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::mmap;
+use user_lib::{mmap, Errno};
 
 /*
-理想结果：对于错误的 mmap 返回 -1，最终输出 Test 04_4 test OK!
+理想结果：对于错误的 mmap 返回 -EINVAL，最终输出 Test 04_4 test OK!
 */
 
 #[no_mangle]
@@ -16,9 +16,9 @@ fn main() -> i32 {
     let len: usize = 4096;
     let prot: usize = 3;
     assert_eq!(0, mmap(start, len, prot));
-    assert_eq!(mmap(start - len, len + 1, prot), -1);
-    assert_eq!(mmap(start + len + 1, len, prot), -1);
-    assert_eq!(mmap(start + len, len, 0), -1);
-    assert_eq!(mmap(start + len, len, prot | 8), -1);
+    assert_eq!(mmap(start - len, len + 1, prot), Errno::EINVAL.as_ret());
+    assert_eq!(mmap(start + len + 1, len, prot), Errno::EINVAL.as_ret());
+    assert_eq!(mmap(start + len, len, 0), Errno::EINVAL.as_ret());
+    assert_eq!(mmap(start + len, len, prot | 8), Errno::EINVAL.as_ret());
     0
 }
@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+ * mmap a region larger than physical memory, only the touched pages take up frames.
+ * expected: return 0
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 256 * 1024 * 1024;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    for addr in [start, start + len / 2, start + len - 1] {
+        let ptr = addr as *mut u8;
+        unsafe {
+            assert_eq!(*ptr, 0);
+            *ptr = addr as u8;
+            assert_eq!(*ptr, addr as u8);
+        }
+    }
+    assert_eq!(munmap(start, len), 0);
+    0
+}
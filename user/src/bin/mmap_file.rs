@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, mmap_file, munmap, open, read, write, MmapFlags, OpenFlags};
+
+/*
+ * Writes through a MAP_SHARED mapping reach the file, writes through a MAP_PRIVATE one don't.
+ * expected: return 0
+*/
+
+const CONTENT: &str = "Hello, mmap!";
+
+fn check_file(fname: &str, expected: &[u8]) {
+    let fd = open(fname, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buffer = [0u8; 32];
+    let len = read(fd as usize, &mut buffer) as usize;
+    close(fd as usize);
+    assert_eq!(&buffer[..len], expected);
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let fname = "mmap_file\0";
+    let fd = open(fname, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, CONTENT.as_bytes()) as usize, CONTENT.len());
+
+    let prot: usize = 3;
+    let start = mmap_file(0, CONTENT.len(), prot, MmapFlags::PRIVATE, fd, 0);
+    assert!(start > 0);
+    let data = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, CONTENT.len()) };
+    assert_eq!(data, CONTENT.as_bytes());
+    data[0] = b'J';
+    assert_eq!(munmap(start as usize, CONTENT.len()), 0);
+    check_file(fname, CONTENT.as_bytes());
+
+    let start = mmap_file(0, CONTENT.len(), prot, MmapFlags::SHARED, fd, 0);
+    assert!(start > 0);
+    let data = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, CONTENT.len()) };
+    data[0] = b'J';
+    assert_eq!(munmap(start as usize, CONTENT.len()), 0);
+    close(fd);
+    check_file(fname, b"Jello, mmap!");
+
+    println!("Test mmap_file OK!");
+    0
+}
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, vec::Vec};
+use user_lib::{args, rename, stat::stat, Errno};
+
+/*
+ * mv source target: rename source to target, or move it into target if that is a directory.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let args: Vec<&str> = args().skip(1).collect();
+    let [from, to] = args[..] else {
+        eprintln!("usage: mv source target");
+        return 2;
+    };
+    let to = match stat(to) {
+        Ok(stat) if stat.is_dir() => format!("{}/{}\0", to, from.rsplit('/').next().unwrap()),
+        _ => format!("{}\0", to),
+    };
+    match Errno::from_ret(rename(from, &to)) {
+        Ok(_) => 0,
+        Err(errno) => {
+            eprintln!(
+                "mv: {} to {}: {}",
+                from,
+                to.trim_end_matches('\0'),
+                errno.name()
+            );
+            1
+        }
+    }
+}
@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::{
+    close, exit, fork, getpid, kill, pipe, read, sigaction,
+    signal::{SigAction, SIGPIPE, SIGUSR1, SIG_IGN},
+    waitpid, write, Errno,
+};
+
+/*
+ * processes talk through a pipe, a reader waits for data and sees the end when the writers are
+ * gone, a writer waits for room and gets EPIPE and SIGPIPE when the readers are gone.
+ * expected: return 0
+*/
+
+// more than a pipe holds, so the writer has to wait for the reader
+const LEN: usize = 3 * 4096 + 100;
+
+extern "C" fn ignore(_signum: usize) {}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut fds = [0i32; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let (rx, tx) = (fds[0] as usize, fds[1] as usize);
+    let mut buf = [0u8; 16];
+    assert_eq!(write(tx, b"hello"), 5);
+    assert_eq!(read(rx, &mut buf), 5);
+    assert_eq!(&buf[..5], b"hello");
+    // the ends only go one way
+    assert_eq!(read(tx, &mut buf), Errno::EBADF.as_ret());
+    assert_eq!(write(rx, b"x"), Errno::EBADF.as_ret());
+
+    // from a child to its parent
+    let child = fork();
+    if child == 0 {
+        close(rx);
+        let data: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+        assert_eq!(write(tx, &data), LEN as isize);
+        exit(0);
+    }
+    close(tx);
+    let mut total = 0;
+    loop {
+        let n = read(rx, &mut buf);
+        assert!(n >= 0);
+        if n == 0 {
+            break;
+        }
+        for &byte in &buf[..n as usize] {
+            assert_eq!(byte, total as u8);
+            total += 1;
+        }
+    }
+    assert_eq!(total, LEN);
+    let mut exit_code = -1;
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 0);
+    close(rx);
+
+    // with the readers gone, SIGPIPE kills the writer, or EPIPE if it is ignored
+    assert_eq!(pipe(&mut fds), 0);
+    let (rx, tx) = (fds[0] as usize, fds[1] as usize);
+    close(rx);
+    let child = fork();
+    if child == 0 {
+        write(tx, b"x");
+        exit(0);
+    }
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 128 + SIGPIPE as i32);
+    let ignore_action = SigAction {
+        handler: SIG_IGN,
+        ..Default::default()
+    };
+    assert_eq!(sigaction(SIGPIPE, Some(&ignore_action), None), 0);
+    assert_eq!(write(tx, b"x"), Errno::EPIPE.as_ret());
+    close(tx);
+
+    // a signal interrupts a reader waiting for data
+    assert_eq!(pipe(&mut fds), 0);
+    let (rx, tx) = (fds[0] as usize, fds[1] as usize);
+    assert_eq!(sigaction(SIGUSR1, Some(&SigAction::new(ignore)), None), 0);
+    let pid = getpid();
+    let child = fork();
+    if child == 0 {
+        kill(pid, SIGUSR1);
+        exit(0);
+    }
+    assert_eq!(read(rx, &mut buf), Errno::EINTR.as_ret());
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    close(rx);
+    close(tx);
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{format, vec::Vec};
+use user_lib::{dir::read_dir, read_file};
+
+/*
+ * ps: list the processes with their parent, state, number of threads and name, from /proc.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let entries = match read_dir("/proc\0") {
+        Ok(entries) => entries,
+        Err(errno) => {
+            eprintln!("ps: /proc: {}", errno.name());
+            return 1;
+        }
+    };
+    let mut pids: Vec<usize> = entries
+        .iter()
+        .filter_map(|entry| entry.name.parse().ok())
+        .collect();
+    pids.sort();
+    println!("{:>5} {:>5} S THR CMD", "PID", "PPID");
+    for pid in pids {
+        // it may have exited since
+        let Ok(stat) = read_file(&format!("/proc/{}/stat\0", pid)) else {
+            continue;
+        };
+        let stat = core::str::from_utf8(&stat).unwrap_or("");
+        // pid (name) state ppid ..., the name may hold spaces and parentheses
+        let (Some(start), Some(end)) = (stat.find('('), stat.rfind(')')) else {
+            continue;
+        };
+        let name = &stat[start + 1..end];
+        let fields: Vec<&str> = stat[end + 1..].split_whitespace().collect();
+        // state is the 3rd field, ppid the 4th and num_threads the 20th
+        let (Some(state), Some(ppid), Some(threads)) =
+            (fields.first(), fields.get(1), fields.get(17))
+        else {
+            continue;
+        };
+        println!("{:>5} {:>5} {} {:>3} {}", pid, ppid, state, threads, name);
+    }
+    0
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{args, unlink, Errno};
+
+/*
+ * rm [-f] file..: remove the files. With -f, files that do not exist are no error.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut force = false;
+    let mut status = 0;
+    for path in args().skip(1) {
+        if path == "-f" {
+            force = true;
+            continue;
+        }
+        match Errno::from_ret(unlink(path)) {
+            Ok(_) => {}
+            Err(Errno::ENOENT) if force => {}
+            Err(errno) => {
+                eprintln!("rm: {}: {}", path, errno.name());
+                status = 1;
+            }
+        }
+    }
+    status
+}
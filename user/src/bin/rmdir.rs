@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{args, rmdir, Errno};
+
+/*
+ * rmdir dir..: remove the directories, which must be empty.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut status = 0;
+    for path in args().skip(1) {
+        if let Err(errno) = Errno::from_ret(rmdir(path)) {
+            eprintln!("rmdir: {}: {}", path, errno.name());
+            status = 1;
+        }
+    }
+    status
+}
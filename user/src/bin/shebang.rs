@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{args, exec, exit, fork, waitpid, write_file, Errno};
+
+/*
+ * exec of a script starting with `#!` runs its interpreter, which is this
+ * program, with the script path in argv.
+ * expected: return 0
+*/
+
+// exec `path` in a child and return its exit code
+fn run(path: &str, argv: &[&str]) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exec(path, argv, &[]);
+        exit(-1);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut args = args().skip(1);
+    match args.next() {
+        None => {}
+        // run as the interpreter of shebang_script
+        Some("-x  y") => {
+            assert_eq!(args.next(), Some("shebang_script"));
+            return match args.next() {
+                Some("hello") => 3,
+                // shebang_script is the interpreter of shebang_nested
+                Some("shebang_nested") => {
+                    assert_eq!(args.next(), Some("world"));
+                    4
+                }
+                _ => -1,
+            };
+        }
+        Some(_) => return -1,
+    }
+
+    // the argument is everything after the interpreter
+    write_file("shebang_script\0", b"#! shebang -x  y \necho unreachable\n");
+    assert_eq!(run("shebang_script\0", &["script\0", "hello\0"]), 3);
+    // a script as the interpreter, without a newline
+    write_file("shebang_nested\0", b"#!shebang_script");
+    assert_eq!(run("shebang_nested\0", &["nested\0", "world\0"]), 4);
+
+    write_file("shebang_loop\0", b"#!shebang_loop\n");
+    assert_eq!(exec("shebang_loop\0", &[], &[]), Errno::ELOOP.as_ret());
+    write_file("shebang_empty\0", b"#!\n");
+    assert_eq!(exec("shebang_empty\0", &[], &[]), Errno::ENOEXEC.as_ret());
+    write_file("shebang_missing\0", b"#!no_such_file\n");
+    assert_eq!(exec("shebang_missing\0", &[], &[]), Errno::ENOENT.as_ret());
+    0
+}
@@ -1,59 +1,1026 @@
 #![no_std]
 #![no_main]
 
-use user_lib::{console::getchar, exec, fork, waitpid};
 #[macro_use]
 extern crate user_lib;
 extern crate alloc;
 
-const LF: u8 = 0x0au8;
-const CR: u8 = 0x0du8;
-const DL: u8 = 0x7fu8;
-const BS: u8 = 0x08u8;
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{
+    args, chdir,
+    dir::read_dir,
+    envs, exec, exit, fork, get_time, getcwd, read, read_file, sigaction,
+    signal::{SigAction, SIGINT},
+    waitpid, write, Errno,
+};
 
-use alloc::string::String;
+/*
+ * A shell: a line editor with history and tab completion, variables, if/while/for and scripts.
+ * `shell` reads commands from the terminal, `shell -c cmd` runs cmd, `shell file args..` runs
+ * the script in file.
+*/
 
-#[no_mangle]
-unsafe fn main() -> i32 {
-    println!("Shell");
-    let mut line = String::new();
-    print!("$ ");
-    loop {
-        let ch = getchar();
-        match ch {
-            LF | CR => {
+const NUL: u8 = 0x00;
+const CTRL_A: u8 = 0x01;
+const CTRL_D: u8 = 0x04;
+const CTRL_E: u8 = 0x05;
+const BS: u8 = 0x08;
+const TAB: u8 = 0x09;
+const LF: u8 = 0x0a;
+const CR: u8 = 0x0d;
+const CTRL_U: u8 = 0x15;
+const ESC: u8 = 0x1b;
+const DL: u8 = 0x7f;
+
+const STDIN: usize = 0;
+const STDERR: usize = 2;
+
+const BUILTINS: &[&str] = &[
+    ".", "cd", "echo", "exit", "export", "false", "pwd", "source", "time", "true",
+];
+
+// set by Ctrl-C, a running loop stops
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Ctrl-C interrupts the program running in the foreground, not the shell
+extern "C" fn on_interrupt(_signum: usize) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+// errors go to stderr, so that they don't mix with the output of a script
+macro_rules! eprintln {
+    ($($arg: tt)*) => {
+        write(STDERR, format!("shell: {}\n", format_args!($($arg)*)).as_bytes())
+    };
+}
+
+enum Input {
+    Line(String),
+    // Ctrl-C, the line is dropped
+    Interrupted,
+    // Ctrl-D on an empty line, or the end of stdin
+    Eof,
+}
+
+/// Reads lines from the terminal, which is in raw mode, so it echoes them itself
+struct Editor {
+    history: Vec<String>,
+}
+
+impl Editor {
+    // edit a line with the keys in `input`, which come from stdin
+    fn read_line(&mut self, prompt: &str, input: &mut impl Iterator<Item = u8>) -> Input {
+        let mut line: Vec<u8> = Vec::new();
+        let mut cursor = 0;
+        // where in the history the line is, `history.len()` for the new line
+        let mut pos = self.history.len();
+        // the new line while an older one is shown
+        let mut new_line = Vec::new();
+        print!("{}", prompt);
+        loop {
+            let Some(byte) = input.next() else {
+                if line.is_empty() {
+                    return Input::Eof;
+                }
                 println!();
-                if !line.is_empty() {
-                    line.push('\0');
-                    let pid = fork();
-                    if pid == 0 {
-                        if exec(line.as_str()) == -1 {
-                            println!("Error when executing");
-                            return -1;
+                break;
+            };
+            match byte {
+                // the read was interrupted by Ctrl-C
+                NUL => {
+                    println!("^C");
+                    return Input::Interrupted;
+                }
+                LF | CR => {
+                    println!();
+                    break;
+                }
+                CTRL_D if line.is_empty() => {
+                    println!();
+                    return Input::Eof;
+                }
+                CTRL_D => {
+                    if cursor < line.len() {
+                        line.remove(cursor);
+                    }
+                }
+                BS | DL => {
+                    if cursor > 0 {
+                        cursor -= 1;
+                        line.remove(cursor);
+                    }
+                }
+                CTRL_A => cursor = 0,
+                CTRL_E => cursor = line.len(),
+                CTRL_U => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                TAB => {
+                    if let Some((start, names)) = complete(&line[..cursor]) {
+                        let insert = common_prefix(&names)[cursor - start..].to_vec();
+                        let single = names.len() == 1;
+                        if insert.is_empty() && !single {
+                            // nothing more to fill in, show what the word may be
+                            println!();
+                            println!("{}", names.join("  "));
                         }
-                        unreachable!()
+                        cursor = insert_at(&mut line, cursor, &insert);
+                        if single {
+                            cursor = insert_at(&mut line, cursor, b" ");
+                        }
+                    }
+                }
+                ESC => match read_escape(input) {
+                    Key::Up if pos > 0 => {
+                        if pos == self.history.len() {
+                            new_line = line;
+                        }
+                        pos -= 1;
+                        line = self.history[pos].as_bytes().to_vec();
+                        cursor = line.len();
+                    }
+                    Key::Down if pos < self.history.len() => {
+                        pos += 1;
+                        line = match self.history.get(pos) {
+                            Some(old) => old.as_bytes().to_vec(),
+                            None => core::mem::take(&mut new_line),
+                        };
+                        cursor = line.len();
+                    }
+                    Key::Left => cursor = cursor.saturating_sub(1),
+                    Key::Right => cursor = (cursor + 1).min(line.len()),
+                    Key::Home => cursor = 0,
+                    Key::End => cursor = line.len(),
+                    Key::Delete if cursor < line.len() => {
+                        line.remove(cursor);
+                    }
+                    _ => {}
+                },
+                // anything else not printable is ignored, and so are multibyte characters
+                0x20..=0x7e => {
+                    line.insert(cursor, byte);
+                    cursor += 1;
+                    if cursor == line.len() {
+                        // typed at the end, no need to write the line again
+                        print!("{}", byte as char);
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            redraw(prompt, &line, cursor);
+        }
+        let line = String::from_utf8(line).unwrap();
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        Input::Line(line)
+    }
+}
+
+// None at the end of stdin, NUL if the read was interrupted
+fn read_byte() -> Option<u8> {
+    let mut byte = [0u8; 1];
+    match read(STDIN, &mut byte) {
+        0 => None,
+        ret if ret == Errno::EINTR.as_ret() => Some(NUL),
+        ret if ret < 0 => None,
+        _ => Some(byte[0]),
+    }
+}
+
+enum Key {
+    Up,
+    Down,
+    Right,
+    Left,
+    Home,
+    End,
+    Delete,
+    Unknown,
+}
+
+// the rest of an escape sequence after ESC: `[` or `O`, maybe a number, and the final byte
+fn read_escape(input: &mut impl Iterator<Item = u8>) -> Key {
+    let Some(b'[' | b'O') = input.next() else {
+        return Key::Unknown;
+    };
+    let mut param = 0;
+    loop {
+        match input.next() {
+            Some(digit @ b'0'..=b'9') => param = param * 10 + (digit - b'0') as usize,
+            Some(b'A') => return Key::Up,
+            Some(b'B') => return Key::Down,
+            Some(b'C') => return Key::Right,
+            Some(b'D') => return Key::Left,
+            Some(b'H') => return Key::Home,
+            Some(b'F') => return Key::End,
+            Some(b'~') => {
+                return match param {
+                    1 | 7 => Key::Home,
+                    4 | 8 => Key::End,
+                    3 => Key::Delete,
+                    _ => Key::Unknown,
+                }
+            }
+            _ => return Key::Unknown,
+        }
+    }
+}
+
+// write the line again and put the cursor back where it is in the line
+fn redraw(prompt: &str, line: &[u8], cursor: usize) {
+    print!("\r{}{}\x1b[K", prompt, core::str::from_utf8(line).unwrap());
+    if cursor < line.len() {
+        print!("\x1b[{}D", line.len() - cursor);
+    }
+}
+
+// insert `bytes` into `line` at `cursor`, return the cursor after them
+fn insert_at(line: &mut Vec<u8>, cursor: usize, bytes: &[u8]) -> usize {
+    line.splice(cursor..cursor, bytes.iter().copied());
+    cursor + bytes.len()
+}
+
+// The names the word before the cursor may be and where it starts: commands for the first word
+// of a command, files in the current directory for the others.
+fn complete(before: &[u8]) -> Option<(usize, Vec<String>)> {
+    let start = before
+        .iter()
+        .rposition(|&b| b == b' ' || b == b';')
+        .map_or(0, |i| i + 1);
+    let prefix = core::str::from_utf8(&before[start..]).ok()?;
+    let first = before[..start]
+        .iter()
+        .rev()
+        .find(|&&b| b != b' ')
+        .is_none_or(|&b| b == b';');
+    // commands are run from the root directory
+    let dir = if first { "/\0" } else { ".\0" };
+    let mut names: Vec<String> = read_dir(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.name != "." && entry.name != ".." && !(first && entry.is_dir()))
+        .map(|entry| entry.name)
+        .collect();
+    if first {
+        names.extend(BUILTINS.iter().map(|name| name.to_string()));
+    }
+    names.retain(|name| name.starts_with(prefix));
+    names.sort();
+    names.dedup();
+    if names.is_empty() {
+        return None;
+    }
+    Some((start, names))
+}
+
+fn common_prefix(names: &[String]) -> &[u8] {
+    let first = names[0].as_bytes();
+    let len = names.iter().fold(first.len(), |len, name| {
+        first[..len]
+            .iter()
+            .zip(name.as_bytes())
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    &first[..len]
+}
+
+/// A piece of a word before expansion
+#[derive(Debug)]
+enum Part {
+    // text as it is, `quoted` if it was in quotes or escaped
+    Lit { text: String, quoted: bool },
+    // `$name`, the value of an unquoted one is split into words
+    Var { name: String, quoted: bool },
+}
+
+type Word = Vec<Part>;
+
+enum Token {
+    Word(Word),
+    // `;` or a newline
+    Sep,
+}
+
+enum ParseError {
+    // the input ends in the middle of a command, more lines may complete it
+    Incomplete,
+    Syntax(String),
+}
+
+// split `src` into words and separators
+fn lex(src: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut word: Word = Vec::new();
+    let mut in_word = false;
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' | ';' => {
+                if in_word {
+                    tokens.push(Token::Word(core::mem::take(&mut word)));
+                    in_word = false;
+                }
+                if c == '\n' || c == ';' {
+                    tokens.push(Token::Sep);
+                }
+            }
+            '#' if !in_word => while chars.next_if(|&c| c != '\n').is_some() {},
+            '\'' => {
+                in_word = true;
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err(ParseError::Incomplete),
+                    }
+                }
+                push_lit(&mut word, &text, true);
+            }
+            '"' => {
+                in_word = true;
+                // an empty string is a word too
+                push_lit(&mut word, "", true);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('$' | '"' | '\\')) => push_char(&mut word, c, true),
+                            Some('\n') => {}
+                            Some(c) => {
+                                push_char(&mut word, '\\', true);
+                                push_char(&mut word, c, true);
+                            }
+                            None => return Err(ParseError::Incomplete),
+                        },
+                        Some('$') => lex_var(&mut chars, &mut word, true),
+                        Some(c) => push_char(&mut word, c, true),
+                        None => return Err(ParseError::Incomplete),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // the line goes on
+                Some('\n') => {}
+                Some(c) => {
+                    in_word = true;
+                    push_char(&mut word, c, true);
+                }
+                None => return Err(ParseError::Incomplete),
+            },
+            '$' => {
+                in_word = true;
+                lex_var(&mut chars, &mut word, false);
+            }
+            c => {
+                in_word = true;
+                push_char(&mut word, c, false);
+            }
+        }
+    }
+    if in_word {
+        tokens.push(Token::Word(word));
+    }
+    Ok(tokens)
+}
+
+// `$?`, `$1`, `$#`, `${name}` or `$name` after the `$`, which is just a `$` otherwise
+fn lex_var(chars: &mut core::iter::Peekable<core::str::Chars>, word: &mut Word, quoted: bool) {
+    let name = match chars.peek() {
+        Some(&c @ ('?' | '#' | '0'..='9')) => {
+            chars.next();
+            c.to_string()
+        }
+        Some('{') => {
+            chars.next();
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|&c| c != '}') {
+                name.push(c);
+            }
+            chars.next();
+            name
+        }
+        Some(&c) if c.is_ascii_alphabetic() || c == '_' => {
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '_') {
+                name.push(c);
+            }
+            name
+        }
+        _ => return push_char(word, '$', quoted),
+    };
+    word.push(Part::Var { name, quoted });
+}
+
+fn push_char(word: &mut Word, c: char, quoted: bool) {
+    let mut buf = [0u8; 4];
+    push_lit(word, c.encode_utf8(&mut buf), quoted);
+}
+
+// append to the last part if it is a literal quoted the same way
+fn push_lit(word: &mut Word, s: &str, quoted: bool) {
+    if let Some(Part::Lit { text, quoted: q }) = word.last_mut() {
+        if *q == quoted {
+            text.push_str(s);
+            return;
+        }
+    }
+    word.push(Part::Lit {
+        text: s.to_string(),
+        quoted,
+    });
+}
+
+enum Command {
+    // words, the leading `name=value` ones are assignments
+    Simple(Vec<Word>),
+    // the conditions and bodies of `if` and each `elif`, then the `else` body
+    If(Vec<(Vec<Command>, Vec<Command>)>, Vec<Command>),
+    While(Vec<Command>, Vec<Command>),
+    For(String, Vec<Word>, Vec<Command>),
+}
+
+const KEYWORDS: &[&str] = &[
+    "if", "then", "elif", "else", "fi", "while", "do", "done", "for",
+];
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+fn parse(src: &str) -> Result<Vec<Command>, ParseError> {
+    let mut parser = Parser {
+        tokens: lex(src)?,
+        pos: 0,
+    };
+    parser.parse_list(&[])
+}
+
+impl Parser {
+    // the keyword at the current token, if it is an unquoted one
+    fn keyword(&self) -> Option<&str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(word)) => match word.as_slice() {
+                [Part::Lit {
+                    text,
+                    quoted: false,
+                }] if KEYWORDS.contains(&text.as_str()) => Some(text.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, keyword: &str) -> Result<(), ParseError> {
+        match self.keyword() {
+            Some(found) if found == keyword => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ if self.pos == self.tokens.len() => Err(ParseError::Incomplete),
+            _ => Err(ParseError::Syntax(format!("expected `{}`", keyword))),
+        }
+    }
+
+    // commands up to one of `terminators`, which is left for the caller, or the end of the input
+    fn parse_list(&mut self, terminators: &[&str]) -> Result<Vec<Command>, ParseError> {
+        let mut commands = Vec::new();
+        loop {
+            while let Some(Token::Sep) = self.tokens.get(self.pos) {
+                self.pos += 1;
+            }
+            if self.pos == self.tokens.len() {
+                if terminators.is_empty() {
+                    return Ok(commands);
+                }
+                return Err(ParseError::Incomplete);
+            }
+            if self.keyword().is_some_and(|k| terminators.contains(&k)) {
+                return Ok(commands);
+            }
+            commands.push(self.parse_command()?);
+        }
+    }
+
+    fn parse_command(&mut self) -> Result<Command, ParseError> {
+        match self.keyword() {
+            Some("if") => {
+                self.pos += 1;
+                let mut branches = Vec::new();
+                loop {
+                    let condition = self.parse_list(&["then"])?;
+                    self.expect("then")?;
+                    let body = self.parse_list(&["elif", "else", "fi"])?;
+                    branches.push((condition, body));
+                    if self.keyword() == Some("elif") {
+                        self.pos += 1;
                     } else {
-                        let mut exit_code = 0;
-                        let exit_pid = waitpid(pid, &mut exit_code);
-                        assert_eq!(pid, exit_pid);
-                        println!("Shell: Process {} exited with code {}", pid, exit_code);
+                        break;
+                    }
+                }
+                let mut otherwise = Vec::new();
+                if self.keyword() == Some("else") {
+                    self.pos += 1;
+                    otherwise = self.parse_list(&["fi"])?;
+                }
+                self.expect("fi")?;
+                Ok(Command::If(branches, otherwise))
+            }
+            Some("while") => {
+                self.pos += 1;
+                let condition = self.parse_list(&["do"])?;
+                self.expect("do")?;
+                let body = self.parse_list(&["done"])?;
+                self.expect("done")?;
+                Ok(Command::While(condition, body))
+            }
+            Some("for") => {
+                self.pos += 1;
+                let var = match self.tokens.get(self.pos) {
+                    Some(Token::Word(word)) => match word.as_slice() {
+                        [Part::Lit {
+                            text,
+                            quoted: false,
+                        }] if is_name(text) => text.clone(),
+                        _ => return Err(ParseError::Syntax("bad for variable".into())),
+                    },
+                    _ => return Err(ParseError::Incomplete),
+                };
+                self.pos += 1;
+                let in_word = match self.tokens.get(self.pos) {
+                    Some(Token::Word(word)) => matches!(
+                        word.as_slice(),
+                        [Part::Lit { text, quoted: false }] if text == "in"
+                    ),
+                    Some(Token::Sep) => false,
+                    None => return Err(ParseError::Incomplete),
+                };
+                if !in_word {
+                    return Err(ParseError::Syntax("expected `in`".into()));
+                }
+                self.pos += 1;
+                let mut words = Vec::new();
+                while let Some(Token::Word(_)) = self.tokens.get(self.pos) {
+                    let Token::Word(word) =
+                        core::mem::replace(&mut self.tokens[self.pos], Token::Sep)
+                    else {
+                        unreachable!()
+                    };
+                    words.push(word);
+                    self.pos += 1;
+                }
+                while let Some(Token::Sep) = self.tokens.get(self.pos) {
+                    self.pos += 1;
+                }
+                self.expect("do")?;
+                let body = self.parse_list(&["done"])?;
+                self.expect("done")?;
+                Ok(Command::For(var, words, body))
+            }
+            Some(keyword) => Err(ParseError::Syntax(format!("unexpected `{}`", keyword))),
+            None => {
+                let mut words = Vec::new();
+                while let Some(Token::Word(_)) = self.tokens.get(self.pos) {
+                    let Token::Word(word) =
+                        core::mem::replace(&mut self.tokens[self.pos], Token::Sep)
+                    else {
+                        unreachable!()
+                    };
+                    words.push(word);
+                    self.pos += 1;
+                }
+                Ok(Command::Simple(words))
+            }
+        }
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct Var {
+    value: String,
+    // passed to the programs it runs
+    exported: bool,
+}
+
+struct Shell {
+    vars: BTreeMap<String, Var>,
+    // $0, $1, ...
+    args: Vec<String>,
+    // $?
+    status: i32,
+    editor: Editor,
+}
+
+impl Shell {
+    fn new(args: Vec<String>) -> Self {
+        let mut vars = BTreeMap::new();
+        for env in envs() {
+            if let Some((name, value)) = env.split_once('=') {
+                vars.insert(
+                    name.to_string(),
+                    Var {
+                        value: value.to_string(),
+                        exported: true,
+                    },
+                );
+            }
+        }
+        Self {
+            vars,
+            args,
+            status: 0,
+            editor: Editor {
+                history: Vec::new(),
+            },
+        }
+    }
+
+    fn interactive(&mut self) -> i32 {
+        // lines of a command that goes on
+        let mut pending = String::new();
+        let mut stdin = core::iter::from_fn(read_byte);
+        loop {
+            let prompt = if pending.is_empty() { "$ " } else { "> " };
+            let line = match self.editor.read_line(prompt, &mut stdin) {
+                Input::Line(line) => line,
+                Input::Interrupted => {
+                    pending.clear();
+                    self.status = 130;
+                    continue;
+                }
+                Input::Eof => return self.status,
+            };
+            pending.push_str(&line);
+            pending.push('\n');
+            match parse(&pending) {
+                Err(ParseError::Incomplete) => continue,
+                Err(ParseError::Syntax(msg)) => {
+                    eprintln!("syntax error: {}", msg);
+                    self.status = 2;
+                }
+                Ok(commands) => {
+                    INTERRUPTED.store(false, Ordering::Relaxed);
+                    self.run(&commands);
+                }
+            }
+            pending.clear();
+        }
+    }
+
+    // run a whole script
+    fn run_script(&mut self, src: &str) {
+        match parse(src) {
+            Ok(commands) => self.run(&commands),
+            Err(ParseError::Incomplete) => {
+                eprintln!("syntax error: unexpected end of file");
+                self.status = 2;
+            }
+            Err(ParseError::Syntax(msg)) => {
+                eprintln!("syntax error: {}", msg);
+                self.status = 2;
+            }
+        }
+    }
+
+    fn run(&mut self, commands: &[Command]) {
+        for command in commands {
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                self.status = 130;
+                return;
+            }
+            self.run_command(command);
+        }
+    }
+
+    fn run_command(&mut self, command: &Command) {
+        match command {
+            Command::Simple(words) => self.run_simple(words),
+            Command::If(branches, otherwise) => {
+                for (condition, body) in branches {
+                    self.run(condition);
+                    if self.status == 0 {
+                        self.run(body);
+                        return;
                     }
-                    line.clear();
                 }
-                print!("$ ");
+                self.status = 0;
+                self.run(otherwise);
             }
-            BS | DL => {
-                if !line.is_empty() {
-                    print!("{}", BS as char);
-                    print!(" ");
-                    print!("{}", BS as char);
-                    line.pop();
+            Command::While(condition, body) => {
+                let mut status = 0;
+                loop {
+                    self.run(condition);
+                    if self.status != 0 || INTERRUPTED.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    self.run(body);
+                    status = self.status;
                 }
+                self.status = status;
             }
-            _ => {
-                line.push(ch as char);
-                print!("{}", ch as char);
+            Command::For(var, words, body) => {
+                let values: Vec<String> = words.iter().flat_map(|w| self.expand(w)).collect();
+                self.status = 0;
+                for value in values {
+                    if INTERRUPTED.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    self.set_var(var, value, false);
+                    self.run(body);
+                }
             }
         }
     }
+
+    fn run_simple(&mut self, words: &[Word]) {
+        // leading `name=value` words set variables, only for the program if there is one
+        let mut assignments = Vec::new();
+        let mut rest = words;
+        while let Some((word, tail)) = rest.split_first() {
+            let Some((name, value)) = self.assignment(word) else {
+                break;
+            };
+            assignments.push((name, value));
+            rest = tail;
+        }
+        let argv: Vec<String> = rest.iter().flat_map(|word| self.expand(word)).collect();
+        if argv.is_empty() {
+            for (name, value) in assignments {
+                self.set_var(&name, value, false);
+            }
+            self.status = 0;
+            return;
+        }
+        self.status = match argv[0].as_str() {
+            "exit" => exit(match argv.get(1) {
+                Some(code) => code.parse().unwrap_or(2),
+                None => self.status,
+            }) as i32,
+            "echo" => {
+                let (newline, words) = match argv.get(1).map(String::as_str) {
+                    Some("-n") => (false, &argv[2..]),
+                    _ => (true, &argv[1..]),
+                };
+                print!("{}", words.join(" "));
+                if newline {
+                    println!();
+                }
+                0
+            }
+            "cd" => {
+                // the root directory is home as well
+                let dir = argv.get(1).map_or("/", String::as_str);
+                match Errno::from_ret(chdir(&format!("{}\0", dir))) {
+                    Ok(_) => 0,
+                    Err(errno) => {
+                        eprintln!("cd: {}: {}", dir, errno.name());
+                        1
+                    }
+                }
+            }
+            "pwd" => match getcwd() {
+                Ok(cwd) => {
+                    println!("{}", cwd);
+                    0
+                }
+                Err(errno) => {
+                    eprintln!("pwd: {}", errno.name());
+                    1
+                }
+            },
+            "export" => self.export(&argv[1..]),
+            "true" => 0,
+            "false" => 1,
+            "time" => {
+                let start = get_time();
+                self.status = 0;
+                if argv.len() > 1 {
+                    self.status = self.spawn(&argv[1..], &assignments);
+                }
+                let ms = get_time() - start;
+                write(
+                    STDERR,
+                    format!("real\t{}.{:03}s\n", ms / 1000, ms % 1000).as_bytes(),
+                );
+                self.status
+            }
+            "source" | "." => match argv.get(1) {
+                Some(path) => self.source(path),
+                None => {
+                    eprintln!("{}: filename argument required", argv[0]);
+                    2
+                }
+            },
+            _ => self.spawn(&argv, &assignments),
+        };
+    }
+
+    // `name=value`, with the value expanded but not split
+    fn assignment(&self, word: &Word) -> Option<(String, String)> {
+        let Some(Part::Lit {
+            text,
+            quoted: false,
+        }) = word.first()
+        else {
+            return None;
+        };
+        let (name, first) = text.split_once('=')?;
+        if !is_name(name) {
+            return None;
+        }
+        let mut value = first.to_string();
+        for part in &word[1..] {
+            match part {
+                Part::Lit { text, .. } => value.push_str(text),
+                Part::Var { name, .. } => value.push_str(&self.var(name)),
+            }
+        }
+        Some((name.to_string(), value))
+    }
+
+    // the words of `word` after expansion, the value of an unquoted variable is split on spaces
+    fn expand(&self, word: &Word) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        // a word with nothing but unquoted variables is no word if they are empty
+        let mut keep = false;
+        for part in word {
+            match part {
+                Part::Lit { text, .. } => {
+                    keep = true;
+                    fields.last_mut().unwrap().push_str(text);
+                }
+                Part::Var { name, quoted: true } => {
+                    keep = true;
+                    fields.last_mut().unwrap().push_str(&self.var(name));
+                }
+                Part::Var {
+                    name,
+                    quoted: false,
+                } => {
+                    let value = self.var(name);
+                    let mut pieces = value.split_whitespace();
+                    if let Some(first) = pieces.next() {
+                        fields.last_mut().unwrap().push_str(first);
+                    }
+                    fields.extend(pieces.map(|piece| piece.to_string()));
+                }
+            }
+        }
+        if !keep && fields.len() == 1 && fields[0].is_empty() {
+            fields.clear();
+        }
+        fields
+    }
+
+    fn var(&self, name: &str) -> String {
+        match name {
+            "?" => self.status.to_string(),
+            "#" => self.args.len().saturating_sub(1).to_string(),
+            _ => match name.parse::<usize>() {
+                Ok(i) => self.args.get(i).cloned().unwrap_or_default(),
+                Err(_) => self
+                    .vars
+                    .get(name)
+                    .map(|var| var.value.clone())
+                    .unwrap_or_default(),
+            },
+        }
+    }
+
+    fn set_var(&mut self, name: &str, value: String, export: bool) {
+        let var = self.vars.entry(name.to_string()).or_insert(Var {
+            value: String::new(),
+            exported: false,
+        });
+        var.value = value;
+        var.exported |= export;
+    }
+
+    // `export name=value` or `export name`, without names list the exported variables
+    fn export(&mut self, args: &[String]) -> i32 {
+        if args.is_empty() {
+            for (name, var) in self.vars.iter().filter(|(_, var)| var.exported) {
+                println!("export {}={}", name, var.value);
+            }
+            return 0;
+        }
+        let mut status = 0;
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if !is_name(name) {
+                eprintln!("export: `{}': not a valid identifier", arg);
+                status = 1;
+                continue;
+            }
+            let value = value.unwrap_or_else(|| self.var(name));
+            self.set_var(name, value, true);
+        }
+        status
+    }
+
+    // run the commands in the file at `path` in this shell
+    fn source(&mut self, path: &str) -> i32 {
+        let Ok(src) = read_file(&format!("{}\0", path)) else {
+            eprintln!("{}: cannot open", path);
+            return 1;
+        };
+        self.status = 0;
+        self.run_script(&String::from_utf8_lossy(&src));
+        self.status
+    }
+
+    // run the program `argv[0]` and wait for it, return its exit code
+    fn spawn(&self, argv: &[String], assignments: &[(String, String)]) -> i32 {
+        let args: Vec<String> = argv.iter().map(|arg| format!("{}\0", arg)).collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let mut env: BTreeMap<&str, &str> = self
+            .vars
+            .iter()
+            .filter(|(_, var)| var.exported)
+            .map(|(name, var)| (name.as_str(), var.value.as_str()))
+            .collect();
+        for (name, value) in assignments {
+            env.insert(name, value);
+        }
+        let env: Vec<String> = env
+            .iter()
+            .map(|(name, value)| format!("{}={}\0", name, value))
+            .collect();
+        let env: Vec<&str> = env.iter().map(String::as_str).collect();
+        // a name without a `/` is a program in the root directory, wherever the shell is
+        let path = match argv[0].contains('/') {
+            true => args[0].to_string(),
+            false => format!("/{}", args[0]),
+        };
+
+        let pid = fork();
+        if pid == 0 {
+            let errno = Errno::from_ret(exec(&path, &args, &env)).unwrap_err();
+            if errno == Errno::ENOENT {
+                eprintln!("{}: command not found", argv[0]);
+                exit(127);
+            }
+            eprintln!("{}: {}", argv[0], errno.name());
+            exit(126);
+        }
+        if pid < 0 {
+            eprintln!("fork: {}", Errno::from_ret(pid).unwrap_err().name());
+            return 1;
+        }
+        let mut exit_code = 0;
+        // Ctrl-C interrupts the wait, the program gets SIGINT as well
+        while waitpid(pid, &mut exit_code) == Errno::EINTR.as_ret() {}
+        exit_code
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    sigaction(SIGINT, Some(&SigAction::new(on_interrupt)), None);
+    let mut args: Vec<String> = args().map(|arg| arg.to_string()).collect();
+    match args.get(1).map(String::as_str) {
+        None => Shell::new(args).interactive(),
+        Some("-c") => {
+            let Some(command) = args.get(2).cloned() else {
+                eprintln!("-c: option requires an argument");
+                return 2;
+            };
+            // the arguments after the command are $0, $1, ...
+            args.drain(..3);
+            if args.is_empty() {
+                args.push("shell".into());
+            }
+            let mut shell = Shell::new(args);
+            shell.run_script(&command);
+            shell.status
+        }
+        Some(_) => {
+            args.remove(0);
+            let path = args[0].clone();
+            let mut shell = Shell::new(args);
+            shell.source(&path)
+        }
+    }
 }
@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::{close, dup, dup2, pipe, read_file, run_shell, write, write_file};
+
+/*
+ * the line editor of the shell moves the cursor, edits, recalls the history and completes
+ * names with the keys it reads from stdin, escape sequences included.
+ * expected: return 0
+*/
+
+// what is typed, one line after another
+const KEYS: &[&[u8]] = &[
+    // left twice
+    b"echo out:abc\x1b[D\x1b[DX\r",
+    // home and end
+    b"cho out:1\x1b[He\x1b[F2\r",
+    // Ctrl-A, then delete
+    b"xecho out:del\x01\x1b[3~\r",
+    // Ctrl-U drops what is before the cursor
+    b"garbage\x15echo out:u\r",
+    // backspace
+    b"echo out:abx\x7f\r",
+    b"echo out:first\r",
+    // up twice is the line before the last one
+    b"\x1b[A\x1b[A\r",
+    // down again is the new line
+    b"echo out:new\x1b[A\x1b[B\r",
+    // a command, then the common prefix of two files and one of them
+    b"ech\tout:tab\r",
+    b"cat edit_pa\to\t\r",
+    // an unknown sequence is ignored
+    b"echo out:esc\x1b[Z\r",
+    // Ctrl-D deletes the character under the cursor, and ends the input on an empty line
+    b"echo out:cd!\x1b[D\x04\r",
+    b"\x04",
+];
+
+// what the commands print, the lines of the editor never start with `out:`
+const OUTPUT: &[&str] = &[
+    "out:aXbc",
+    "out:12",
+    "out:del",
+    "out:u",
+    "out:ab",
+    "out:first",
+    "out:ab",
+    "out:new",
+    "out:tab",
+    "out:one",
+    "out:esc",
+    "out:cd",
+];
+
+#[no_mangle]
+fn main() -> i32 {
+    write_file("edit_pa_one\0", b"out:one\n");
+    write_file("edit_pa_two\0", b"out:two\n");
+
+    // all the keys fit in the pipe, so the shell finds them there once it starts
+    let mut fds = [0i32; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let (rx, tx) = (fds[0] as usize, fds[1] as usize);
+    for keys in KEYS {
+        assert_eq!(write(tx, keys), keys.len() as isize);
+    }
+    close(tx);
+    let stdin = dup(0);
+    assert!(stdin > 0);
+    dup2(rx, 0);
+    close(rx);
+    let exit_code = run_shell(&["shell\0"], "edit_out\0");
+    dup2(stdin as usize, 0);
+    close(stdin as usize);
+    assert_eq!(exit_code, 0);
+
+    let output = read_file("edit_out\0").unwrap();
+    let lines: Vec<&[u8]> = output
+        .split(|&b| b == b'\n')
+        .filter(|line| line.starts_with(b"out:"))
+        .collect();
+    assert_eq!(lines.len(), OUTPUT.len());
+    for (line, expected) in lines.iter().zip(OUTPUT) {
+        assert_eq!(*line, expected.as_bytes());
+    }
+    0
+}
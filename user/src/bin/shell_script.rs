@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{read_file, run_shell, write_file};
+
+/*
+ * the shell runs scripts with variables, $?, if/while/for, source, export and its builtins.
+ * expected: return 0
+*/
+
+const SCRIPT: &str = "# a comment
+name=world
+echo hello $name \"quoted  $name\" 'single $name' \\$name
+false
+echo status $?
+true; echo status $?
+no_such_program
+echo status $?
+if false; then
+    echo no
+elif true; then
+    echo elif
+else
+    echo no
+fi
+for i in 1 2 3; do echo -n $i; done
+echo
+list=\"a b\"
+for i in $list \"$list\"; do echo \"[$i]\"; done
+go=true
+while $go; do
+    go=false
+    echo once
+done
+. sh_test_lib
+echo $from_lib
+export KEY=value
+shell -c 'echo $KEY $0 $1' zero one
+KEY=other shell -c 'echo $KEY'
+echo args $# $1 $2
+time true
+exit 3
+";
+
+const OUTPUT: &[u8] = b"hello world quoted  world single $name $name
+status 1
+status 0
+shell: no_such_program: command not found
+status 127
+elif
+123
+[a]
+[b]
+[a b]
+once
+lib
+value zero one
+other
+args 2 x y
+";
+
+#[no_mangle]
+fn main() -> i32 {
+    write_file("sh_test_main\0", SCRIPT.as_bytes());
+    write_file("sh_test_lib\0", b"from_lib=lib\n");
+    assert_eq!(
+        run_shell(
+            &["shell\0", "sh_test_main\0", "x\0", "y\0"],
+            "sh_test_out\0"
+        ),
+        3
+    );
+    // `time` prints how long the command took on stderr last
+    let output = read_file("sh_test_out\0").unwrap();
+    assert_eq!(&output[..OUTPUT.len()], OUTPUT);
+    assert!(output[OUTPUT.len()..].starts_with(b"real\t"));
+
+    assert_eq!(
+        run_shell(&["shell\0", "-c\0", "exit 5\0"], "sh_test_out\0"),
+        5
+    );
+    // syntax errors
+    assert_eq!(run_shell(&["shell\0", "-c\0", "fi\0"], "sh_test_out\0"), 2);
+    assert_eq!(
+        run_shell(&["shell\0", "-c\0", "if true; then\0"], "sh_test_out\0"),
+        2
+    );
+    0
+}
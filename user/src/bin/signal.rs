@@ -0,0 +1,106 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, getpid, kill, sigaction,
+    signal::{
+        SigAction, SIGKILL, SIGSEGV, SIGTERM, SIGUSR1, SIGUSR2, SIG_BLOCK, SIG_IGN, SIG_SETMASK,
+        SIG_UNBLOCK,
+    },
+    sigprocmask, thread, waitpid, yield_, Errno,
+};
+
+/*
+ * signals run handlers, can be blocked by each thread and ignored, and kill by default.
+ * expected: return 0
+*/
+
+static CAUGHT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn count(signum: usize) {
+    CAUGHT.fetch_add(signum, Ordering::Relaxed);
+}
+
+extern "C" fn exit_on_fault(signum: usize) {
+    exit(signum as i32);
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = getpid();
+
+    // the handler runs before kill returns, and kill still returns 0
+    assert_eq!(sigaction(SIGUSR1, Some(&SigAction::new(count)), None), 0);
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(CAUGHT.load(Ordering::Relaxed), SIGUSR1);
+
+    let mut old = SigAction::default();
+    assert_eq!(sigaction(SIGUSR1, None, Some(&mut old)), 0);
+    assert_eq!(old.handler, count as usize);
+
+    // a blocked signal waits until it is unblocked
+    let set = 1 << (SIGUSR1 - 1);
+    assert_eq!(sigprocmask(SIG_BLOCK, Some(set), None), 0);
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(CAUGHT.load(Ordering::Relaxed), SIGUSR1);
+    let mut blocked = 0;
+    assert_eq!(sigprocmask(SIG_SETMASK, None, Some(&mut blocked)), 0);
+    assert_eq!(blocked, set);
+    assert_eq!(sigprocmask(SIG_UNBLOCK, Some(set), None), 0);
+    assert_eq!(CAUGHT.load(Ordering::Relaxed), 2 * SIGUSR1);
+
+    // a thread starts with the mask of its creator, and changes only its own
+    assert_eq!(sigprocmask(SIG_BLOCK, Some(set), None), 0);
+    let handle = thread::spawn(move || {
+        let mut blocked = 0;
+        assert_eq!(sigprocmask(SIG_SETMASK, Some(0), Some(&mut blocked)), 0);
+        (blocked == set) as i32
+    })
+    .unwrap();
+    assert_eq!(handle.join(), 1);
+    assert_eq!(sigprocmask(SIG_UNBLOCK, Some(set), Some(&mut blocked)), 0);
+    assert_eq!(blocked, set);
+
+    // an ignored signal is discarded
+    let ignore = SigAction {
+        handler: SIG_IGN,
+        ..Default::default()
+    };
+    assert_eq!(sigaction(SIGUSR2, Some(&ignore), None), 0);
+    assert_eq!(kill(pid, SIGUSR2), 0);
+
+    assert_eq!(
+        sigaction(SIGKILL, Some(&ignore), None),
+        Errno::EINVAL.as_ret()
+    );
+    assert_eq!(kill(pid, 65), Errno::EINVAL.as_ret());
+    assert_eq!(kill(12345, SIGTERM), Errno::ESRCH.as_ret());
+
+    // SIGTERM kills by default, which the wait status reports
+    let child = fork();
+    if child == 0 {
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(kill(child, SIGTERM), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 128 + SIGTERM as i32);
+
+    // a fault raises SIGSEGV, which can be caught
+    let child = fork();
+    if child == 0 {
+        sigaction(SIGSEGV, Some(&SigAction::new(exit_on_fault)), None);
+        unsafe {
+            core::ptr::null_mut::<u8>().write_volatile(0);
+        }
+        exit(-1);
+    }
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, SIGSEGV as i32);
+    0
+}
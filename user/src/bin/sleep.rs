@@ -4,16 +4,40 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{get_time, yield_};
+use user_lib::{args, sleep};
+
+/*
+ * sleep seconds: wait for the number of seconds, which may have a fraction like 0.5.
+*/
+
+// milliseconds in `s`, which is like `1` or `2.25`
+fn parse_ms(s: &str) -> Option<usize> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let whole: usize = match whole {
+        "" => 0,
+        whole => whole.parse().ok()?,
+    };
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // milliseconds are all there is to it
+    let ms = fraction
+        .bytes()
+        .chain(core::iter::repeat(b'0'))
+        .take(3)
+        .fold(0, |ms, digit| ms * 10 + (digit - b'0') as usize);
+    Some(whole * 1000 + ms)
+}
 
 #[no_mangle]
 fn main() -> i32 {
-    let current_timer = get_time();
-    println!("{}", current_timer);
-    let wait_for = current_timer + 3000;
-    while get_time() < wait_for {
-        yield_();
-    }
-    println!("Test sleep OK!");
+    let Some(ms) = args().nth(1).and_then(parse_ms) else {
+        eprintln!("usage: sleep seconds");
+        return 2;
+    };
+    sleep(ms);
     0
 }
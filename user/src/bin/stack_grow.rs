@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use core::hint::black_box;
+
+/*
+ * recurse with 1MiB of stack in total, far beyond the initial 8KiB stack.
+ * expected: return 0
+*/
+
+fn recurse(depth: usize) -> usize {
+    let frame = black_box([depth as u8; 4096]);
+    if depth == 0 {
+        return frame[0] as usize;
+    }
+    recurse(depth - 1) + frame[4095] as usize
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let depth = 256;
+    let expected: usize = (0..=depth).map(|d| d as u8 as usize).sum();
+    assert_eq!(recurse(depth), expected);
+    0
+}
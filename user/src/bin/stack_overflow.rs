@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::hint::black_box;
+
+fn recurse(depth: usize) -> usize {
+    let frame = black_box([depth as u8; 4096]);
+    // never true, the stack runs out long before
+    if depth == usize::MAX {
+        return 0;
+    }
+    recurse(depth + 1) + frame[0] as usize
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    println!("Into Test stack_overflow, we will recurse without end...");
+    println!("Kernel should kill this application!");
+    recurse(0) as i32
+}
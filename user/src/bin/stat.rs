@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{args, stat::stat};
+
+/*
+ * stat file..: show the status of the files.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut status = 0;
+    for path in args().skip(1) {
+        match stat(path) {
+            Ok(stat) => {
+                println!("  File: {}", path);
+                println!(
+                    "  Size: {:<10} Blocks: {:<10} IO Block: {:<6} {}",
+                    stat.size,
+                    stat.blocks,
+                    stat.blksize,
+                    stat.type_name()
+                );
+                println!("Inode: {:<11} Links: {}", stat.ino, stat.nlink);
+                println!(
+                    "Access: ({:04o}/{})",
+                    stat.mode & 0o7777,
+                    stat.mode_string()
+                );
+            }
+            Err(errno) => {
+                eprintln!("stat: {}: {}", path, errno.name());
+                status = 1;
+            }
+        }
+    }
+    status
+}
@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+ * write more memory than physically exists, the pages that do not fit are swapped out.
+ * expected: return 0
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x20000000;
+    let len: usize = 160 * 1024 * 1024;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    for addr in (start..start + len).step_by(4096) {
+        let ptr = addr as *mut usize;
+        unsafe {
+            *ptr = addr;
+        }
+    }
+    for addr in (start..start + len).step_by(4096) {
+        let ptr = addr as *const usize;
+        unsafe {
+            assert_eq!(*ptr, addr);
+        }
+    }
+    assert_eq!(munmap(start, len), 0);
+    0
+}
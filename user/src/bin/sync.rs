@@ -0,0 +1,101 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    sync::{Condvar, Mutex, Semaphore},
+    thread, yield_,
+};
+
+/*
+ * Mutex, Condvar and Semaphore keep threads in order.
+ * expected: return 0
+*/
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 50;
+
+static COUNTER: Mutex<usize> = Mutex::new(0);
+
+static QUEUE: Mutex<VecDeque<usize>> = Mutex::new(VecDeque::new());
+static NOT_EMPTY: Condvar = Condvar::new();
+static NOT_FULL: Condvar = Condvar::new();
+const CAPACITY: usize = 2;
+
+static SLOTS: Semaphore = Semaphore::new(2);
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+#[no_mangle]
+fn main() -> i32 {
+    // yielding in the critical section makes the others wait for the lock
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..ROUNDS {
+                    let mut counter = COUNTER.lock();
+                    let value = *counter;
+                    yield_();
+                    *counter = value + 1;
+                }
+                0
+            })
+            .unwrap()
+        })
+        .collect();
+    handles
+        .into_iter()
+        .for_each(|handle| assert_eq!(handle.join(), 0));
+    assert_eq!(*COUNTER.lock(), THREADS * ROUNDS);
+
+    // a consumer waits for items from a producer, which waits for room in the queue
+    let consumer = thread::spawn(|| {
+        let mut sum = 0;
+        for _ in 0..ROUNDS {
+            let mut queue = QUEUE.lock();
+            while queue.is_empty() {
+                queue = NOT_EMPTY.wait(queue);
+            }
+            sum += queue.pop_front().unwrap();
+            NOT_FULL.notify_one();
+        }
+        sum as i32
+    })
+    .unwrap();
+    for i in 0..ROUNDS {
+        let mut queue = QUEUE.lock();
+        while queue.len() == CAPACITY {
+            queue = NOT_FULL.wait(queue);
+        }
+        queue.push_back(i);
+        NOT_EMPTY.notify_one();
+    }
+    assert_eq!(consumer.join() as usize, (0..ROUNDS).sum::<usize>());
+
+    // at most 2 threads hold the semaphore at once
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..ROUNDS {
+                    SLOTS.acquire();
+                    assert!(ACTIVE.fetch_add(1, Ordering::Relaxed) < 2);
+                    yield_();
+                    ACTIVE.fetch_sub(1, Ordering::Relaxed);
+                    SLOTS.release();
+                }
+                0
+            })
+            .unwrap()
+        })
+        .collect();
+    handles
+        .into_iter()
+        .for_each(|handle| assert_eq!(handle.join(), 0));
+    assert!(SLOTS.try_acquire());
+    assert!(SLOTS.try_acquire());
+    assert!(!SLOTS.try_acquire());
+    0
+}
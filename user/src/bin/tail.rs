@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{args, read_input, write_all};
+
+/*
+ * tail [-n count] [file..]: print the last 10, or count, lines of the files, or of stdin.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut args = args().skip(1).peekable();
+    let mut count = 10;
+    if args.peek() == Some(&"-n") {
+        args.next();
+        match args.next().and_then(|n| n.parse().ok()) {
+            Some(n) => count = n,
+            None => {
+                eprintln!("usage: tail [-n count] [file..]");
+                return 2;
+            }
+        }
+    }
+    let mut paths: Vec<&str> = args.collect();
+    if paths.is_empty() {
+        paths.push("-");
+    }
+    let mut status = 0;
+    for (i, path) in paths.iter().enumerate() {
+        let data = match read_input(path) {
+            Ok(data) => data,
+            Err(errno) => {
+                eprintln!("tail: {}: {}", path, errno.name());
+                status = 1;
+                continue;
+            }
+        };
+        if paths.len() > 1 {
+            if i > 0 {
+                println!();
+            }
+            println!("==> {} <==", path);
+        }
+        // the newline of the last line does not start another one
+        let body = data.strip_suffix(b"\n").unwrap_or(&data);
+        let start = match count {
+            0 => data.len(),
+            _ => body
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, &b)| b == b'\n')
+                .nth(count - 1)
+                .map_or(0, |(i, _)| i + 1),
+        };
+        write_all(1, &data[start..]).ok();
+    }
+    status
+}
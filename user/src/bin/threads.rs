@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+extern crate user_lib;
+
+use alloc::{sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{fork, getpid, gettid, thread, waitpid, yield_};
+
+/*
+ * threads share memory and the pid, a thread's exit leaves the process running,
+ * and the process exits with all its threads.
+ * expected: return 0
+*/
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 100;
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = getpid();
+    assert_eq!(gettid(), pid);
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let handles: Vec<_> = (0..THREADS)
+        .map(|i| {
+            let counter = counter.clone();
+            thread::spawn(move || {
+                assert_eq!(getpid(), pid);
+                assert_ne!(gettid(), pid);
+                for _ in 0..ROUNDS {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    yield_();
+                }
+                i as i32 + 1
+            })
+            .unwrap()
+        })
+        .collect();
+    for i in 0..THREADS {
+        for j in 0..i {
+            assert_ne!(handles[i].tid(), handles[j].tid());
+        }
+    }
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join(), i as i32 + 1);
+    }
+    assert_eq!(counter.load(Ordering::Relaxed), THREADS * ROUNDS);
+
+    // returning from main exits the process, the other thread too
+    let child = fork();
+    if child == 0 {
+        thread::spawn(|| loop {
+            yield_();
+        })
+        .unwrap();
+        yield_();
+        return 7;
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 7);
+
+    // a fault in a thread kills the process
+    let child = fork();
+    if child == 0 {
+        thread::spawn(|| {
+            unsafe { core::ptr::null_mut::<u8>().write_volatile(0) };
+            0
+        })
+        .unwrap();
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(waitpid(child, &mut exit_code), child);
+    assert_eq!(exit_code, 139);
+    0
+}
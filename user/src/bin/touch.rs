@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{args, close, open, Errno, OpenFlags};
+
+/*
+ * touch file..: create the files that do not exist yet. Files have no times to update.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut status = 0;
+    for path in args().skip(1) {
+        match Errno::from_ret(open(path, OpenFlags::RDONLY | OpenFlags::CREATE)) {
+            Ok(fd) => {
+                close(fd);
+            }
+            Err(errno) => {
+                eprintln!("touch: {}: {}", path, errno.name());
+                status = 1;
+            }
+        }
+    }
+    status
+}
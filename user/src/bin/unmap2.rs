@@ -3,19 +3,32 @@
 
 extern crate user_lib;
 
-use user_lib::{mmap, munmap};
+use user_lib::{mmap, mmap_file, munmap, Errno, MmapFlags};
 
 /*
+ * munmap takes whole pages of user memory only, and mmap fails with ENOMEM without room.
  * expected: return 0
 */
 
+// the trap context of the main thread, and the code signal handlers return to
+const TRAP_CONTEXT: usize = 0usize.wrapping_sub(2 * 4096);
+const SIGRETURN_TRAMPOLINE: usize = 0x40_0000_0000 - 4096 * 2048 - 4096 * 16 - 4096;
+
 #[no_mangle]
 fn main() -> i32 {
     let start: usize = 0x10000000;
     let len: usize = 4096;
     let prot: usize = 3;
     assert_eq!(0, mmap(start, len, prot));
-    assert_eq!(munmap(start, len + 1), -1);
-    assert_eq!(munmap(start + 1, len - 1), -1);
+    assert_eq!(munmap(start, len + 1), Errno::EINVAL.as_ret());
+    assert_eq!(munmap(start + 1, len - 1), Errno::EINVAL.as_ret());
+    assert_eq!(munmap(start, 0), Errno::EINVAL.as_ret());
+    assert_eq!(munmap(TRAP_CONTEXT, len), Errno::EINVAL.as_ret());
+    assert_eq!(munmap(SIGRETURN_TRAMPOLINE, len), Errno::EINVAL.as_ret());
+    let flags = MmapFlags::PRIVATE | MmapFlags::ANONYMOUS;
+    assert_eq!(
+        mmap_file(0, 1 << 40, prot, flags, 0, 0),
+        Errno::ENOMEM.as_ret()
+    );
     0
 }
@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sysinfo, Errno, SysInfo};
+
+/*
+ * uptime: show how long the system has been up and how many processes there are.
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut info = SysInfo::default();
+    if let Err(errno) = Errno::from_ret(sysinfo(&mut info)) {
+        eprintln!("uptime: {}", errno.name());
+        return 1;
+    }
+    let secs = info.uptime;
+    println!(
+        "up {}:{:02}:{:02}, {} processes",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        info.procs
+    );
+    0
+}
@@ -6,22 +6,46 @@ extern crate user_lib;
 
 use user_lib::{exec, fork, waitpid};
 
-// name exit_code
+// name, exit code or 128 plus the signal that killed it
 const APPS: &[(&str, i32)] = &[
+    ("args\0", 0),
+    ("auxv\0", 0),
+    ("bad_address\0", 0),
+    ("bad_exec\0", 0),
+    ("coreutils\0", 0),
+    ("dirs\0", 0),
+    ("dup\0", 0),
+    ("errno\0", 0),
     ("file\0", 0),
+    ("fork_cow\0", 0),
     ("fork_test\0", 0),
     ("fork_test2\0", 0),
+    ("futex\0", 0),
+    ("heap_grow\0", 0),
+    ("linux_abi\0", 0),
     ("matrix\0", 0),
     ("mmap1\0", 0),
-    ("mmap2\0", -1),
+    ("mmap2\0", 139),
     ("mmap3\0", 0),
+    ("mmap4\0", 0),
+    ("mmap_file\0", 0),
+    ("pipe\0", 0),
     ("power_3\0", 0),
     ("power_5\0", 0),
     ("power_7\0", 0),
-    ("priv_csr\0", -1),
-    ("priv_inst\0", -1),
-    ("sleep\0", 0),
-    ("store_fault\0", -1),
+    ("priv_csr\0", 132),
+    ("priv_inst\0", 132),
+    ("shebang\0", 0),
+    ("shell_editor\0", 0),
+    ("shell_script\0", 0),
+    ("signal\0", 0),
+    ("sleep_test\0", 0),
+    ("stack_grow\0", 0),
+    ("stack_overflow\0", 139),
+    ("store_fault\0", 139),
+    ("swap\0", 0),
+    ("sync\0", 0),
+    ("threads\0", 0),
     ("unmap1\0", 0),
     ("unmap2\0", 0),
 ];
@@ -33,7 +57,7 @@ fn main() -> i32 {
         println!("[{}] starting running {}\n", file!(), app);
         let pid = fork();
         if pid == 0 {
-            exec(app);
+            exec(app, &[app], &[]);
             unreachable!();
         } else {
             let mut exit_code = 0;
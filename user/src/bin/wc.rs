@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{args, read_input};
+
+/*
+ * wc [-lwc] [file..]: count the lines, words and bytes of the files, or of stdin, with a total
+ * if there is more than one. -l, -w and -c pick the counts.
+*/
+
+fn count(data: &[u8]) -> [usize; 3] {
+    let lines = data.iter().filter(|&&b| b == b'\n').count();
+    let words = data
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|word| !word.is_empty())
+        .count();
+    [lines, words, data.len()]
+}
+
+fn report(counts: &[usize; 3], show: &[bool; 3], name: &str) {
+    for (count, _) in counts.iter().zip(show).filter(|(_, &show)| show) {
+        print!("{:>8}", count);
+    }
+    if name.is_empty() {
+        println!();
+    } else {
+        println!(" {}", name);
+    }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut show = [false; 3];
+    let mut paths = Vec::new();
+    for arg in args().skip(1) {
+        match arg.strip_prefix('-') {
+            Some(flags) if !flags.is_empty() => {
+                for flag in flags.chars() {
+                    match flag {
+                        'l' => show[0] = true,
+                        'w' => show[1] = true,
+                        'c' => show[2] = true,
+                        _ => {
+                            eprintln!("wc: unknown option -{}", flag);
+                            return 2;
+                        }
+                    }
+                }
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if show == [false; 3] {
+        show = [true; 3];
+    }
+    if paths.is_empty() {
+        // stdin has no name
+        return match read_input("-") {
+            Ok(data) => {
+                report(&count(&data), &show, "");
+                0
+            }
+            Err(errno) => {
+                eprintln!("wc: -: {}", errno.name());
+                1
+            }
+        };
+    }
+    let mut status = 0;
+    let mut total = [0; 3];
+    for path in paths.iter() {
+        match read_input(path) {
+            Ok(data) => {
+                let counts = count(&data);
+                for (total, count) in total.iter_mut().zip(counts) {
+                    *total += count;
+                }
+                report(&counts, &show, path);
+            }
+            Err(errno) => {
+                eprintln!("wc: {}: {}", path, errno.name());
+                status = 1;
+            }
+        }
+    }
+    if paths.len() > 1 {
+        report(&total, &show, "total");
+    }
+    status
+}
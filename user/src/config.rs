@@ -1,2 +1,4 @@
 pub const HEAP_ORDER_SIZE: usize = 32;
 pub const USER_HEAP_SIZE: usize = 0x4000;
+// how much the heap grows through brk at least when it runs out
+pub const USER_HEAP_GROW_SIZE: usize = 0x4000;
@@ -3,7 +3,9 @@ use core::fmt::{self, Write};
 
 const STDIN: usize = 0;
 const STDOUT: usize = 1;
+const STDERR: usize = 2;
 struct Stdout;
+struct Stderr;
 
 impl Write for Stdout {
     fn write_str(&mut self, fmt: &str) -> fmt::Result {
@@ -12,10 +14,21 @@ impl Write for Stdout {
     }
 }
 
+impl Write for Stderr {
+    fn write_str(&mut self, fmt: &str) -> fmt::Result {
+        write(STDERR, fmt.as_bytes());
+        Ok(())
+    }
+}
+
 pub fn print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+pub fn eprint(args: fmt::Arguments) {
+    Stderr.write_fmt(args).unwrap();
+}
+
 #[macro_export]
 macro_rules! print {
     ($($arg: tt)*) => {
@@ -34,6 +47,15 @@ macro_rules! println {
     };
 }
 
+// errors go to stderr, so that they don't mix with the output
+#[macro_export]
+#[allow_internal_unstable(format_args_nl)]
+macro_rules! eprintln {
+    ($($arg: tt)*) => {
+        $crate::console::eprint(format_args_nl!($($arg)*))
+    };
+}
+
 pub fn getchar() -> u8 {
     let mut ch = [0u8; 1];
     read(STDIN, &mut ch);
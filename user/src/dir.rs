@@ -0,0 +1,52 @@
+//! Reading directories with getdents64
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{close, open, syscall::sys_getdents64, Errno, OpenFlags};
+
+// `kind` of a DirEntry
+pub const DT_DIR: u8 = 4;
+pub const DT_REG: u8 = 8;
+
+/// An entry of a directory, `.` and `..` included
+pub struct DirEntry {
+    pub ino: u64,
+    pub kind: u8,
+    pub name: String,
+}
+
+impl DirEntry {
+    pub fn is_dir(&self) -> bool {
+        self.kind == DT_DIR
+    }
+}
+
+/// The entries of the directory at `path`, which is nul-terminated
+pub fn read_dir(path: &str) -> Result<Vec<DirEntry>, Errno> {
+    let fd = Errno::from_ret(open(path, OpenFlags::RDONLY))?;
+    let mut entries = Vec::new();
+    let mut buf = [0u8; 512];
+    let ret = loop {
+        let len = match Errno::from_ret(sys_getdents64(fd, &mut buf)) {
+            Ok(0) => break Ok(entries),
+            Ok(len) => len,
+            Err(errno) => break Err(errno),
+        };
+        // struct linux_dirent64: d_ino, d_off, d_reclen, d_type and the nul-terminated name
+        let mut pos = 0;
+        while pos < len {
+            let record = &buf[pos..];
+            let reclen = u16::from_ne_bytes([record[16], record[17]]) as usize;
+            let name = &record[19..reclen];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            entries.push(DirEntry {
+                ino: u64::from_ne_bytes(record[..8].try_into().unwrap()),
+                kind: record[18],
+                name: String::from_utf8_lossy(name).into(),
+            });
+            pos += reclen;
+        }
+    };
+    close(fd);
+    ret
+}
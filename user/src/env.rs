@@ -0,0 +1,77 @@
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+// argv and envp on the initial stack, set once in `_start`
+static ARGV: AtomicPtr<*const u8> = AtomicPtr::new(core::ptr::null_mut());
+static ENVP: AtomicPtr<*const u8> = AtomicPtr::new(core::ptr::null_mut());
+static AUXV: AtomicPtr<usize> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Find argv and envp on the initial stack laid out by the kernel: argc, argv[], envp[], auxv
+///
+/// # Safety
+/// `sp` must be the stack pointer the program started with
+pub(crate) unsafe fn init(sp: *const usize) {
+    let argc = *sp;
+    let argv = sp.add(1) as *mut *const u8;
+    let envp = argv.add(argc + 1);
+    ARGV.store(argv, Ordering::Relaxed);
+    ENVP.store(envp, Ordering::Relaxed);
+    let mut auxv = envp;
+    while !(*auxv).is_null() {
+        auxv = auxv.add(1);
+    }
+    AUXV.store(auxv.add(1) as *mut usize, Ordering::Relaxed);
+}
+
+/// Iterator over a NULL-terminated array of nul-terminated strings, such as argv.
+/// The strings are still nul-terminated in memory, so they can be passed to `exec` as they are.
+pub struct StrArray(*const *const u8);
+
+impl Iterator for StrArray {
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_null() {
+            return None;
+        }
+        let ptr = unsafe { *self.0 };
+        if ptr.is_null() {
+            return None;
+        }
+        self.0 = unsafe { self.0.add(1) };
+        let len = (0..).find(|&i| unsafe { *ptr.add(i) } == 0).unwrap();
+        let bytes = unsafe { core::slice::from_raw_parts(ptr, len) };
+        Some(core::str::from_utf8(bytes).unwrap())
+    }
+}
+
+/// The arguments of the program, the first is its name
+pub fn args() -> StrArray {
+    StrArray(ARGV.load(Ordering::Relaxed))
+}
+
+/// The environment of the program, as `KEY=value` strings
+pub fn envs() -> StrArray {
+    StrArray(ENVP.load(Ordering::Relaxed))
+}
+
+/// The value of the environment variable `key`
+pub fn env(key: &str) -> Option<&'static str> {
+    envs().find_map(|env| env.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// The value of the auxiliary vector entry of type `key`, such as AT_PAGESZ
+pub fn auxv(key: usize) -> Option<usize> {
+    let mut entry = AUXV.load(Ordering::Relaxed) as *const usize;
+    if entry.is_null() {
+        return None;
+    }
+    loop {
+        let (k, v) = unsafe { (*entry, *entry.add(1)) };
+        match k {
+            // AT_NULL
+            0 => return None,
+            _ if k == key => return Some(v),
+            _ => entry = unsafe { entry.add(2) },
+        }
+    }
+}
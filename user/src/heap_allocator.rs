@@ -1,8 +1,11 @@
-use crate::config::{HEAP_ORDER_SIZE, USER_HEAP_SIZE};
-use buddy_system_allocator::LockedHeap;
+use crate::config::{HEAP_ORDER_SIZE, USER_HEAP_GROW_SIZE, USER_HEAP_SIZE};
+use crate::sbrk;
+use buddy_system_allocator::{Heap, LockedHeapWithRescue};
+use core::alloc::Layout;
 
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap<HEAP_ORDER_SIZE> = LockedHeap::new();
+static HEAP_ALLOCATOR: LockedHeapWithRescue<HEAP_ORDER_SIZE> =
+    LockedHeapWithRescue::new(heap_rescue);
 
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 
@@ -14,6 +17,20 @@ pub fn init_heap() {
     }
 }
 
+// called when the heap runs out of space, grow it through brk
+fn heap_rescue(heap: &mut Heap<HEAP_ORDER_SIZE>, layout: &Layout) {
+    // buddy blocks are power-of-two sized and aligned, twice the block size always holds one
+    let block = layout.size().max(layout.align()).next_power_of_two();
+    let size = (block * 2).max(USER_HEAP_GROW_SIZE);
+    let start = sbrk(size as isize);
+    if start < 0 {
+        return;
+    }
+    unsafe {
+        heap.add_to_heap(start as usize, start as usize + size);
+    }
+}
+
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layout = {:?}", layout)
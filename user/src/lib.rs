@@ -10,16 +10,37 @@ extern crate alloc;
 
 pub mod config;
 pub mod console;
+pub mod dir;
+mod env;
+mod errno;
 mod heap_allocator;
 mod lang_items;
+pub mod signal;
+pub mod stat;
+pub mod sync;
 mod syscall;
+pub mod thread;
 
+use alloc::{string::String, vec::Vec};
 use bitflags::*;
+use core::{arch::global_asm, sync::atomic::AtomicU32};
+pub use env::{args, auxv, env, envs, StrArray};
+pub use errno::{errno, Errno};
+use signal::SigAction;
 use syscall::*;
 
-#[no_mangle]
-#[link_section = ".text.entry"]
-pub extern "C" fn _start() -> ! {
+// sp points at argc when the program starts, hand it to `rust_start` before it is moved
+global_asm!(
+    ".section .text.entry",
+    ".globl _start",
+    "_start:",
+    "    mv a0, sp",
+    "    call {rust_start}",
+    rust_start = sym rust_start,
+);
+
+extern "C" fn rust_start(sp: *const usize) -> ! {
+    unsafe { env::init(sp) };
     heap_allocator::init_heap();
     // heap_allocator::heap_test();
     // We don't need to clear bss, because the frame allocator will do that for us.
@@ -39,19 +60,103 @@ bitflags! {
         const RDONLY = 0;
         const WRONLY = 1 << 0;
         const RDWR = 1 << 1;
-        const CREATE = 1 << 9;
-        const TRUNC = 1 << 10;
+        const CREATE = 0o100;
+        const TRUNC = 0o1000;
+        const APPEND = 0o2000;
+        const NONBLOCK = 0o4000;
+        const CLOEXEC = 0o2000000;
+    }
+}
+
+// fcntl commands
+pub const F_DUPFD: usize = 0;
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const F_DUPFD_CLOEXEC: usize = 1030;
+// the descriptor flag of F_GETFD and F_SETFD
+pub const FD_CLOEXEC: usize = 1;
+
+bitflags! {
+    pub struct MmapFlags: u32 {
+        const SHARED = 0x01;
+        const PRIVATE = 0x02;
+        const FIXED = 0x10;
+        const ANONYMOUS = 0x20;
     }
 }
 
+// `dirfd` of the current directory
+const AT_FDCWD: isize = -100;
+// unlinkat removes a directory
+const AT_REMOVEDIR: usize = 0x200;
+
 pub fn open(path: &str, flags: OpenFlags) -> isize {
-    sys_open(path.as_ptr(), flags.bits())
+    sys_openat(AT_FDCWD, path.as_ptr(), flags.bits())
 }
 
 pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
 
+// the paths are nul-terminated, like those of `open`
+pub fn mkdir(path: &str) -> isize {
+    sys_mkdirat(AT_FDCWD, path.as_ptr(), 0o755)
+}
+
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path.as_ptr(), 0)
+}
+
+pub fn rmdir(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path.as_ptr(), AT_REMOVEDIR)
+}
+
+// move `old` to `new`, replacing the file that is there
+pub fn rename(old: &str, new: &str) -> isize {
+    sys_renameat2(AT_FDCWD, old.as_ptr(), AT_FDCWD, new.as_ptr(), 0)
+}
+
+// `path` is nul-terminated
+pub fn chdir(path: &str) -> isize {
+    sys_chdir(path.as_ptr())
+}
+
+/// The working directory
+pub fn getcwd() -> Result<String, Errno> {
+    // PATH_MAX
+    let mut buf = [0u8; 4096];
+    let len = Errno::from_ret(sys_getcwd(&mut buf))?;
+    // without the nul
+    Ok(String::from_utf8_lossy(&buf[..len - 1]).into())
+}
+
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+
+// make `newfd` refer to the file of `oldfd`, closing what it was before
+pub fn dup2(oldfd: usize, newfd: usize) -> isize {
+    if oldfd == newfd {
+        // dup3 takes no equal descriptors, but `oldfd` has to be valid
+        return match sys_fcntl(oldfd, F_GETFD, 0) {
+            ret if ret < 0 => ret,
+            _ => newfd as isize,
+        };
+    }
+    sys_dup3(oldfd, newfd, 0)
+}
+
+pub fn fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_fcntl(fd, cmd, arg)
+}
+
+// the read end goes to `pipe_fd[0]` and the write end to `pipe_fd[1]`
+pub fn pipe(pipe_fd: &mut [i32; 2]) -> isize {
+    sys_pipe2(pipe_fd, 0)
+}
+
 // current we only support buf.len() == 1
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
@@ -61,50 +166,263 @@ pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
 
+// write all of `buf`, even if it takes more than one write, as for a pipe
+pub fn write_all(fd: usize, mut buf: &[u8]) -> Result<(), Errno> {
+    while !buf.is_empty() {
+        let n = Errno::from_ret(write(fd, buf))?;
+        buf = &buf[n..];
+    }
+    Ok(())
+}
+
+// read `fd` until the end of the file
+pub fn read_to_end(fd: usize) -> Result<Vec<u8>, Errno> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        match Errno::from_ret(read(fd, &mut buf))? {
+            0 => return Ok(data),
+            n => data.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+// the contents of the file at `path`, which is nul-terminated
+pub fn read_file(path: &str) -> Result<Vec<u8>, Errno> {
+    let fd = Errno::from_ret(open(path, OpenFlags::RDONLY))?;
+    let data = read_to_end(fd);
+    close(fd);
+    data
+}
+
+// create or truncate the file at `path`, which is nul-terminated, and write `data` to it
+pub fn write_file(path: &str, data: &[u8]) {
+    let fd = open(
+        path,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, data), data.len() as isize);
+    close(fd as usize);
+}
+
+// run the shell with `argv` and stdout and stderr going to `out`, return its exit code
+pub fn run_shell(argv: &[&str], out: &str) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        let fd = open(
+            out,
+            OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+        );
+        assert!(fd > 0);
+        dup2(fd as usize, 1);
+        dup2(fd as usize, 2);
+        close(fd as usize);
+        exec("shell\0", argv, &[]);
+        exit(-1);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    exit_code
+}
+
+// like `read_file`, but `-` is stdin, as the programs that read files take it
+pub fn read_input(path: &str) -> Result<Vec<u8>, Errno> {
+    if path == "-" {
+        read_to_end(0)
+    } else {
+        read_file(path)
+    }
+}
+
+// exit the process, with all its threads
 pub fn exit(state: i32) -> isize {
-    sys_exit(state)
+    sys_exit_group(state)
 }
 
 pub fn yield_() -> isize {
     sys_yield()
 }
 
+const FUTEX_WAIT: usize = 0;
+const FUTEX_WAKE: usize = 1;
+const FUTEX_PRIVATE_FLAG: usize = 128;
+
+// sleep until `futex_wake` on `word`, unless it no longer holds `val` (EAGAIN)
+pub fn futex_wait(word: &AtomicU32, val: u32) -> isize {
+    sys_futex(word.as_ptr(), FUTEX_WAIT | FUTEX_PRIVATE_FLAG, val)
+}
+
+// wake up to `n` tasks waiting on `word`, return how many were woken
+pub fn futex_wake(word: &AtomicU32, n: u32) -> isize {
+    sys_futex(word.as_ptr(), FUTEX_WAKE | FUTEX_PRIVATE_FLAG, n)
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+// milliseconds since boot
 pub fn get_time() -> isize {
-    sys_get_time()
+    let mut tv = TimeVal::default();
+    sys_gettimeofday(&mut tv);
+    (tv.sec * 1000 + tv.usec / 1000) as isize
+}
+
+/// `struct sysinfo` of linux/sysinfo.h
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SysInfo {
+    // seconds since boot
+    pub uptime: i64,
+    pub loads: [u64; 3],
+    // the memory sizes are in units of `mem_unit` bytes
+    pub totalram: u64,
+    pub freeram: u64,
+    pub sharedram: u64,
+    pub bufferram: u64,
+    pub totalswap: u64,
+    pub freeswap: u64,
+    // the number of processes
+    pub procs: u16,
+    pub pad: u16,
+    pub totalhigh: u64,
+    pub freehigh: u64,
+    pub mem_unit: u32,
+}
+
+pub fn sysinfo(info: &mut SysInfo) -> isize {
+    sys_sysinfo(info)
 }
 
 pub fn getpid() -> isize {
     sys_getpid()
 }
 
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+
 pub fn fork() -> isize {
-    sys_fork()
+    // the signal for the parent when the child exits
+    sys_clone(signal::SIGCHLD)
 }
 
-pub fn exec(path: &str) -> isize {
-    sys_exec(path)
+// run the program at `path` with the arguments `argv` and the environment `envp`,
+// all of them nul-terminated
+pub fn exec(path: &str, argv: &[&str], envp: &[&str]) -> isize {
+    let null_terminated = |strings: &[&str]| -> Vec<*const u8> {
+        strings
+            .iter()
+            .map(|s| s.as_ptr())
+            .chain(core::iter::once(core::ptr::null()))
+            .collect()
+    };
+    sys_execve(path, &null_terminated(argv), &null_terminated(envp))
 }
 
 pub fn wait(exit_code: &mut i32) -> isize {
     waitpid(-1, exit_code)
 }
 
+// `exit_code` gets the code passed to `exit`, decoded from the wait status,
+// or 128 plus the signal number like shells report it if a signal killed the child
 pub fn waitpid(pid: isize, exit_code: &mut i32) -> isize {
-    sys_waitpid(pid, exit_code as *mut i32)
+    let ret = sys_wait4(pid, exit_code as *mut i32, 0);
+    if ret > 0 {
+        *exit_code = match *exit_code & 0x7f {
+            0 => (*exit_code >> 8) as i8 as i32,
+            signum => 128 + signum,
+        };
+    }
+    ret
+}
+
+pub fn kill(pid: isize, signum: usize) -> isize {
+    sys_kill(pid, signum)
+}
+
+// set the action of `signum`, and get the old one in `old`
+pub fn sigaction(signum: usize, action: Option<&SigAction>, old: Option<&mut SigAction>) -> isize {
+    sys_rt_sigaction(
+        signum,
+        action.map_or(core::ptr::null(), |action| action as *const SigAction),
+        old.map_or(core::ptr::null_mut(), |old| old as *mut SigAction),
+    )
+}
+
+// block (SIG_BLOCK), unblock (SIG_UNBLOCK) or set (SIG_SETMASK) the blocked signals,
+// and get the old ones in `old`
+pub fn sigprocmask(how: usize, set: Option<u64>, old: Option<&mut u64>) -> isize {
+    sys_rt_sigprocmask(
+        how,
+        set.as_ref()
+            .map_or(core::ptr::null(), |set| set as *const u64),
+        old.map_or(core::ptr::null_mut(), |old| old as *mut u64),
+    )
 }
 
 pub fn sleep(len_ms: usize) -> isize {
-    let start = sys_get_time();
-    while sys_get_time() < start + len_ms as isize {
+    let start = get_time();
+    while get_time() < start + len_ms as isize {
         sys_yield();
     }
     0
 }
 
+// anonymous private mapping at `start`, return 0 on success
 pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
-    sys_mmap(start, len, prot)
+    let flags = MmapFlags::PRIVATE | MmapFlags::ANONYMOUS | MmapFlags::FIXED;
+    let ret = sys_mmap(start, len, prot, flags.bits(), 0, 0);
+    if ret < 0 {
+        ret
+    } else {
+        0
+    }
+}
+
+// map `len` bytes of file `fd` from `offset`, return the start address of the mapping
+pub fn mmap_file(
+    start: usize,
+    len: usize,
+    prot: usize,
+    flags: MmapFlags,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    sys_mmap(start, len, prot, flags.bits(), fd, offset)
+}
+
+pub fn msync(start: usize, len: usize) -> isize {
+    sys_msync(start, len)
 }
 
 pub fn munmap(start: usize, len: usize) -> isize {
     sys_munmap(start, len)
 }
+
+// set the program break to `addr`, return 0 on success
+pub fn brk(addr: usize) -> isize {
+    if sys_brk(addr) as usize == addr {
+        0
+    } else {
+        -1
+    }
+}
+
+// move the program break by `increment`, return the old break or -1
+pub fn sbrk(increment: isize) -> isize {
+    let old = sys_brk(0);
+    if increment == 0 {
+        return old;
+    }
+    let new = (old + increment) as usize;
+    if sys_brk(new) as usize == new {
+        old
+    } else {
+        -1
+    }
+}
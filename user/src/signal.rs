@@ -0,0 +1,56 @@
+//! Signal numbers and `struct sigaction` of Linux, as the kernel takes them
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+
+/// The default action of a signal
+pub const SIG_DFL: usize = 0;
+/// Ignore a signal
+pub const SIG_IGN: usize = 1;
+
+// `how` of sigprocmask
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+/// Do not block the signal while its handler runs
+pub const SA_NODEFER: usize = 0x4000_0000;
+/// Reset the action to SIG_DFL once the handler is called
+pub const SA_RESETHAND: usize = 0x8000_0000;
+
+/// What to do with a signal: SIG_DFL, SIG_IGN or an `extern "C" fn(signum: usize)` handler,
+/// with the signals in `mask` blocked while the handler runs.
+/// A handler returns through a trampoline the kernel provides.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    /// bit `n - 1` for signal `n`
+    pub mask: u64,
+}
+
+impl SigAction {
+    pub fn new(handler: extern "C" fn(usize)) -> Self {
+        Self {
+            handler: handler as usize,
+            ..Default::default()
+        }
+    }
+}
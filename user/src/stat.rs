@@ -0,0 +1,112 @@
+//! The status of files and of the filesystems they are on
+
+use alloc::string::String;
+
+use crate::syscall::{sys_fstat, sys_newfstatat, sys_statfs};
+use crate::{Errno, AT_FDCWD};
+
+/// `struct stat` of asm-generic/stat.h
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    _pad: u64,
+    pub size: i64,
+    pub blksize: i32,
+    _pad2: i32,
+    pub blocks: i64,
+    pub atime_sec: i64,
+    pub atime_nsec: i64,
+    pub mtime_sec: i64,
+    pub mtime_nsec: i64,
+    pub ctime_sec: i64,
+    pub ctime_nsec: i64,
+    _unused: [u32; 2],
+}
+
+impl Stat {
+    // the file type bits of `mode`
+    pub const S_IFMT: u32 = 0o170000;
+    pub const S_IFIFO: u32 = 0o010000;
+    pub const S_IFCHR: u32 = 0o020000;
+    pub const S_IFDIR: u32 = 0o040000;
+    pub const S_IFREG: u32 = 0o100000;
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & Self::S_IFMT == Self::S_IFDIR
+    }
+
+    /// The type and the permissions like `ls -l` shows them, such as `-rwxr-xr-x`
+    pub fn mode_string(&self) -> String {
+        let kind = match self.mode & Self::S_IFMT {
+            Self::S_IFDIR => 'd',
+            Self::S_IFCHR => 'c',
+            Self::S_IFIFO => 'p',
+            _ => '-',
+        };
+        let mut s = String::from(kind);
+        for shift in [6, 3, 0] {
+            let bits = self.mode >> shift;
+            s.push(if bits & 4 != 0 { 'r' } else { '-' });
+            s.push(if bits & 2 != 0 { 'w' } else { '-' });
+            s.push(if bits & 1 != 0 { 'x' } else { '-' });
+        }
+        s
+    }
+
+    /// The file type in words
+    pub fn type_name(&self) -> &'static str {
+        match self.mode & Self::S_IFMT {
+            Self::S_IFDIR => "directory",
+            Self::S_IFCHR => "character special file",
+            Self::S_IFIFO => "fifo",
+            Self::S_IFREG => "regular file",
+            _ => "unknown",
+        }
+    }
+}
+
+/// The status of the file at `path`, which is nul-terminated
+pub fn stat(path: &str) -> Result<Stat, Errno> {
+    let mut stat = Stat::default();
+    Errno::from_ret(sys_newfstatat(AT_FDCWD, path.as_ptr(), &mut stat, 0))?;
+    Ok(stat)
+}
+
+/// The status of the open file `fd`
+pub fn fstat(fd: usize) -> Result<Stat, Errno> {
+    let mut stat = Stat::default();
+    Errno::from_ret(sys_fstat(fd, &mut stat))?;
+    Ok(stat)
+}
+
+/// `struct statfs` of asm-generic/statfs.h, with 64-bit words
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatFs {
+    pub fs_type: u64,
+    pub bsize: u64,
+    pub blocks: u64,
+    pub bfree: u64,
+    pub bavail: u64,
+    pub files: u64,
+    pub ffree: u64,
+    pub fsid: [i32; 2],
+    pub namelen: u64,
+    pub frsize: u64,
+    pub flags: u64,
+    pub spare: [u64; 4],
+}
+
+/// The status of the filesystem the file at `path` is on, `path` is nul-terminated
+pub fn statfs(path: &str) -> Result<StatFs, Errno> {
+    let mut statfs = StatFs::default();
+    Errno::from_ret(sys_statfs(path.as_ptr(), &mut statfs))?;
+    Ok(statfs)
+}
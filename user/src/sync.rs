@@ -0,0 +1,162 @@
+//! Blocking synchronization between threads on top of futexes
+
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{futex_wait, futex_wake};
+
+// states of a Mutex
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+// locked, and some thread may be waiting for it
+const CONTENDED: u32 = 2;
+
+/// A lock that puts the threads waiting for it to sleep
+pub struct Mutex<T> {
+    state: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+/// The lock of a Mutex, it is unlocked on drop
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(data: T) -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // whoever unlocks it next has to wake a waiter
+            while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+                futex_wait(&self.state, CONTENDED);
+            }
+        }
+        MutexGuard { mutex: self }
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            futex_wake(&self.state, 1);
+        }
+    }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+/// Wait for a condition guarded by a Mutex. A wait may return without a notify, so check the
+/// condition again after it.
+pub struct Condvar {
+    // changes with every notify, a waiter sleeps only if it has not changed since it unlocked
+    seq: AtomicU32,
+}
+
+impl Condvar {
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+        }
+    }
+
+    /// Unlock `guard`, sleep until a notify and lock it again
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let seq = self.seq.load(Ordering::Relaxed);
+        let mutex = guard.mutex;
+        drop(guard);
+        futex_wait(&self.seq, seq);
+        mutex.lock()
+    }
+
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        futex_wake(&self.seq, 1);
+    }
+
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        futex_wake(&self.seq, i32::MAX as u32);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A counting semaphore
+pub struct Semaphore {
+    count: AtomicU32,
+}
+
+impl Semaphore {
+    pub const fn new(count: u32) -> Self {
+        Self {
+            count: AtomicU32::new(count),
+        }
+    }
+
+    /// Take one, sleep while there is none
+    pub fn acquire(&self) {
+        loop {
+            let count = self.count.load(Ordering::Relaxed);
+            if count == 0 {
+                futex_wait(&self.count, 0);
+            } else if self
+                .count
+                .compare_exchange(count, count - 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Take one if there is one, without sleeping
+    pub fn try_acquire(&self) -> bool {
+        self.count
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |count| {
+                count.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// Give one back, waking a thread waiting for it
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        futex_wake(&self.count, 1);
+    }
+}
@@ -2,28 +2,56 @@
 
 use core::arch::asm;
 
+use crate::{
+    signal::SigAction,
+    stat::{Stat, StatFs},
+    SysInfo, TimeVal,
+};
+
 // https://github.com/torvalds/linux/blob/9b6de136b5f0158c60844f85286a593cb70fb364/include/uapi/asm-generic/unistd.h
 struct Syscall;
 
 impl Syscall {
-    const OPEN: usize = 56;
+    const GETCWD: usize = 17;
+    const DUP: usize = 23;
+    const DUP3: usize = 24;
+    const FCNTL: usize = 25;
+    const MKDIRAT: usize = 34;
+    const UNLINKAT: usize = 35;
+    const STATFS: usize = 43;
+    const CHDIR: usize = 49;
+    const OPENAT: usize = 56;
     const CLOSE: usize = 57;
+    const PIPE2: usize = 59;
+    const GETDENTS64: usize = 61;
     const READ: usize = 63;
     const WRITE: usize = 64;
+    const NEWFSTATAT: usize = 79;
+    const FSTAT: usize = 80;
     const EXIT: usize = 93;
-    const YIELD: usize = 124;
-    const GETTIME: usize = 169;
+    const EXIT_GROUP: usize = 94;
+    const FUTEX: usize = 98;
+    const SCHED_YIELD: usize = 124;
+    const KILL: usize = 129;
+    const RT_SIGACTION: usize = 134;
+    const RT_SIGPROCMASK: usize = 135;
+    const GETTIMEOFDAY: usize = 169;
     const GETPID: usize = 172;
-    const FORK: usize = 220;
-    const EXEC: usize = 221;
-    const WAITPID: usize = 260;
-    const MMAP: usize = 270;
-    const MUNMAP: usize = 271;
+    const GETTID: usize = 178;
+    const SYSINFO: usize = 179;
+    const BRK: usize = 214;
+    const MUNMAP: usize = 215;
+    const CLONE: usize = 220;
+    const EXECVE: usize = 221;
+    const MMAP: usize = 222;
+    const MSYNC: usize = 227;
+    const WAIT4: usize = 260;
+    const RENAMEAT2: usize = 276;
 }
 
-// a0-a2 for arguments, a7 for syscall id
-// return in a0
-fn syscall(id: usize, args: [usize; 3]) -> isize {
+// a0-a5 for arguments, a7 for syscall id
+// return in a0, a negated errno on failure
+fn syscall(id: usize, args: [usize; 6]) -> isize {
     let ret: isize;
     unsafe {
         asm!(
@@ -31,60 +59,258 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
             inlateout("a0") args[0] => ret,
             in("a1") args[1],
             in("a2") args[2],
+            in("a3") args[3],
+            in("a4") args[4],
+            in("a5") args[5],
             in("a7") id,
         )
     }
+    crate::errno::record(ret);
     ret
 }
 
-pub fn sys_open(path: *const u8, flags: u32) -> isize {
-    syscall(Syscall::OPEN, [path as usize, flags as usize, 0])
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(Syscall::DUP, [fd, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_dup3(oldfd: usize, newfd: usize, flags: u32) -> isize {
+    syscall(Syscall::DUP3, [oldfd, newfd, flags as usize, 0, 0, 0])
+}
+
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(Syscall::FCNTL, [fd, cmd, arg, 0, 0, 0])
+}
+
+pub fn sys_getcwd(buf: &mut [u8]) -> isize {
+    syscall(
+        Syscall::GETCWD,
+        [buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0, 0],
+    )
+}
+
+pub fn sys_chdir(path: *const u8) -> isize {
+    syscall(Syscall::CHDIR, [path as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, mode: u32) -> isize {
+    syscall(
+        Syscall::MKDIRAT,
+        [dirfd as usize, path as usize, mode as usize, 0, 0, 0],
+    )
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: usize) -> isize {
+    syscall(
+        Syscall::UNLINKAT,
+        [dirfd as usize, path as usize, flags, 0, 0, 0],
+    )
+}
+
+pub fn sys_statfs(path: *const u8, buf: *mut StatFs) -> isize {
+    syscall(Syscall::STATFS, [path as usize, buf as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_openat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    syscall(
+        Syscall::OPENAT,
+        [dirfd as usize, path as usize, flags as usize, 0, 0, 0],
+    )
 }
 
 pub fn sys_close(fd: usize) -> isize {
-    syscall(Syscall::CLOSE, [fd as usize, 0, 0])
+    syscall(Syscall::CLOSE, [fd as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_pipe2(pipefd: &mut [i32; 2], flags: u32) -> isize {
+    syscall(
+        Syscall::PIPE2,
+        [pipefd.as_mut_ptr() as usize, flags as usize, 0, 0, 0, 0],
+    )
+}
+
+pub fn sys_getdents64(fd: usize, buf: &mut [u8]) -> isize {
+    syscall(
+        Syscall::GETDENTS64,
+        [fd, buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0],
+    )
 }
 
 pub fn sys_read(fd: usize, buf: &mut [u8]) -> isize {
-    syscall(Syscall::READ, [fd, buf.as_mut_ptr() as usize, buf.len()])
+    syscall(
+        Syscall::READ,
+        [fd, buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0],
+    )
 }
 
 pub fn sys_write(fd: usize, buf: &[u8]) -> isize {
-    syscall(Syscall::WRITE, [fd, buf.as_ptr() as usize, buf.len()])
+    syscall(
+        Syscall::WRITE,
+        [fd, buf.as_ptr() as usize, buf.len(), 0, 0, 0],
+    )
+}
+
+pub fn sys_newfstatat(dirfd: isize, path: *const u8, statbuf: *mut Stat, flags: usize) -> isize {
+    syscall(
+        Syscall::NEWFSTATAT,
+        [dirfd as usize, path as usize, statbuf as usize, flags, 0, 0],
+    )
+}
+
+pub fn sys_fstat(fd: usize, statbuf: *mut Stat) -> isize {
+    syscall(Syscall::FSTAT, [fd, statbuf as usize, 0, 0, 0, 0])
 }
 
+// exit the calling thread
 pub fn sys_exit(state: i32) -> isize {
-    syscall(Syscall::EXIT, [state as usize, 0, 0])
+    syscall(Syscall::EXIT, [state as usize, 0, 0, 0, 0, 0])
+}
+
+// exit every thread of the process
+pub fn sys_exit_group(state: i32) -> isize {
+    syscall(Syscall::EXIT_GROUP, [state as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_futex(uaddr: *const u32, op: usize, val: u32) -> isize {
+    syscall(Syscall::FUTEX, [uaddr as usize, op, val as usize, 0, 0, 0])
 }
 
 pub fn sys_yield() -> isize {
-    syscall(Syscall::YIELD, [0, 0, 0])
+    syscall(Syscall::SCHED_YIELD, [0, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_kill(pid: isize, signum: usize) -> isize {
+    syscall(Syscall::KILL, [pid as usize, signum, 0, 0, 0, 0])
 }
 
-pub fn sys_get_time() -> isize {
-    syscall(Syscall::GETTIME, [0, 0, 0])
+// the kernel's sigset_t is 8 bytes
+pub fn sys_rt_sigaction(signum: usize, act: *const SigAction, oldact: *mut SigAction) -> isize {
+    syscall(
+        Syscall::RT_SIGACTION,
+        [signum, act as usize, oldact as usize, 8, 0, 0],
+    )
+}
+
+pub fn sys_rt_sigprocmask(how: usize, set: *const u64, oldset: *mut u64) -> isize {
+    syscall(
+        Syscall::RT_SIGPROCMASK,
+        [how, set as usize, oldset as usize, 8, 0, 0],
+    )
+}
+
+pub fn sys_gettimeofday(tv: *mut TimeVal) -> isize {
+    syscall(Syscall::GETTIMEOFDAY, [tv as usize, 0, 0, 0, 0, 0])
 }
 
 pub fn sys_getpid() -> isize {
-    syscall(Syscall::GETPID, [0, 0, 0])
+    syscall(Syscall::GETPID, [0, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_gettid() -> isize {
+    syscall(Syscall::GETTID, [0, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_fork() -> isize {
-    syscall(Syscall::FORK, [0, 0, 0])
+pub fn sys_sysinfo(info: *mut SysInfo) -> isize {
+    syscall(Syscall::SYSINFO, [info as usize, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_exec(path: &str) -> isize {
-    syscall(Syscall::EXEC, [path.as_ptr() as usize, 0, 0])
+pub fn sys_clone(flags: usize) -> isize {
+    syscall(Syscall::CLONE, [flags, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    syscall(Syscall::WAITPID, [pid as usize, exit_code_ptr as usize, 0])
+// Clone a thread running `entry(arg)` on `stack`, with the tid stored at `tid` by
+// CLONE_PARENT_SETTID and cleared by CLONE_CHILD_CLEARTID.
+// The new thread returns from the ecall with the registers of this one, so it can not return
+// into any Rust code: it jumps to `entry` right away.
+pub unsafe fn sys_clone_thread(
+    flags: usize,
+    stack: usize,
+    tid: *mut u32,
+    entry: extern "C" fn(usize) -> !,
+    arg: usize,
+) -> isize {
+    let ret: isize;
+    asm!(
+        "ecall",
+        "bnez a0, 1f",
+        "mv a0, a5",
+        "jr a6",
+        "1:",
+        inlateout("a0") flags as isize => ret,
+        in("a1") stack,
+        in("a2") tid,
+        in("a3") 0,
+        in("a4") tid,
+        in("a5") arg,
+        in("a6") entry as usize,
+        in("a7") Syscall::CLONE,
+    );
+    crate::errno::record(ret);
+    ret
+}
+
+pub fn sys_execve(path: &str, argv: &[*const u8], envp: &[*const u8]) -> isize {
+    syscall(
+        Syscall::EXECVE,
+        [
+            path.as_ptr() as usize,
+            argv.as_ptr() as usize,
+            envp.as_ptr() as usize,
+            0,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_wait4(pid: isize, wstatus: *mut i32, options: usize) -> isize {
+    syscall(
+        Syscall::WAIT4,
+        [pid as usize, wstatus as usize, options, 0, 0, 0],
+    )
 }
 
-pub fn sys_mmap(start: usize, len: usize, port: usize) -> isize {
-    syscall(Syscall::MMAP, [start, len, port])
+pub fn sys_renameat2(
+    olddirfd: isize,
+    oldpath: *const u8,
+    newdirfd: isize,
+    newpath: *const u8,
+    flags: usize,
+) -> isize {
+    syscall(
+        Syscall::RENAMEAT2,
+        [
+            olddirfd as usize,
+            oldpath as usize,
+            newdirfd as usize,
+            newpath as usize,
+            flags,
+            0,
+        ],
+    )
+}
+
+pub fn sys_mmap(
+    start: usize,
+    len: usize,
+    port: usize,
+    flags: u32,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    syscall(
+        Syscall::MMAP,
+        [start, len, port, flags as usize, fd, offset],
+    )
+}
+
+pub fn sys_brk(new_brk: usize) -> isize {
+    syscall(Syscall::BRK, [new_brk, 0, 0, 0, 0, 0])
 }
 
 pub fn sys_munmap(start: usize, len: usize) -> isize {
-    syscall(Syscall::MUNMAP, [start, len, 0])
+    syscall(Syscall::MUNMAP, [start, len, 0, 0, 0, 0])
+}
+
+pub fn sys_msync(start: usize, len: usize) -> isize {
+    syscall(Syscall::MSYNC, [start, len, 0, 0, 0, 0])
 }
@@ -0,0 +1,104 @@
+//! Threads sharing the address space of the process, created with clone like pthread_create
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{
+    mem::ManuallyDrop,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{
+    errno::Errno,
+    futex_wait,
+    syscall::{sys_clone_thread, sys_exit},
+};
+
+/// The stack of a thread, it does not grow
+pub const STACK_SIZE: usize = 4096 * 16;
+
+// the flags of clone pthread_create uses
+const CLONE_VM: usize = 0x100;
+const CLONE_FS: usize = 0x200;
+const CLONE_FILES: usize = 0x400;
+const CLONE_SIGHAND: usize = 0x800;
+const CLONE_THREAD: usize = 0x10000;
+const CLONE_SYSVSEM: usize = 0x40000;
+const CLONE_PARENT_SETTID: usize = 0x100000;
+const CLONE_CHILD_CLEARTID: usize = 0x200000;
+
+// shared by a thread and its JoinHandle
+struct Packet {
+    f: Option<Box<dyn FnOnce() -> i32 + Send>>,
+    exit_code: i32,
+    // the tid while the thread runs, the kernel clears it and wakes the futex once the thread has
+    // exited
+    tid: AtomicU32,
+}
+
+/// A thread started by `spawn`. Dropping it detaches the thread, whose stack is never freed then.
+pub struct JoinHandle {
+    packet: ManuallyDrop<Box<Packet>>,
+    stack: ManuallyDrop<Vec<u8>>,
+    tid: isize,
+}
+
+/// Run `f` in a new thread, `join` gives what it returns
+pub fn spawn<F>(f: F) -> Result<JoinHandle, Errno>
+where
+    F: FnOnce() -> i32 + Send + 'static,
+{
+    let mut packet = Box::new(Packet {
+        f: Some(Box::new(f)),
+        exit_code: 0,
+        tid: AtomicU32::new(0),
+    });
+    let stack = vec![0u8; STACK_SIZE];
+    let stack_top = (stack.as_ptr() as usize + STACK_SIZE) & !0xf;
+    let flags = CLONE_VM
+        | CLONE_FS
+        | CLONE_FILES
+        | CLONE_SIGHAND
+        | CLONE_THREAD
+        | CLONE_SYSVSEM
+        | CLONE_PARENT_SETTID
+        | CLONE_CHILD_CLEARTID;
+    let tid = packet.tid.as_ptr();
+    let arg = packet.as_mut() as *mut Packet as usize;
+    let tid =
+        Errno::from_ret(unsafe { sys_clone_thread(flags, stack_top, tid, thread_start, arg) })?;
+    Ok(JoinHandle {
+        packet: ManuallyDrop::new(packet),
+        stack: ManuallyDrop::new(stack),
+        tid: tid as isize,
+    })
+}
+
+extern "C" fn thread_start(packet: usize) -> ! {
+    let packet = unsafe { &mut *(packet as *mut Packet) };
+    let f = packet.f.take().unwrap();
+    packet.exit_code = f();
+    sys_exit(0);
+    unreachable!()
+}
+
+impl JoinHandle {
+    pub fn tid(&self) -> isize {
+        self.tid
+    }
+
+    /// Wait for the thread to exit, return what its closure returned
+    pub fn join(mut self) -> i32 {
+        loop {
+            let tid = self.packet.tid.load(Ordering::Acquire);
+            if tid == 0 {
+                break;
+            }
+            futex_wait(&self.packet.tid, tid);
+        }
+        let exit_code = self.packet.exit_code;
+        unsafe {
+            ManuallyDrop::drop(&mut self.packet);
+            ManuallyDrop::drop(&mut self.stack);
+        }
+        exit_code
+    }
+}